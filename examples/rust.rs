@@ -1,13 +1,12 @@
-use std::{
-    env,
-    sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
+use std::{env, sync::Arc};
+
+use github_db::{
+    GithubCredentials, GithubDb,
+    forge::{
+        Forge, OctocrabForge,
+        cassette::{Recorder, Replayer},
     },
-    time::Duration,
 };
-
-use github_db::{GithubCredentials, GithubDb};
 use tracing::level_filters::LevelFilter;
 
 #[tokio::main]
@@ -23,6 +22,8 @@ async fn main() {
 
     let app_ids = env::var("GITHUB_APP_ID").unwrap();
     let app_secrets = env::var("GITHUB_APP_SECRET").unwrap();
+    // optional, discussions are only synced with a token, which goes with the first app
+    let mut graphql_token = env::var("GITHUB_GRAPHQL_TOKEN").ok();
 
     let credentials = app_ids
         .split(";;")
@@ -30,33 +31,40 @@ async fn main() {
         .map(|(app_id, app_secret)| GithubCredentials {
             app_id: app_id.to_string(),
             app_secret: app_secret.to_string(),
+            graphql_token: graphql_token.take(),
         })
         .collect::<Vec<_>>();
 
+    // RECORD_CASSETTE=file records all responses, REPLAY_CASSETTE=file replays them
+    let forge: Arc<dyn Forge> = if let Ok(path) = env::var("REPLAY_CASSETTE") {
+        Arc::new(Replayer::open(path).unwrap())
+    } else if let Ok(path) = env::var("RECORD_CASSETTE") {
+        Arc::new(Recorder::create(Arc::new(OctocrabForge::new(&credentials)), path).unwrap())
+    } else {
+        Arc::new(OctocrabForge::new(&credentials))
+    };
+
     let gh = Arc::new(
-        GithubDb::new(
+        GithubDb::new_with_forge(
             env::var("DB_PATH").unwrap(),
-            &credentials,
+            forge,
             4000 * credentials.len(),
             &["rust-lang/rust"],
         )
         .await,
     );
 
-    let done = Arc::new(AtomicBool::new(false));
-
-    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    // with the metrics feature, METRICS_ADDR=0.0.0.0:9184 serves metrics for Prometheus
+    #[cfg(feature = "metrics")]
+    if let Ok(addr) = env::var("METRICS_ADDR") {
+        tokio::spawn(gh.clone().serve_metrics(addr));
+    }
 
-    tokio::spawn({
-        let done = done.clone();
-        async move {
+    let summary = gh
+        .run(async {
             tokio::signal::ctrl_c().await.unwrap();
-            done.store(true, Ordering::Relaxed);
-        }
-    });
+        })
+        .await;
 
-    while !done.load(Ordering::Relaxed) {
-        interval.tick().await;
-        gh.clone().update().await;
-    }
+    tracing::info!("{summary:?}");
 }
@@ -0,0 +1,255 @@
+//! Consistent copies of the database while it's being synced.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::Utc;
+use rusqlite::{
+    Connection,
+    backup::{Backup, StepResult},
+    params,
+};
+use tokio::task;
+
+use crate::{GithubDb, requests::Request};
+
+#[derive(Debug)]
+pub enum BackupError {
+    Io(io::Error),
+    Sqlite(String),
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::Io(e) => write!(f, "{e}"),
+            BackupError::Sqlite(e) => write!(f, "backup failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<io::Error> for BackupError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Sqlite(value.to_string())
+    }
+}
+
+/// Set with [`GithubDb::with_snapshots`].
+pub(crate) struct Snapshots {
+    pub(crate) dir: PathBuf,
+    pub(crate) every: Duration,
+    pub(crate) keep: usize,
+}
+
+/// A request that was taken from the queue to be handled.
+#[derive(Clone)]
+struct Running {
+    category: i64,
+    name: String,
+    data: Vec<u8>,
+    finished: bool,
+}
+
+/// Requests are deleted from the `Request` table when they're started, so a plain copy
+/// of the database would lose the ones being handled, and with them the rest of their
+/// listing. Backups put them back into the copy's queue.
+#[derive(Default)]
+pub(crate) struct RunningRequests {
+    requests: HashMap<i64, Running>,
+    /// Backups in progress. While there are any, finished requests are kept,
+    /// so a backup sees every request that ran while it was copying.
+    pins: usize,
+}
+
+impl RunningRequests {
+    pub(crate) fn started(
+        this: &Arc<Mutex<Self>>,
+        sequence_number: i64,
+        category: i64,
+        name: String,
+        data: Vec<u8>,
+    ) -> RunningGuard {
+        this.lock().unwrap().requests.insert(
+            sequence_number,
+            Running {
+                category,
+                name,
+                data,
+                finished: false,
+            },
+        );
+        RunningGuard {
+            requests: this.clone(),
+            sequence_number,
+        }
+    }
+
+    /// Whether a request with this name is being handled.
+    pub(crate) fn is_running(&self, name: &str) -> bool {
+        self.requests
+            .values()
+            .any(|request| request.name == name && !request.finished)
+    }
+
+    fn finished(&mut self, sequence_number: i64) {
+        if self.pins == 0 {
+            self.requests.remove(&sequence_number);
+        } else if let Some(request) = self.requests.get_mut(&sequence_number) {
+            request.finished = true;
+        }
+    }
+
+    /// Every request that ran between this and the previous call to `pin`.
+    fn unpin(&mut self) -> Vec<(i64, Running)> {
+        let requests = self
+            .requests
+            .iter()
+            .map(|(sequence_number, request)| (*sequence_number, request.clone()))
+            .collect();
+        self.pins -= 1;
+        if self.pins == 0 {
+            self.requests.retain(|_, request| !request.finished);
+        }
+        requests
+    }
+}
+
+/// Marks a request as finished when dropped, also when its handler panicked or was aborted.
+pub(crate) struct RunningGuard {
+    requests: Arc<Mutex<RunningRequests>>,
+    sequence_number: i64,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.finished(self.sequence_number);
+        }
+    }
+}
+
+impl GithubDb {
+    /// Write a consistent copy of the database to `path` with SQLite's
+    /// [online backup](https://sqlite.org/backup.html), without pausing syncing.
+    ///
+    /// Requests that were being handled at that moment are put back in the copy's queue,
+    /// so syncing a restored copy continues every listing where this one was.
+    /// To restore, copy the file to the database path while nothing has it open.
+    ///
+    /// The copy is written next to `path` and then renamed, so `path` is never half-written.
+    pub async fn backup_to(&self, path: impl AsRef<Path>) -> Result<(), BackupError> {
+        let path = path.as_ref().to_path_buf();
+        let db = self.raw_db.clone();
+        let running = self.running.clone();
+
+        task::spawn_blocking(move || {
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+            if tmp.exists() {
+                fs::remove_file(&tmp)?;
+            }
+
+            running.lock().unwrap().pins += 1;
+            let copy = copy(&db.rusqlite_connection(), &tmp);
+            let requests = running.lock().unwrap().unpin();
+
+            let copy = copy?;
+            let requeued = requeue(&copy, requests)?;
+            // a single file, without `-wal` next to it
+            copy.pragma_update(None, "journal_mode", "DELETE")?;
+            copy.close().map_err(|(_, e)| e)?;
+            fs::rename(&tmp, &path)?;
+
+            tracing::info!(
+                "backed up to {}, requeued {requeued} running requests",
+                path.display()
+            );
+            Ok(())
+        })
+        .await
+        .expect("backup panicked")
+    }
+
+    /// Write a snapshot to the [snapshot directory](GithubDb::with_snapshots)
+    /// and remove the oldest ones that are too many.
+    pub(crate) async fn snapshot(&self) -> Result<PathBuf, BackupError> {
+        let Snapshots { dir, keep, .. } =
+            self.snapshots.as_ref().expect("snapshots are configured");
+        fs::create_dir_all(dir)?;
+
+        // sorting the names sorts them by time
+        let name = Utc::now().format("snapshot-%Y%m%dT%H%M%S%.3fZ.sqlite");
+        let path = dir.join(name.to_string());
+        self.backup_to(&path).await?;
+
+        let mut snapshots: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("snapshot-") && name.ends_with(".sqlite"))
+            .collect();
+        snapshots.sort();
+        for old in snapshots.iter().rev().skip(*keep) {
+            fs::remove_file(dir.join(old))?;
+        }
+        Ok(path)
+    }
+}
+
+fn copy(db: &Connection, to: &Path) -> rusqlite::Result<Connection> {
+    let mut copy = Connection::open(to)?;
+    let backup = Backup::new(db, &mut copy)?;
+    // all pages in a single step, which reads from one consistent snapshot of the source
+    while backup.step(-1)? != StepResult::Done {
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(backup);
+    Ok(copy)
+}
+
+/// Add the running requests that the copy's queue doesn't continue already.
+fn requeue(copy: &Connection, mut running: Vec<(i64, Running)>) -> rusqlite::Result<usize> {
+    let mut queued: Vec<Request> = copy
+        .prepare("SELECT data FROM request")?
+        .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+        .filter_map(|data| serde_json::from_slice(&data.ok()?).ok())
+        .collect();
+
+    // the newest request of a listing is the furthest along
+    running.sort_by_key(|(sequence_number, _)| -sequence_number);
+    let mut insert = copy.prepare(
+        "INSERT INTO request (category, data, name, sequence_number) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    let mut requeued = 0;
+    for (sequence_number, request) in running {
+        let Ok(parsed) = serde_json::from_slice::<Request>(&request.data) else {
+            continue;
+        };
+        if queued.iter().any(|q| q.listing() == parsed.listing()) {
+            continue;
+        }
+        insert.execute(params![
+            request.category,
+            request.data,
+            request.name,
+            sequence_number
+        ])?;
+        queued.push(parsed);
+        requeued += 1;
+    }
+    Ok(requeued)
+}
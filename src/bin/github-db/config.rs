@@ -0,0 +1,135 @@
+//! Settings from the config file and the environment.
+//!
+//! ```toml
+//! database = "github.sqlite"
+//! repos = ["rust-lang/rust", "rust-lang/cargo"]
+//! # defaults to 4000 per app
+//! requests_per_hour = 8000
+//! # only with the metrics feature
+//! metrics_addr = "0.0.0.0:9184"
+//! # accounts that are stored as bots even though GitHub says they're users
+//! bots = ["bors", "rust-highfive"]
+//!
+//! # optional, a backup every `interval_minutes` while syncing, the newest `keep` are kept
+//! [snapshots]
+//! dir = "snapshots"
+//! interval_minutes = 360
+//! keep = 4
+//!
+//! [[apps]]
+//! id = "..."
+//! secret = "..."
+//! # optional, GraphQL doesn't accept the id and secret, so discussions need a token
+//! graphql_token = "..."
+//! ```
+//!
+//! `GITHUB_APP_ID` and `GITHUB_APP_SECRET` override the apps in the file,
+//! several apps are separated by `;;`. `GITHUB_GRAPHQL_TOKEN` has the tokens of the
+//! first apps, separated the same way.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use github_db::GithubCredentials;
+use serde::Deserialize;
+
+/// Used when no config file is given and it exists.
+const DEFAULT_PATH: &str = "github-db.toml";
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub database: Option<PathBuf>,
+    #[serde(default)]
+    pub repos: Vec<String>,
+    pub requests_per_hour: Option<usize>,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub metrics_addr: Option<String>,
+    pub snapshots: Option<Snapshots>,
+    #[serde(default)]
+    pub bots: Vec<String>,
+    #[serde(default)]
+    apps: Vec<App>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snapshots {
+    pub dir: PathBuf,
+    pub interval_minutes: u64,
+    pub keep: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct App {
+    id: String,
+    secret: String,
+    graphql_token: Option<String>,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_PATH).exists() => Path::new(DEFAULT_PATH),
+            None => return Ok(Self::default()),
+        };
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {e}", path.display()))?;
+        toml::from_str(&text).map_err(|e| format!("invalid config {}: {e}", path.display()))
+    }
+
+    pub fn credentials(&self) -> Result<Vec<GithubCredentials>, String> {
+        if let (Ok(ids), Ok(secrets)) = (env::var("GITHUB_APP_ID"), env::var("GITHUB_APP_SECRET")) {
+            let ids: Vec<_> = ids.split(";;").collect();
+            let secrets: Vec<_> = secrets.split(";;").collect();
+            if ids.len() != secrets.len() {
+                return Err(format!(
+                    "got {} app ids but {} app secrets",
+                    ids.len(),
+                    secrets.len()
+                ));
+            }
+            let tokens = env::var("GITHUB_GRAPHQL_TOKEN").unwrap_or_default();
+            let mut tokens: Vec<_> = tokens.split(";;").collect();
+            if tokens.len() > ids.len() {
+                return Err(format!(
+                    "got {} GraphQL tokens but only {} apps",
+                    tokens.len(),
+                    ids.len()
+                ));
+            }
+            tokens.resize(ids.len(), "");
+            return Ok(ids
+                .into_iter()
+                .zip(secrets)
+                .zip(tokens)
+                .map(|((app_id, app_secret), token)| GithubCredentials {
+                    app_id: app_id.to_string(),
+                    app_secret: app_secret.to_string(),
+                    graphql_token: (!token.is_empty()).then(|| token.to_string()),
+                })
+                .collect());
+        }
+
+        if self.apps.is_empty() {
+            return Err(
+                "no credentials, set GITHUB_APP_ID and GITHUB_APP_SECRET or add [[apps]] to the config file"
+                    .to_string(),
+            );
+        }
+        Ok(self
+            .apps
+            .iter()
+            .map(|app| GithubCredentials {
+                app_id: app.id.clone(),
+                app_secret: app.secret.clone(),
+                graphql_token: app.graphql_token.clone(),
+            })
+            .collect())
+    }
+}
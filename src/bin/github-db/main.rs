@@ -0,0 +1,498 @@
+//! Command-line interface to a github-db database, see `github-db --help`.
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use github_db::{
+    Filter, GithubDb, ItemKind, Repo, SearchFilters,
+    export::{ExportFormat, ExportOptions},
+    forge::{Forge, ForgeError, ForgePage, ListType, async_trait},
+    import::ImportSummary,
+};
+use octocrab::models::{
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::Config;
+
+mod config;
+
+/// Requests per hour that a single GitHub app may make.
+const REQUESTS_PER_APP: usize = 4000;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Mirror GitHub issues, pull requests and comments into SQLite"
+)]
+struct Cli {
+    /// Config file, `github-db.toml` is used if it exists
+    #[arg(long, global = true, env = "GITHUB_DB_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// The database, overrides `database` in the config file
+    #[arg(long, global = true, env = "DB_PATH")]
+    db: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Keep the database up to date until interrupted with ctrl-c
+    Sync {
+        /// Repositories to sync, like `rust-lang/rust`, overrides `repos` in the config file
+        #[arg(long = "repo")]
+        repos: Vec<String>,
+    },
+    /// Show what's in the database and how far syncing has gotten
+    Stats,
+    /// Inspect and manage the pending requests
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommand,
+    },
+    /// Full-text search through titles, descriptions and comments
+    Search(SearchArgs),
+    /// List issues and pull requests matching a filter like `is:pr is:open label:T-compiler`
+    Find {
+        filter: String,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Write every table to a file in a directory, for pandas, DuckDB and the like
+    Export {
+        dir: PathBuf,
+        #[arg(long, default_value = "jsonl")]
+        format: Format,
+        /// Only issues, pull requests and comments updated since this date or RFC 3339 timestamp
+        #[arg(long, value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Load offline dumps, syncing afterwards only has to fill the gaps
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+    /// Copy the database to a file, also while it's being synced
+    Backup { path: PathBuf },
+    /// Create the database or bring it up to the latest schema version
+    Migrate,
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    /// A directory written by `export --format jsonl`
+    Dump { dir: PathBuf },
+    /// GH Archive files with one event per line, gzipped if they end in `.gz`
+    Archive {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Only events of these repositories, like `rust-lang/rust`
+        #[arg(long = "repo")]
+        repos: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// Show pending requests in the order they were queued
+    List {
+        #[arg(long)]
+        priority: Option<Priority>,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Remove requests by their sequence number
+    Remove { sequence_numbers: Vec<i64> },
+    /// Remove all pending requests, or only those of one priority
+    Clear {
+        #[arg(long)]
+        priority: Option<Priority>,
+    },
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// In FTS5 syntax, like `borrowck ICE` or `"exact phrase"`
+    query: String,
+    /// Only results in this repository, like `rust-lang/rust`
+    #[arg(long)]
+    repo: Option<String>,
+    #[arg(long)]
+    kind: Option<Kind>,
+    /// Only open issues and pull requests
+    #[arg(long, conflicts_with = "closed")]
+    open: bool,
+    /// Only closed issues and pull requests
+    #[arg(long)]
+    closed: bool,
+    /// Don't search through comments
+    #[arg(long)]
+    no_comments: bool,
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Priority {
+    Update,
+    Index,
+    Comments,
+}
+
+impl From<Priority> for github_db::Priority {
+    fn from(value: Priority) -> Self {
+        match value {
+            Priority::Update => Self::Update,
+            Priority::Index => Self::Index,
+            Priority::Comments => Self::Comments,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Jsonl,
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl From<Format> for ExportFormat {
+    fn from(value: Format) -> Self {
+        match value {
+            Format::Jsonl => Self::Jsonl,
+            Format::Csv => Self::Csv,
+            #[cfg(feature = "parquet")]
+            Format::Parquet => Self::Parquet,
+        }
+    }
+}
+
+fn parse_since(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.to_utc())
+        .map_err(|_| "expected `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SSZ`".to_string())
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Issue,
+    Pr,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let cli = Cli::parse();
+
+    tracing_subscriber::fmt()
+        .with_target(false)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let config = Config::load(cli.config.as_deref())?;
+    let db_path = cli
+        .db
+        .or(config.database.clone())
+        .ok_or("no database, pass --db, set DB_PATH or add `database` to the config file")?;
+
+    let command = match cli.command {
+        Command::Migrate => {
+            github_db::schema::migrate(&db_path);
+            println!("{} is up to date", db_path.display());
+            return Ok(());
+        }
+        Command::Sync { repos } => {
+            let repos = if repos.is_empty() {
+                config.repos.clone()
+            } else {
+                repos
+            };
+            return sync(&config, db_path, repos).await;
+        }
+        command => command,
+    };
+
+    // everything else works on a database that `migrate` or `sync` already brought up to date
+    let gh = GithubDb::open_with_forge(db_path, Arc::new(Offline), 0, &[])
+        .await
+        .map_err(|e| e.to_string())?;
+    match command {
+        Command::Stats => stats(&gh).await,
+        Command::Queue { command } => queue(&gh, command).await,
+        Command::Search(args) => search(&gh, args).await?,
+        Command::Find { filter, limit } => {
+            let filter = Filter::parse(&filter).map_err(|e| e.to_string())?;
+            for item in gh.find(&filter, limit).await {
+                println!(
+                    "{:?}#{:<7} {:<5} {:<6} {:<20} {}",
+                    item.repo,
+                    item.number,
+                    kind(item.kind),
+                    if item.open { "open" } else { "closed" },
+                    item.author,
+                    item.title
+                );
+            }
+        }
+        Command::Export { dir, format, since } => {
+            let options = ExportOptions {
+                format: format.into(),
+                since,
+            };
+            let summary = gh.export(dir, options).await.map_err(|e| e.to_string())?;
+            for (path, rows) in summary.files {
+                println!("{rows:>9} {}", path.display());
+            }
+        }
+        Command::Import { command } => import(&gh, command).await?,
+        Command::Backup { path } => {
+            gh.backup_to(&path).await.map_err(|e| e.to_string())?;
+            println!("wrote {}", path.display());
+        }
+        Command::Migrate | Command::Sync { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+async fn sync(config: &Config, db_path: PathBuf, repos: Vec<String>) -> Result<(), String> {
+    if repos.is_empty() {
+        return Err("no repositories, pass --repo or add `repos` to the config file".to_string());
+    }
+    for repo in &repos {
+        parse_repo(repo)?;
+    }
+    let credentials = config.credentials()?;
+    let requests_per_hour = config
+        .requests_per_hour
+        .unwrap_or(REQUESTS_PER_APP * credentials.len());
+
+    let repos: Vec<&str> = repos.iter().map(String::as_str).collect();
+    let bots: Vec<&str> = config.bots.iter().map(String::as_str).collect();
+    let mut gh = GithubDb::new(db_path, &credentials, requests_per_hour, &repos)
+        .await
+        .with_bots(&bots);
+    if let Some(snapshots) = &config.snapshots {
+        gh = gh.with_snapshots(
+            &snapshots.dir,
+            Duration::from_secs(snapshots.interval_minutes * 60),
+            snapshots.keep,
+        );
+    }
+    let gh = Arc::new(gh);
+
+    #[cfg(feature = "metrics")]
+    if let Some(addr) = config.metrics_addr.clone() {
+        tokio::spawn(gh.clone().serve_metrics(addr));
+    }
+
+    let summary = gh
+        .run(async {
+            tokio::signal::ctrl_c().await.unwrap();
+        })
+        .await;
+    tracing::info!("{summary:?}");
+    Ok(())
+}
+
+async fn stats(gh: &GithubDb) {
+    let stats = gh.stats().await;
+    println!("pull requests  {}", stats.prs);
+    println!("issues         {}", stats.issues);
+    println!("users          {}", stats.users);
+    println!("comments       {}", stats.comments);
+    println!("labels         {}", stats.labels);
+    println!();
+
+    println!("queued requests {}", stats.pending_requests());
+    for (priority, n) in &stats.queue {
+        println!("  {priority:<10?} {n}");
+    }
+
+    let progress = gh.progress().await;
+    if !progress.listings.is_empty() {
+        println!();
+        println!("indexing");
+    }
+    for listing in &progress.listings {
+        let total = listing
+            .total_pages
+            .map_or("?".to_string(), |n| n.to_string());
+        println!(
+            "  {} {:?}: page {}/{}, {} complete passes",
+            listing.repo, listing.listing, listing.page, total, listing.passes
+        );
+    }
+    println!();
+    println!(
+        "issues and pull requests with comments to sync: {}",
+        progress.missing_comment_syncs
+    );
+    println!(
+        "users with profiles to fetch: {}",
+        progress.missing_profiles
+    );
+}
+
+async fn queue(gh: &GithubDb, command: QueueCommand) {
+    match command {
+        QueueCommand::List { priority, limit } => {
+            for request in gh.queue(priority.map(Into::into), limit).await {
+                println!(
+                    "{:>8} {:<10?} {:<10} {}",
+                    request.sequence_number, request.priority, request.name, request.data
+                );
+            }
+        }
+        QueueCommand::Remove { sequence_numbers } => {
+            for sequence_number in sequence_numbers {
+                if !gh.remove_queued(sequence_number).await {
+                    eprintln!("request {sequence_number} isn't queued");
+                }
+            }
+        }
+        QueueCommand::Clear { priority } => {
+            let removed = gh.clear_queue(priority.map(Into::into)).await;
+            println!("removed {removed} requests");
+        }
+    }
+}
+
+async fn search(gh: &GithubDb, args: SearchArgs) -> Result<(), String> {
+    let repo = args.repo.as_deref().map(parse_repo).transpose()?;
+    let filters = SearchFilters {
+        repo,
+        kind: args.kind.map(|kind| match kind {
+            Kind::Issue => ItemKind::Issue,
+            Kind::Pr => ItemKind::PullRequest,
+        }),
+        open: (args.open || args.closed).then_some(args.open),
+        comments: !args.no_comments,
+        limit: args.limit,
+    };
+
+    let hits = gh
+        .search(&args.query, filters)
+        .await
+        .map_err(|e| e.to_string())?;
+    for hit in hits {
+        let location = match hit.comment_id {
+            Some(id) => format!("{:?}#{} (comment {id})", hit.repo, hit.number),
+            None => format!("{:?}#{}", hit.repo, hit.number),
+        };
+        println!("{location} {} {}", kind(hit.kind), hit.title);
+        println!("    {}", hit.snippet.replace('\n', " "));
+    }
+    Ok(())
+}
+
+async fn import(gh: &GithubDb, command: ImportCommand) -> Result<(), String> {
+    let print = |path: &Path, summary: ImportSummary| {
+        println!(
+            "{}: {} issues, {} pull requests, {} comments, skipped {} stale and {} invalid",
+            path.display(),
+            summary.issues,
+            summary.pull_requests,
+            summary.comments,
+            summary.stale,
+            summary.invalid
+        );
+    };
+
+    match command {
+        ImportCommand::Dump { dir } => {
+            let summary = gh.import_dump(&dir).await.map_err(|e| e.to_string())?;
+            print(&dir, summary);
+        }
+        ImportCommand::Archive { files, repos } => {
+            let repos = repos
+                .iter()
+                .map(|repo| parse_repo(repo))
+                .collect::<Result<Vec<_>, _>>()?;
+            for file in files {
+                let summary = gh
+                    .import_archive(&file, &repos)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                print(&file, summary);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_repo(repo: &str) -> Result<Repo, String> {
+    repo.parse()
+        .map_err(|()| format!("`{repo}` isn't a repository, expected `owner/name`"))
+}
+
+fn kind(kind: ItemKind) -> &'static str {
+    match kind {
+        ItemKind::Issue => "issue",
+        ItemKind::PullRequest => "pr",
+    }
+}
+
+/// Used when only reading the database, never asked for anything
+/// because no repositories are synced.
+struct Offline;
+
+#[async_trait]
+impl Forge for Offline {
+    async fn list_prs(
+        &self,
+        _: &Repo,
+        _: ListType,
+        _: usize,
+        _: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        Err(ForgeError::Other("offline".to_string()))
+    }
+
+    async fn list_issues(
+        &self,
+        _: &Repo,
+        _: ListType,
+        _: usize,
+        _: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        Err(ForgeError::Other("offline".to_string()))
+    }
+
+    async fn list_comments(
+        &self,
+        _: &Repo,
+        _: u64,
+        _: Option<DateTime<Utc>>,
+        _: usize,
+        _: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        Err(ForgeError::Other("offline".to_string()))
+    }
+}
@@ -0,0 +1,52 @@
+//! Decoding how schema versions before 4 stored enums, for the migration and old exports.
+
+use octocrab::models::{issues::IssueStateReason, pulls};
+
+use crate::enums::{AuthorAssociation, MergeableState, StateReason};
+
+/// A discriminant of the octocrab enum, assuming it didn't change since it was stored.
+pub fn mergeable_state(discriminant: i64) -> MergeableState {
+    [
+        pulls::MergeableState::Behind,
+        pulls::MergeableState::Blocked,
+        pulls::MergeableState::Clean,
+        pulls::MergeableState::Dirty,
+        pulls::MergeableState::Draft,
+        pulls::MergeableState::HasHooks,
+        pulls::MergeableState::Unstable,
+    ]
+    .into_iter()
+    .find(|state| state.clone() as i64 == discriminant)
+    .and_then(|state| MergeableState::from_api(&state))
+    .unwrap_or(MergeableState::Unknown)
+}
+
+/// A discriminant of the octocrab enum, None for discriminants it doesn't know.
+pub fn state_reason(discriminant: i64) -> Option<StateReason> {
+    [
+        IssueStateReason::Completed,
+        IssueStateReason::NotPlanned,
+        IssueStateReason::Reopened,
+        IssueStateReason::Duplicate,
+    ]
+    .into_iter()
+    .find(|reason| reason.clone() as i64 == discriminant)
+    .and_then(|reason| StateReason::from_api(&reason))
+}
+
+/// The variant name, like `FirstTimeContributor`, or how the api spelled values
+/// the octocrab enum didn't have.
+pub fn author_association(stored: &str) -> AuthorAssociation {
+    if let Ok(association) = stored.parse() {
+        return association;
+    }
+
+    let mut api = String::new();
+    for (i, c) in stored.chars().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            api.push('_');
+        }
+        api.push(c.to_ascii_uppercase());
+    }
+    api.parse().unwrap_or(AuthorAssociation::Unknown)
+}
@@ -0,0 +1,250 @@
+//! `@login` and `@org/team` mentions in descriptions and comments.
+//!
+//! Like on GitHub, mentions in code blocks, inline code and quotes don't ping anyone,
+//! so they're skipped. Logins aren't case-sensitive, so mentions are stored in lowercase
+//! and users are linked by their lowercase login, also once they show up in the database
+//! after being mentioned.
+
+use rust_query::{Database, TableRow, Transaction};
+
+use crate::database::schema::{self, Schema};
+
+/// Logins are at most this long.
+const MAX_LOGIN: usize = 39;
+
+/// What's after the `@` of a mention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mentioned {
+    User(String),
+    Team { organization: String, slug: String },
+}
+
+impl Mentioned {
+    /// How it's stored in [`Mention::name`](schema::Mention::name).
+    pub fn name(&self) -> String {
+        match self {
+            Mentioned::User(login) => login.to_lowercase(),
+            Mentioned::Team { organization, slug } => {
+                format!("{organization}/{slug}").to_lowercase()
+            }
+        }
+    }
+}
+
+/// The distinct mentions in markdown `text`, in the order they first appear.
+pub fn parse(text: &str) -> Vec<Mentioned> {
+    let mut found = Vec::new();
+    // the marker of the fenced code block we're in, if any
+    let mut fence = None;
+    // whether the previous line is part of a paragraph, which indented code can't interrupt
+    let mut paragraph = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            paragraph = false;
+            continue;
+        }
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        if indented && !paragraph {
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            paragraph = false;
+            continue;
+        }
+        paragraph = true;
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        parse_line(line, &mut found);
+    }
+    found
+}
+
+fn parse_line(line: &str, found: &mut Vec<Mentioned>) {
+    let chars: Vec<char> = line.chars().collect();
+    // length of the backtick run that opened the inline code we're in
+    let mut code = None;
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let run = chars[i..].iter().take_while(|c| **c == '`').count();
+            code = match code {
+                Some(open) if open == run => None,
+                None => Some(run),
+                open => open,
+            };
+            i += run;
+            continue;
+        }
+
+        let after_word = i > 0 && is_word(chars[i - 1]);
+        if chars[i] != '@' || code.is_some() || after_word {
+            i += 1;
+            continue;
+        }
+        let (mentioned, len) = mention_at(&chars[i + 1..]);
+        if let Some(mentioned) = mentioned
+            && !found.iter().any(|m| m.name() == mentioned.name())
+        {
+            found.push(mentioned);
+        }
+        i += 1 + len;
+    }
+}
+
+/// Characters that can't come right before a mention, like in e-mail addresses.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '@' | '`')
+}
+
+/// The mention at the start of `chars`, which follow an `@`, and how many chars it takes.
+fn mention_at(chars: &[char]) -> (Option<Mentioned>, usize) {
+    let login = name(chars, |c| c.is_ascii_alphanumeric() || c == '-');
+    let len = login.chars().count();
+    if login.is_empty() || login.ends_with('-') || len > MAX_LOGIN {
+        return (None, len);
+    }
+
+    if chars.get(len) == Some(&'/') {
+        let slug = name(&chars[len + 1..], |c| {
+            c.is_ascii_alphanumeric() || matches!(c, '-' | '_')
+        });
+        if !slug.is_empty() {
+            let slug_len = slug.chars().count();
+            let team = Mentioned::Team {
+                organization: login,
+                slug,
+            };
+            return (Some(team), len + 1 + slug_len);
+        }
+    }
+    (Some(Mentioned::User(login)), len)
+}
+
+/// The longest prefix of `chars` that's allowed in a name, starting with an alphanumeric.
+fn name(chars: &[char], allowed: impl Fn(char) -> bool) -> String {
+    if !chars.first().is_some_and(|c| c.is_ascii_alphanumeric()) {
+        return String::new();
+    }
+    chars.iter().take_while(|c| allowed(**c)).collect()
+}
+
+/// Make the mentions of a description, or of `comment` if it's given, match `text`.
+///
+/// Mentions that are gone are pushed to `outdated`, to be deleted once the transaction
+/// is downgraded.
+pub fn update(
+    txn: &mut Transaction<Schema>,
+    issue_or_pr: TableRow<schema::IssuePullRequestShared>,
+    comment: Option<TableRow<schema::Comment>>,
+    text: &str,
+    outdated: &mut Vec<TableRow<schema::Mention>>,
+) {
+    use schema::*;
+
+    let existing = txn.query(|rows| {
+        let mention = rows.join(Mention);
+        rows.filter(mention.issue_or_pr.eq(issue_or_pr));
+        match comment {
+            Some(comment) => {
+                let in_comment = rows.filter_some(&mention.comment);
+                rows.filter(in_comment.eq(comment));
+            }
+            None => rows.filter(mention.comment.is_none()),
+        }
+        rows.into_iter((&mention, &mention.name))
+            .collect::<Vec<_>>()
+    });
+    let mentioned = parse(text);
+
+    for (row, name) in &existing {
+        if !mentioned.iter().any(|m| m.name() == *name) {
+            outdated.push(*row);
+        }
+    }
+    for m in mentioned {
+        let name = m.name();
+        if existing.iter().any(|(_, existing)| *existing == name) {
+            continue;
+        }
+        let (user, team) = match m {
+            Mentioned::User(_) => (find_user(txn, &name), None),
+            Mentioned::Team { organization, slug } => {
+                (None, Some(txn.find_or_insert(Team { organization, slug })))
+            }
+        };
+        txn.insert_ok(Mention {
+            issue_or_pr,
+            comment,
+            name,
+            user,
+            team,
+        });
+    }
+}
+
+/// The user with `login`, which is in lowercase.
+fn find_user(txn: &Transaction<Schema>, login: &str) -> Option<TableRow<schema::User>> {
+    txn.query(|rows| {
+        let user = rows.join(schema::User);
+        rows.filter(user.login.eq(login));
+        rows.into_iter(&user).next()
+    })
+}
+
+/// Link the mentions of `login` that were made before the user was in the database.
+pub fn link_user(txn: &mut Transaction<Schema>, user: TableRow<schema::User>, login: &str) {
+    use schema::*;
+
+    let login = login.to_lowercase();
+    let unlinked = txn.query(|rows| {
+        let mention = rows.join(Mention);
+        rows.filter(mention.name.eq(&login));
+        rows.filter(mention.user.is_none());
+        rows.filter(mention.team.is_none());
+        rows.into_vec(mention)
+    });
+    for row in unlinked {
+        txn.mutable(row).user = Some(user);
+    }
+}
+
+/// Extract the mentions of everything that was stored before they were, or before they
+/// were stored in lowercase.
+pub fn backfill(db: &Database<Schema>) {
+    use schema::*;
+
+    db.transaction_mut_ok(|txn| {
+        let mut outdated = Vec::new();
+
+        let shared = txn.query(|rows| {
+            let shared = rows.join(IssuePullRequestShared);
+            rows.into_vec((&shared, &shared.description))
+        });
+        for (row, description) in shared {
+            update(txn, row, None, &description, &mut outdated);
+        }
+
+        let comments = txn.query(|rows| {
+            let comment = rows.join(Comment);
+            rows.into_vec((&comment, (&comment.issue_or_pr, &comment.text)))
+        });
+        for (row, (issue_or_pr, text)) in comments {
+            let comment = Some(row);
+            update(txn, issue_or_pr, comment, &text, &mut outdated);
+        }
+
+        let txn = txn.downgrade();
+        for row in outdated {
+            txn.delete_ok(row);
+        }
+    });
+}
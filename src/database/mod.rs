@@ -1,2 +1,5 @@
+pub mod legacy;
+pub mod mentions;
 pub mod schema;
+pub mod search;
 pub mod updates;
@@ -1,10 +1,19 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use octocrab::models::pulls::MergeableState;
-use rust_query::{Database, DatabaseAsync, Lazy, migration::schema};
+use rust_query::{Database, Lazy, migration::schema};
+
+use crate::{
+    database::{legacy, mentions, search},
+    enums::{AuthorAssociation, ItemState, TransitionKind},
+};
 
 #[schema(Schema)]
-#[version(0..=2)]
+#[version(0..=13)]
 pub mod vN {
 
     pub struct Config {
@@ -25,7 +34,29 @@ pub mod vN {
         #[unique]
         pub github_id: i64,
         pub name: String,
+        /// The name from the profile, `name` if there is none
         pub display_name: String,
+
+        /// A [`UserKind`](crate::enums::UserKind), None if not known yet
+        #[version(6..)]
+        pub kind: Option<String>,
+        /// From the profile, None if empty or not fetched yet
+        #[version(6..)]
+        pub company: Option<String>,
+        /// From the profile, None if empty or not fetched yet
+        #[version(6..)]
+        pub location: Option<String>,
+        /// When the account was created, None if the profile wasn't fetched yet
+        #[version(6..)]
+        pub account_created_timestamp: Option<i64>,
+        /// When the profile was last fetched, or failed to be.
+        /// None if that never happened
+        #[version(6..)]
+        pub profile_synced_timestamp: Option<i64>,
+        /// `name` in lowercase, since logins aren't case-sensitive
+        #[version(13..)]
+        #[index]
+        pub login: String,
     }
 
     #[unique(organization, name)]
@@ -47,8 +78,15 @@ pub mod vN {
 
         /// Discriminant of octocrab::models::issues::StateReason,
         /// None if no particular reason
-        #[version(1..)]
+        #[version(1..4)]
         pub state_reason: Option<i64>,
+        /// A [`StateReason`](crate::enums::StateReason), None if no particular reason
+        #[version(4..)]
+        pub state_reason: Option<String>,
+
+        /// An [`ItemState`](crate::enums::ItemState)
+        #[version(5..)]
+        pub state: String,
 
         /// None if not closed
         pub closed_at_timestamp: Option<i64>,
@@ -56,8 +94,11 @@ pub mod vN {
         #[version(1..)]
         pub closed_by: Option<User>,
 
-        /// Discriminant of octocrab::models::AuthorAssociation
-        #[version(1..)]
+        /// Variant name of octocrab::models::AuthorAssociation
+        #[version(1..4)]
+        pub author_association: String,
+        /// An [`AuthorAssociation`](crate::enums::AuthorAssociation)
+        #[version(4..)]
         pub author_association: String,
 
         #[version(..1)]
@@ -68,6 +109,11 @@ pub mod vN {
         pub lock_reason: Option<String>,
 
         pub repo: Repo,
+
+        /// When all comments were last fetched,
+        /// None if that never happened since this column was added
+        #[version(3..)]
+        pub comments_synced_timestamp: Option<i64>,
     }
 
     #[unique(user, issue_or_pr)]
@@ -116,8 +162,11 @@ pub mod vN {
         pub base_sha: Option<String>,
 
         /// Discriminant of octocrab::models::pulls::MergeableState
-        #[version(1..)]
+        #[version(1..4)]
         pub mergeable_state: i64,
+        /// A [`MergeableState`](crate::enums::MergeableState)
+        #[version(4..)]
+        pub mergeable_state: String,
 
         /// bool
         #[version(1..)]
@@ -140,6 +189,20 @@ pub mod vN {
         pub pr_closes_issue: i64,
     }
 
+    /// Every time an issue or pull request was seen closed, reopened or merged.
+    #[unique(issue_or_pr, kind, timestamp)]
+    #[version(5..)]
+    pub struct StateTransition {
+        pub issue_or_pr: IssuePullRequestShared,
+        /// A [`TransitionKind`](crate::enums::TransitionKind)
+        pub kind: String,
+        /// None if not known, like for reopens
+        pub actor: Option<User>,
+        /// For reopens, the last update of the item when it was seen open again,
+        /// so at or after the reopen itself
+        pub timestamp: i64,
+    }
+
     #[unique(issue_or_pr, label)]
     pub struct LabelLink {
         pub issue_or_pr: IssuePullRequestShared,
@@ -165,14 +228,228 @@ pub mod vN {
 
         pub created_timestamp: i64,
         pub updated_timestamp: i64,
+
+        /// An [`AuthorAssociation`](crate::enums::AuthorAssociation)
+        #[version(7..)]
+        pub author_association: String,
+        /// None for comments that weren't synced since this was added
+        #[version(7..)]
+        pub html_url: Option<String>,
+        /// None for comments that weren't synced since this was added
+        #[version(7..)]
+        pub node_id: Option<String>,
+    }
+
+    /// A team of an organization.
+    #[unique(organization, slug)]
+    #[version(8..)]
+    pub struct Team {
+        pub organization: String,
+        pub slug: String,
+    }
+
+    /// An `@login` or `@org/team` in a description or comment, outside of code and quotes.
+    #[index(issue_or_pr)]
+    #[index(comment)]
+    #[index(name)]
+    #[no_reference]
+    #[version(8..)]
+    pub struct Mention {
+        pub issue_or_pr: IssuePullRequestShared,
+        /// None if it's in the description
+        pub comment: Option<Comment>,
+        /// What's after the `@` in lowercase, like `alice` or `rust-lang/compiler`
+        pub name: String,
+        /// None for teams, and for users that aren't in the database (yet)
+        pub user: Option<User>,
+        /// None for users
+        pub team: Option<Team>,
+    }
+
+    #[version(9..)]
+    pub struct DiscussionCategory {
+        /// The GraphQL node id
+        #[unique]
+        pub node_id: String,
+        pub repo: Repo,
+        pub name: String,
+        pub slug: String,
+        pub emoji: String,
+        /// bool, whether comments can be marked as the answer
+        pub answerable: i64,
+    }
+
+    #[unique(repo, number)]
+    #[version(9..)]
+    pub struct Discussion {
+        pub repo: Repo,
+        pub number: i64,
+        /// The GraphQL node id
+        pub node_id: String,
+        pub category: DiscussionCategory,
+
+        pub title: String,
+        pub body: String,
+        pub author: User,
+        /// An [`AuthorAssociation`](crate::enums::AuthorAssociation)
+        pub author_association: String,
+        pub html_url: String,
+
+        pub created_timestamp: i64,
+        pub updated_timestamp: i64,
+        /// None if not closed
+        pub closed_at_timestamp: Option<i64>,
+        /// bool
+        pub locked: i64,
+
+        /// None if no answer was chosen
+        pub answer_chosen_timestamp: Option<i64>,
+        /// None if no answer was chosen, or it's not known who chose it
+        pub answer_chosen_by: Option<User>,
+    }
+
+    /// A comment on a discussion, or a reply to one.
+    #[index(discussion)]
+    #[version(9..)]
+    pub struct DiscussionComment {
+        /// The GraphQL node id
+        #[unique]
+        pub node_id: String,
+        pub discussion: Discussion,
+        /// The comment this replies to, None for top-level comments
+        pub parent: Option<DiscussionComment>,
+
+        pub author: User,
+        /// An [`AuthorAssociation`](crate::enums::AuthorAssociation)
+        pub author_association: String,
+        pub body: String,
+        pub html_url: String,
+
+        pub created_timestamp: i64,
+        pub updated_timestamp: i64,
+        /// bool, whether it was marked as the answer
+        pub is_answer: i64,
+    }
+
+    #[version(10..)]
+    pub struct Release {
+        #[unique]
+        pub release_id: i64,
+        pub repo: Repo,
+        /// Also for drafts, whose tag might not exist yet
+        pub tag_name: String,
+        /// None if it has no name, GitHub shows the tag name then
+        pub name: Option<String>,
+        pub body: String,
+        pub author: User,
+        pub html_url: String,
+        /// bool
+        pub draft: i64,
+        /// bool
+        pub prerelease: i64,
+        pub created_timestamp: i64,
+        /// None for drafts
+        pub published_timestamp: Option<i64>,
+    }
+
+    #[unique(repo, name)]
+    #[index(commit_sha)]
+    #[version(10..)]
+    pub struct Tag {
+        pub repo: Repo,
+        pub name: String,
+        /// The commit it points at
+        pub commit_sha: String,
+    }
+
+    /// What the repository page shows, as of the last time it was fetched.
+    #[version(11..)]
+    pub struct RepoMetadata {
+        #[unique]
+        pub repo: Repo,
+        pub description: Option<String>,
+        pub default_branch: Option<String>,
+        /// Like `public`, `private` or `internal`, None if not reported
+        pub visibility: Option<String>,
+        /// bool
+        pub archived: i64,
+        pub synced_timestamp: i64,
+    }
+
+    #[unique(repo, topic)]
+    #[no_reference]
+    #[version(11..)]
+    pub struct RepoTopic {
+        pub repo: Repo,
+        pub topic: String,
+    }
+
+    /// The counts of a repository at one point in time, one is stored every time
+    /// its metadata is fetched.
+    #[index(repo)]
+    #[no_reference]
+    #[version(11..)]
+    pub struct RepoSnapshot {
+        pub repo: Repo,
+        pub timestamp: i64,
+        pub stars: i64,
+        pub forks: i64,
+        /// Users that watch the repository, not the stars that the api calls watchers
+        pub watchers: i64,
+        /// Like on GitHub, this includes open pull requests
+        pub open_issues: i64,
+    }
+
+    /// A user with access to a repository, directly or through a team or the organization.
+    #[unique(repo, user)]
+    #[index(user)]
+    #[no_reference]
+    #[version(12..)]
+    pub struct Collaborator {
+        pub repo: Repo,
+        pub user: User,
+        /// A [`Permission`](crate::enums::Permission), the highest the user has
+        pub permission: String,
+        /// Rows that weren't seen in the last full listing are deleted
+        pub synced_timestamp: i64,
+    }
+
+    /// A team with access to a repository.
+    #[unique(repo, team)]
+    #[no_reference]
+    #[version(12..)]
+    pub struct RepoTeam {
+        pub repo: Repo,
+        pub team: Team,
+        /// The display name, `team.slug` is what's used in mentions
+        pub name: String,
+        /// A [`Permission`](crate::enums::Permission)
+        pub permission: String,
+        /// Rows that weren't seen in the last full listing are deleted
+        pub synced_timestamp: i64,
+    }
+
+    #[unique(team, user)]
+    #[index(user)]
+    #[no_reference]
+    #[version(12..)]
+    pub struct TeamMember {
+        pub team: Team,
+        pub user: User,
+        /// Rows that weren't seen in the last full listing are deleted
+        pub synced_timestamp: i64,
     }
 }
 
-pub use v2::*;
+pub use v13::*;
 
-pub fn migrate(db_path: impl AsRef<Path>) -> DatabaseAsync<v2::Schema> {
-    let m = Database::migrator(rust_query::migration::Config::open(db_path))
-        .expect("database should not be older than supported versions");
+pub fn migrate(db_path: impl AsRef<Path>) -> Arc<Database<v13::Schema>> {
+    let needs_backfill = search::prepare(&db_path);
+
+    let m = Database::migrator(search::init_stmt(rust_query::migration::Config::open(
+        db_path,
+    )))
+    .expect("database should not be older than supported versions");
 
     let m = m.migrate(|txn| v0::migrate::Schema {
         issue_pull_request_shared: txn.migrate_ok(|old: Lazy<v0::IssuePullRequestShared>| {
@@ -197,9 +474,185 @@ pub fn migrate(db_path: impl AsRef<Path>) -> DatabaseAsync<v2::Schema> {
 
     let m = m.migrate(|_txn| v1::migrate::Schema {});
 
+    let m = m.migrate(|txn| v2::migrate::Schema {
+        issue_pull_request_shared: txn.migrate_ok(|_: Lazy<v2::IssuePullRequestShared>| {
+            v2::migrate::IssuePullRequestShared {
+                comments_synced_timestamp: None,
+            }
+        }),
+    });
+
+    // from octocrab's discriminants and variant names to GitHub's spelling
+    let m = m.migrate(|txn| v3::migrate::Schema {
+        issue_pull_request_shared: txn.migrate_ok(|old: Lazy<v3::IssuePullRequestShared>| {
+            v3::migrate::IssuePullRequestShared {
+                state_reason: old
+                    .state_reason
+                    .and_then(legacy::state_reason)
+                    .map(|reason| reason.as_str().to_string()),
+                author_association: legacy::author_association(&old.author_association)
+                    .as_str()
+                    .to_string(),
+            }
+        }),
+        pull_request: txn.migrate_ok(|old: Lazy<v3::PullRequest>| v3::migrate::PullRequest {
+            mergeable_state: legacy::mergeable_state(old.mergeable_state)
+                .as_str()
+                .to_string(),
+        }),
+    });
+
+    let mut needs_transitions = false;
+    let m = m.migrate(|txn| {
+        needs_transitions = true;
+        v4::migrate::Schema {
+            issue_pull_request_shared: txn.migrate_ok(|old: Lazy<v4::IssuePullRequestShared>| {
+                v4::migrate::IssuePullRequestShared {
+                    state: match old.closed_at_timestamp {
+                        Some(_) => ItemState::Closed,
+                        None => ItemState::Open,
+                    }
+                    .as_str()
+                    .to_string(),
+                }
+            }),
+        }
+    });
+
+    let m = m.migrate(|txn| v5::migrate::Schema {
+        user: txn.migrate_ok(|_: Lazy<v5::User>| v5::migrate::User {
+            kind: None,
+            company: None,
+            location: None,
+            account_created_timestamp: None,
+            profile_synced_timestamp: None,
+        }),
+    });
+
+    let m = m.migrate(|txn| v6::migrate::Schema {
+        comment: txn.migrate_ok(|_: Lazy<v6::Comment>| v6::migrate::Comment {
+            author_association: AuthorAssociation::Unknown.as_str().to_string(),
+            html_url: None,
+            node_id: None,
+        }),
+    });
+
+    let mut needs_mentions = false;
+    let m = m.migrate(|_txn| {
+        needs_mentions = true;
+        v7::migrate::Schema {}
+    });
+
+    let m = m.migrate(|_txn| v8::migrate::Schema {});
+
+    let m = m.migrate(|_txn| v9::migrate::Schema {});
+
+    let m = m.migrate(|_txn| v10::migrate::Schema {});
+
+    let m = m.migrate(|_txn| v11::migrate::Schema {});
+
+    // mentions were stored as written, so they're extracted again in lowercase
+    let m = m.migrate(|txn| {
+        needs_mentions = true;
+        v12::migrate::Schema {
+            user: txn
+                .migrate(|old: Lazy<v12::User>| v12::migrate::User {
+                    login: old.name.to_lowercase(),
+                })
+                .expect("github ids were unique before"),
+        }
+    });
+
     let db = m
         .finish()
         .expect("database should not be newer than supported versions");
 
-    DatabaseAsync::new(Arc::new(db))
+    if needs_transitions {
+        backfill_transitions(&db);
+    }
+
+    if needs_mentions {
+        mentions::backfill(&db);
+    }
+
+    if needs_backfill {
+        search::backfill(&db);
+    }
+
+    Arc::new(db)
+}
+
+/// Returned by [`open`].
+#[derive(Debug)]
+pub enum OpenError {
+    /// There's no database at this path
+    Missing(PathBuf),
+    /// The database has to be migrated first
+    Older,
+    /// The database was written by a newer version
+    Newer,
+}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::Missing(path) => write!(f, "{} doesn't exist", path.display()),
+            OpenError::Older => write!(f, "the database is older than supported, migrate it first"),
+            OpenError::Newer => write!(f, "the database is newer than supported"),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+/// Open an existing database that's at the current schema version, without creating
+/// or migrating it.
+pub fn open(db_path: impl AsRef<Path>) -> Result<Arc<Database<Schema>>, OpenError> {
+    let db_path = db_path.as_ref();
+    if !db_path.exists() {
+        return Err(OpenError::Missing(db_path.to_path_buf()));
+    }
+    let m = Database::<Schema>::migrator(search::init_stmt(rust_query::migration::Config::open(
+        db_path,
+    )))
+    .ok_or(OpenError::Older)?;
+    let db = m.finish().ok_or(OpenError::Newer)?;
+    Ok(Arc::new(db))
+}
+
+/// The closes and merges that are known from before transitions were recorded.
+fn backfill_transitions(db: &Database<Schema>) {
+    db.transaction_mut_ok(|txn| {
+        let closed = txn.query(|rows| {
+            let shared = rows.join(IssuePullRequestShared);
+            rows.into_iter((&shared, (&shared.closed_at_timestamp, &shared.closed_by)))
+                .collect::<Vec<_>>()
+        });
+        for (issue_or_pr, (closed_at, closed_by)) in closed {
+            if let Some(timestamp) = closed_at {
+                let _ = txn.insert(StateTransition {
+                    issue_or_pr,
+                    kind: TransitionKind::Closed.as_str(),
+                    actor: closed_by,
+                    timestamp,
+                });
+            }
+        }
+
+        let merged = txn.query(|rows| {
+            let pr = rows.join(PullRequest);
+            rows.into_iter((&pr.shared, (&pr.merged_at_timestamp, &pr.merged_by)))
+                .collect::<Vec<_>>()
+        });
+        for (issue_or_pr, (merged_at, merged_by)) in merged {
+            if let Some(timestamp) = merged_at {
+                let _ = txn.insert(StateTransition {
+                    issue_or_pr,
+                    kind: TransitionKind::Merged.as_str(),
+                    actor: merged_by,
+                    timestamp,
+                });
+            }
+        }
+    });
 }
@@ -0,0 +1,175 @@
+//! Full-text search over the titles and descriptions of issues and pull requests,
+//! and the text of comments.
+//!
+//! The index is an SQLite FTS5 table next to the tables managed by rust-query.
+//! Issues and pull requests are stored under rowid `-number`, comments under their comment id,
+//! so both can share one table and one ranking.
+
+use std::path::Path;
+
+use rusqlite::{Connection, Transaction, params};
+use rust_query::Database;
+
+use crate::database::schema::Schema;
+
+const CREATE_INDEX: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5(title, body, tokenize = 'porter unicode61')";
+
+/// A change to the search index, collected by the `ensure_*` functions and
+/// applied at the end of their transaction.
+pub enum SearchUpdate {
+    Shared {
+        number: i64,
+        title: String,
+        description: String,
+    },
+    Comment {
+        comment_id: i64,
+        text: String,
+    },
+}
+
+impl SearchUpdate {
+    fn rowid(&self) -> i64 {
+        match self {
+            SearchUpdate::Shared { number, .. } => -number,
+            SearchUpdate::Comment { comment_id, .. } => *comment_id,
+        }
+    }
+}
+
+/// What a row in the index refers to.
+pub enum IndexedItem {
+    Shared { number: i64 },
+    Comment { comment_id: i64 },
+}
+
+impl IndexedItem {
+    fn from_rowid(rowid: i64) -> Self {
+        if rowid < 0 {
+            IndexedItem::Shared { number: -rowid }
+        } else {
+            IndexedItem::Comment { comment_id: rowid }
+        }
+    }
+}
+
+/// A match in the index, ordered by relevance.
+pub struct IndexMatch {
+    pub item: IndexedItem,
+    pub snippet: String,
+    /// bm25 score, lower is better
+    pub rank: f64,
+}
+
+/// Creates the index in an existing database, before rust-query opens it.
+/// rust-query doesn't allow schema changes after that.
+///
+/// Returns whether the index was just created and has to be filled with [`backfill`].
+pub fn prepare(db_path: impl AsRef<Path>) -> bool {
+    let conn = Connection::open(db_path).expect("open database");
+    let schema_version: i64 = conn
+        .pragma_query_value(None, "schema_version", |r| r.get(0))
+        .unwrap();
+    // new databases get the index from `init_stmt`, after rust-query created its tables
+    if schema_version == 0 {
+        return false;
+    }
+
+    let exists: bool = conn
+        .query_row(
+            "SELECT count(*) > 0 FROM sqlite_schema WHERE name = 'search'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    if !exists {
+        conn.execute_batch(CREATE_INDEX)
+            .expect("sqlite should support fts5");
+    }
+    !exists
+}
+
+pub fn init_stmt(config: rust_query::migration::Config) -> rust_query::migration::Config {
+    config.init_stmt(CREATE_INDEX)
+}
+
+/// Index everything that's already in the database.
+pub fn backfill(db: &Database<Schema>) {
+    tracing::info!("building search index");
+    db.transaction_mut_ok(|txn| {
+        use crate::schema::*;
+
+        let mut updates: Vec<_> = txn.query(|rows| {
+            let shared = rows.join(IssuePullRequestShared);
+            rows.into_iter((&shared.number, (&shared.title, &shared.description)))
+                .map(|(number, (title, description))| SearchUpdate::Shared {
+                    number,
+                    title,
+                    description,
+                })
+                .collect()
+        });
+        updates.extend(txn.query(|rows| {
+            let comment = rows.join(Comment);
+            rows.into_iter((&comment.comment_id, &comment.text))
+                .map(|(comment_id, text)| SearchUpdate::Comment { comment_id, text })
+                .collect::<Vec<_>>()
+        }));
+
+        txn.downgrade()
+            .rusqlite_transaction(|txn| apply(txn, updates));
+    });
+}
+
+pub fn apply(txn: &Transaction, updates: Vec<SearchUpdate>) {
+    if updates.is_empty() {
+        return;
+    }
+
+    let mut delete = txn
+        .prepare_cached("DELETE FROM search WHERE rowid = ?1")
+        .unwrap();
+    let mut insert = txn
+        .prepare_cached("INSERT INTO search (rowid, title, body) VALUES (?1, ?2, ?3)")
+        .unwrap();
+
+    for update in updates {
+        let rowid = update.rowid();
+        delete.execute([rowid]).unwrap();
+        match update {
+            SearchUpdate::Shared {
+                title, description, ..
+            } => insert.execute(params![rowid, title, description]),
+            SearchUpdate::Comment { text, .. } => insert.execute(params![rowid, "", text]),
+        }
+        .unwrap();
+    }
+}
+
+/// Matches of `query` in FTS5 syntax, best first.
+pub fn matches(
+    conn: &Connection,
+    query: &str,
+    comments: bool,
+    offset: usize,
+    limit: usize,
+) -> rusqlite::Result<Vec<IndexMatch>> {
+    // titles weigh more than bodies
+    let mut stmt = conn.prepare_cached(
+        "SELECT rowid, snippet(search, -1, '[', ']', '…', 16), bm25(search, 4.0, 1.0) AS rank
+        FROM search WHERE search MATCH ?1 AND (?2 OR rowid < 0)
+        ORDER BY rank LIMIT ?3 OFFSET ?4",
+    )?;
+
+    stmt.query_map(
+        params![query, comments, limit as i64, offset as i64],
+        |row| {
+            Ok(IndexMatch {
+                item: IndexedItem::from_rowid(row.get(0)?),
+                snippet: row.get(1)?,
+                rank: row.get(2)?,
+            })
+        },
+    )?
+    .collect()
+}
@@ -1,47 +1,73 @@
 use chrono::Utc;
 use octocrab::models::{
-    AuthorAssociation, IssueState, Label,
+    Author, AuthorAssociation, Collaborator, IssueState, Label, Permissions, Repository,
     issues::{Comment, Issue, IssueStateReason},
-    pulls::{MergeableState, PullRequest},
+    pulls::PullRequest,
+    repos::{Release, Tag},
+    teams::Team,
 };
 use rust_query::{TableRow, Transaction};
 
 use crate::{
-    GithubDb, Repo,
-    database::schema::{self, Schema},
+    Event, GithubDb, Repo,
+    database::{
+        mentions,
+        schema::{self, Schema},
+        search::{self, SearchUpdate},
+    },
+    enums::{self, ItemState, MergeableState, Permission, StateReason, TransitionKind, UserKind},
+    forge::{
+        discussion::{Actor, Discussion, DiscussionCategory, DiscussionComment},
+        json,
+    },
 };
 
+/// Defines `update!(row.field, value)` which assigns `value` to the column.
+/// `update!(tracked: ..)` additionally marks the item as [`ProcessStatus::Updated`]
+/// when the value changed.
+///
+/// When given a `changed` vector, the names of all columns that changed are pushed onto it.
 macro_rules! gen_update {
-    ($status: ident) => {
+    ($status: ident $(, $changed: ident)?) => {
         macro_rules! update {
             (tracked: $a: expr, $b: expr) => {{
                 let b = $b;
                 if $a != b {
                     $status.update(ProcessStatus::Updated);
+                    $($changed.push(field_name(stringify!($a)));)?
                     $a = b;
                 }
             }};
             ($a: expr, $b: expr) => {{
-                $a = $b;
+                let b = $b;
+                $(if $a != b {
+                    $changed.push(field_name(stringify!($a)));
+                })?
+                $a = b;
             }};
         }
     };
 }
 
+/// `"shared.title"` -> `"title"`
+fn field_name(expr: &'static str) -> &'static str {
+    expr.rsplit('.').next().unwrap_or(expr).trim()
+}
+
 impl GithubDb {
     pub async fn process_comment(
         &self,
-        _repo: Repo,
+        repo: Repo,
         Comment {
             id,
-            node_id: _,
+            node_id,
             url: _,
-            html_url: _,
+            html_url,
             issue_url: _,
             body,
             body_text: _,
             body_html: _,
-            author_association: _,
+            author_association,
             user,
             created_at,
             updated_at,
@@ -49,33 +75,66 @@ impl GithubDb {
         }: Comment,
         issue_number: u64,
     ) -> ProcessStatus {
-        self.db
+        let (status, changed) = self
+            .db
             .transaction_mut_ok(move |txn| {
                 use schema::*;
                 let mut status = ProcessStatus::Unchanged;
+                let mut changed = Vec::new();
+                let mut search = Vec::new();
+                let mut outdated_mentions = Vec::new();
 
                 let Some(issue_or_pr) =
                     txn.query_one(IssuePullRequestShared.number(issue_number as i64))
                 else {
                     tracing::error!("no issue found in database for comment {}", id);
-                    return status;
+                    return (status, changed);
                 };
 
                 let author = ensure_user_exists(txn, &mut status, user);
                 ensure_comment_exists(
                     txn,
                     &mut status,
+                    &mut changed,
+                    &mut search,
+                    &mut outdated_mentions,
                     *id as i64,
                     author,
                     issue_or_pr,
                     body,
                     created_at.timestamp(),
                     updated_at.unwrap_or(created_at).timestamp(),
+                    author_association,
+                    html_url.to_string(),
+                    node_id,
                 );
 
-                status
+                let txn = txn.downgrade();
+
+                txn.rusqlite_transaction(|txn| search::apply(txn, search));
+                for i in outdated_mentions {
+                    txn.delete_ok(i);
+                }
+
+                (status, changed)
             })
-            .await
+            .await;
+
+        match status {
+            ProcessStatus::New => self.emit(vec![Event::CommentAdded {
+                repo,
+                issue_number,
+                comment_id: *id,
+            }]),
+            _ if changed.contains(&"text") => self.emit(vec![Event::CommentEdited {
+                repo,
+                issue_number,
+                comment_id: *id,
+            }]),
+            _ => {}
+        }
+
+        status
     }
 
     pub async fn process_pr(
@@ -135,22 +194,25 @@ impl GithubDb {
             ..
         }: PullRequest,
     ) -> ProcessStatus {
-        self.db
+        let (status, events) = self
+            .db
             .transaction_mut_ok(move |txn| {
                 use schema::*;
 
                 let mut status = ProcessStatus::Unchanged;
-
-                let Some(author) = user else {
-                    tracing::error!("no author for pr #");
-                    return ProcessStatus::Unchanged;
+                let mut changed = Vec::new();
+                let mut search = Vec::new();
+                let mut outdated_mentions = Vec::new();
+                let mut events = Vec::new();
+
+                let user = match user {
+                    Some(author) => ensure_user_exists(txn, &mut status, *author),
+                    None => ensure_ghost_exists(txn, &mut status),
                 };
 
-                let user = ensure_user_exists(txn, &mut status, *author);
-
-                let repo = txn.find_or_insert(Repo {
-                    organization: repo.organization,
-                    name: repo.name,
+                let repo_row = txn.find_or_insert(Repo {
+                    organization: repo.organization.clone(),
+                    name: repo.name.clone(),
                 });
                 let closed_at = (state == Some(IssueState::Closed))
                     .then(|| closed_at.unwrap_or_else(Utc::now).timestamp());
@@ -160,8 +222,11 @@ impl GithubDb {
                 let shared = ensure_shared_exists(
                     &mut *txn,
                     &mut status,
+                    &mut changed,
+                    &mut search,
+                    &mut outdated_mentions,
                     user,
-                    repo,
+                    repo_row,
                     number,
                     title,
                     body,
@@ -180,6 +245,7 @@ impl GithubDb {
                 let pr = ensure_pr_exists(
                     txn,
                     &mut status,
+                    &mut changed,
                     shared,
                     draft.unwrap_or(false),
                     maintainer_can_modify,
@@ -194,15 +260,31 @@ impl GithubDb {
                     base.sha,
                     mergeable.unwrap_or(false),
                     rebaseable.unwrap_or(false),
-                    mergeable_state.unwrap_or(MergeableState::Unknown),
+                    mergeable_state
+                        .and_then(|state| MergeableState::from_api(&state))
+                        .unwrap_or(MergeableState::Unknown),
                 );
 
+                match status {
+                    ProcessStatus::New => events.push(Event::PrCreated {
+                        repo: repo.clone(),
+                        number,
+                    }),
+                    _ if !changed.is_empty() => events.push(Event::PrUpdated {
+                        repo: repo.clone(),
+                        number,
+                        changed_fields: changed,
+                    }),
+                    _ => {}
+                }
+
                 let labels: Vec<_> = labels
                     .unwrap_or_default()
                     .into_iter()
                     .map(|label| ensure_label_exists(txn, &mut status, label))
                     .collect();
-                let outdated_labels = update_label_assignments(txn, &mut status, shared, labels);
+                let (added_labels, outdated_labels) =
+                    update_label_assignments(txn, &mut status, shared, labels);
 
                 let assigned_users: Vec<_> = assignees
                     .unwrap_or(assignee.map(|i| *i).as_slice().to_vec())
@@ -210,7 +292,7 @@ impl GithubDb {
                     .map(|user| ensure_user_exists(txn, &mut status, user))
                     .collect();
 
-                let outdated_assignments =
+                let (added_assignments, outdated_assignments) =
                     update_assignments(txn, &mut status, shared, assigned_users);
 
                 let review_requested_users: Vec<_> = requested_reviewers
@@ -218,10 +300,22 @@ impl GithubDb {
                     .flatten()
                     .map(|user| ensure_user_exists(txn, &mut status, user))
                     .collect();
-                let outdated_review_requests =
+                let (added_review_requests, outdated_review_requests) =
                     update_review_requests(txn, &mut status, pr, review_requested_users);
 
+                link_events(
+                    txn,
+                    &mut events,
+                    &repo,
+                    number,
+                    (&added_labels, &outdated_labels),
+                    (&added_assignments, &outdated_assignments),
+                    (&added_review_requests, &outdated_review_requests),
+                );
+
                 let txn = txn.downgrade();
+
+                txn.rusqlite_transaction(|txn| search::apply(txn, search));
                 for i in outdated_assignments {
                     if let Err(()) = txn.delete(i) {
                         tracing::error!("assignment {i:?} referenced somehow");
@@ -237,13 +331,29 @@ impl GithubDb {
                         tracing::error!("review request {i:?} referenced somehow");
                     }
                 }
+                for i in outdated_mentions {
+                    txn.delete_ok(i);
+                }
 
-                status
+                (status, events)
             })
-            .await
+            .await;
+
+        self.emit(events);
+        status
+    }
+
+    pub async fn process_issue(&self, repo: Repo, issue: Issue) -> ProcessStatus {
+        let number = issue.number;
+        let updated_at = issue.updated_at;
+        let status = self.store_issue(repo.clone(), issue).await;
+        self.add_comments_updated_req(status, repo, Some(updated_at.timestamp()), number)
+            .await;
+        status
     }
 
-    pub async fn process_issue(
+    /// Like [`GithubDb::process_issue`], without queueing a request for its comments.
+    pub(crate) async fn store_issue(
         &self,
         repo: Repo,
         Issue {
@@ -271,7 +381,7 @@ impl GithubDb {
             locked,
             active_lock_reason,
             comments: _,
-            pull_request: _,
+            pull_request,
             closed_at,
             closed_by,
             created_at,
@@ -279,7 +389,7 @@ impl GithubDb {
             ..
         }: Issue,
     ) -> ProcessStatus {
-        let status = self
+        let (status, events) = self
             .db
             .transaction_mut_ok({
                 let repo = repo.clone();
@@ -287,12 +397,16 @@ impl GithubDb {
                     use schema::*;
 
                     let mut status = ProcessStatus::Unchanged;
+                    let mut changed = Vec::new();
+                    let mut search = Vec::new();
+                    let mut outdated_mentions = Vec::new();
+                    let mut events = Vec::new();
 
                     let user = ensure_user_exists(txn, &mut status, user);
 
-                    let repo = txn.find_or_insert(Repo {
-                        organization: repo.organization,
-                        name: repo.name,
+                    let repo_row = txn.find_or_insert(Repo {
+                        organization: repo.organization.clone(),
+                        name: repo.name.clone(),
                     });
                     let closed_at = (state == IssueState::Closed)
                         .then(|| closed_at.unwrap_or_else(Utc::now).timestamp());
@@ -303,8 +417,11 @@ impl GithubDb {
                     let shared = ensure_shared_exists(
                         txn,
                         &mut status,
+                        &mut changed,
+                        &mut search,
+                        &mut outdated_mentions,
                         user,
-                        repo,
+                        repo_row,
                         number,
                         Some(title),
                         body,
@@ -319,21 +436,50 @@ impl GithubDb {
 
                     ensure_issue_exists(txn, &mut status, shared);
 
+                    // The issues api also lists pull requests, their events are
+                    // emitted when they're processed as pull request.
+                    if pull_request.is_none() {
+                        match status {
+                            ProcessStatus::New => events.push(Event::IssueCreated {
+                                repo: repo.clone(),
+                                number,
+                            }),
+                            _ if !changed.is_empty() => events.push(Event::IssueUpdated {
+                                repo: repo.clone(),
+                                number,
+                                changed_fields: changed,
+                            }),
+                            _ => {}
+                        }
+                    }
+
                     let labels: Vec<_> = labels
                         .into_iter()
                         .map(|label| ensure_label_exists(txn, &mut status, label))
                         .collect();
-                    let outdated_labels =
+                    let (added_labels, outdated_labels) =
                         update_label_assignments(txn, &mut status, shared, labels);
 
                     let assigned_users: Vec<_> = assignees
                         .into_iter()
                         .map(|user| ensure_user_exists(txn, &mut status, user))
                         .collect();
-                    let outdated_assignments =
+                    let (added_assignments, outdated_assignments) =
                         update_assignments(txn, &mut status, shared, assigned_users);
 
+                    link_events(
+                        txn,
+                        &mut events,
+                        &repo,
+                        number,
+                        (&added_labels, &outdated_labels),
+                        (&added_assignments, &outdated_assignments),
+                        (&[], &[]),
+                    );
+
                     let txn = txn.downgrade();
+
+                    txn.rusqlite_transaction(|txn| search::apply(txn, search));
                     for i in outdated_assignments {
                         if let Err(()) = txn.delete(i) {
                             tracing::error!("assignment {i:?} referenced somehow");
@@ -344,16 +490,595 @@ impl GithubDb {
                             tracing::error!("label assignment {i:?} referenced somehow");
                         }
                     }
+                    for i in outdated_mentions {
+                        txn.delete_ok(i);
+                    }
 
-                    status
+                    (status, events)
                 }
             })
             .await;
 
-        self.add_comments_updated_req(status, repo, Some(updated_at.timestamp()), number)
+        self.emit(events);
+        status
+    }
+
+    /// Store a discussion with its category, comments and replies, and queue requests
+    /// for the comments and replies that didn't fit in the listing.
+    pub async fn process_discussion(&self, repo: Repo, discussion: Discussion) -> ProcessStatus {
+        let number = discussion.number;
+        let mut more = Vec::new();
+        if let Some(cursor) = discussion.comments.page_info.next() {
+            more.push((discussion.id.clone(), false, cursor.to_string()));
+        }
+        for comment in &discussion.comments.nodes {
+            if let Some(cursor) = comment.replies.page_info.next() {
+                more.push((comment.id.clone(), true, cursor.to_string()));
+            }
+        }
+
+        let status = self.store_discussion(repo.clone(), discussion).await;
+        self.add_discussion_comments_reqs(status, repo, number, more)
+            .await;
+        status
+    }
+
+    /// Like [`GithubDb::process_discussion`], without queueing requests for more comments.
+    async fn store_discussion(&self, repo: Repo, discussion: Discussion) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let mut status = ProcessStatus::Unchanged;
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                let category = ensure_discussion_category_exists(txn, repo, discussion.category);
+                let author = ensure_actor_exists(txn, &mut status, discussion.author);
+                let answer_chosen_by = discussion
+                    .answer_chosen_by
+                    .map(|actor| ensure_actor_exists(txn, &mut status, Some(actor)));
+
+                let closed_at = discussion
+                    .closed
+                    .then(|| discussion.closed_at.unwrap_or_else(Utc::now).timestamp());
+                let row = ensure_discussion_exists(
+                    txn,
+                    &mut status,
+                    repo,
+                    discussion.number,
+                    discussion.id,
+                    category,
+                    discussion.title,
+                    discussion.body,
+                    author,
+                    parse_association(&discussion.author_association),
+                    discussion.url,
+                    discussion.created_at.timestamp(),
+                    discussion.updated_at.timestamp(),
+                    closed_at,
+                    discussion.locked,
+                    discussion.answer_chosen_at.map(|at| at.timestamp()),
+                    answer_chosen_by,
+                );
+
+                for comment in discussion.comments.nodes {
+                    ensure_discussion_comment_exists(txn, &mut status, row, None, comment);
+                }
+
+                status
+            })
+            .await
+    }
+
+    /// Store a comment of a page listed by a [`Request::DiscussionComments`] for discussion
+    /// `number`, with the first replies to it. `parent` is the node id of the comment it
+    /// replies to, if it's a reply. Queues a request for the replies that didn't fit.
+    ///
+    /// [`Request::DiscussionComments`]: crate::requests::Request::DiscussionComments
+    pub async fn process_discussion_comment(
+        &self,
+        repo: Repo,
+        comment: DiscussionComment,
+        number: u64,
+        parent: Option<&str>,
+    ) -> ProcessStatus {
+        let more = match comment.replies.page_info.next() {
+            Some(cursor) => vec![(comment.id.clone(), true, cursor.to_string())],
+            None => Vec::new(),
+        };
+
+        let status = self
+            .store_discussion_comment(repo.clone(), comment, number, parent)
+            .await;
+        self.add_discussion_comments_reqs(status, repo, number, more)
             .await;
         status
     }
+
+    /// Like [`GithubDb::process_discussion_comment`], without queueing a request for more replies.
+    async fn store_discussion_comment(
+        &self,
+        repo: Repo,
+        comment: DiscussionComment,
+        number: u64,
+        parent: Option<&str>,
+    ) -> ProcessStatus {
+        let parent = parent.map(ToString::to_string);
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let mut status = ProcessStatus::Unchanged;
+                let Some(discussion) = txn
+                    .query_one(Repo.organization(repo.organization).name(repo.name))
+                    .and_then(|repo| txn.query_one(Discussion.repo(repo).number(number as i64)))
+                else {
+                    tracing::error!("comment {} of unknown discussion #{number}", comment.id);
+                    return status;
+                };
+                let parent = match parent {
+                    Some(parent) => match txn.query_one(DiscussionComment.node_id(&parent)) {
+                        Some(parent) => Some(parent),
+                        None => {
+                            tracing::error!("reply {} to unknown comment {parent}", comment.id);
+                            return status;
+                        }
+                    },
+                    None => None,
+                };
+                ensure_discussion_comment_exists(txn, &mut status, discussion, parent, comment);
+                status
+            })
+            .await
+    }
+
+    pub async fn process_release(
+        &self,
+        repo: Repo,
+        Release {
+            id,
+            html_url,
+            tag_name,
+            name,
+            body,
+            draft,
+            prerelease,
+            created_at,
+            published_at,
+            author,
+            ..
+        }: Release,
+    ) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                let author = match author {
+                    Some(author) => ensure_user_exists(txn, &mut status, author),
+                    None => ensure_ghost_exists(txn, &mut status),
+                };
+                let body = body.unwrap_or_default();
+                let published_timestamp = published_at.map(|at| at.timestamp());
+
+                match txn.insert(Release {
+                    release_id: *id as i64,
+                    repo,
+                    tag_name: tag_name.clone(),
+                    name: name.clone(),
+                    body: body.clone(),
+                    author,
+                    html_url: html_url.to_string(),
+                    draft: draft as i64,
+                    prerelease: prerelease as i64,
+                    created_timestamp: created_at
+                        .or(published_at)
+                        .unwrap_or_else(Utc::now)
+                        .timestamp(),
+                    published_timestamp,
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut release = txn.mutable(e);
+                        update!(tracked: release.tag_name, tag_name);
+                        update!(tracked: release.name, name);
+                        update!(tracked: release.body, body);
+                        update!(tracked: release.draft, draft as i64);
+                        update!(tracked: release.prerelease, prerelease as i64);
+                        update!(tracked: release.published_timestamp, published_timestamp);
+                        update!(release.author, author);
+                        update!(release.html_url, html_url.to_string());
+                    }
+                }
+                status
+            })
+            .await
+    }
+
+    /// Tags are force-pushed sometimes, then the commit they point at is updated.
+    pub async fn process_tag(&self, repo: Repo, Tag { name, commit, .. }: Tag) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                match txn.insert(Tag {
+                    repo,
+                    name,
+                    commit_sha: commit.sha.clone(),
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut tag = txn.mutable(e);
+                        update!(tracked: tag.commit_sha, commit.sha);
+                    }
+                }
+                status
+            })
+            .await
+    }
+
+    /// Store the metadata of a repository, and a snapshot of its counts.
+    /// The snapshot is stored even when nothing changed, to chart them over time.
+    pub async fn process_repository(
+        &self,
+        repo: Repo,
+        Repository {
+            description,
+            default_branch,
+            visibility,
+            archived,
+            topics,
+            stargazers_count,
+            forks_count,
+            subscribers_count,
+            open_issues_count,
+            ..
+        }: Repository,
+    ) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                let now = Utc::now().timestamp();
+                let archived = archived.unwrap_or(false) as i64;
+
+                match txn.insert(RepoMetadata {
+                    repo,
+                    description: description.clone(),
+                    default_branch: default_branch.clone(),
+                    visibility: visibility.clone(),
+                    archived,
+                    synced_timestamp: now,
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut metadata = txn.mutable(e);
+                        update!(tracked: metadata.description, description);
+                        update!(tracked: metadata.default_branch, default_branch);
+                        update!(tracked: metadata.visibility, visibility);
+                        update!(tracked: metadata.archived, archived);
+                        update!(metadata.synced_timestamp, now);
+                    }
+                }
+
+                let topics = topics.unwrap_or_default();
+                let existing = txn.query(|rows| {
+                    let topic = rows.join(RepoTopic);
+                    rows.filter(topic.repo.eq(repo));
+                    rows.into_vec((&topic, &topic.topic))
+                });
+                let outdated: Vec<_> = existing
+                    .iter()
+                    .filter(|(_, topic)| !topics.contains(topic))
+                    .map(|(row, _)| *row)
+                    .collect();
+                for topic in topics {
+                    if txn.insert(RepoTopic { repo, topic }).is_ok() {
+                        status.update(ProcessStatus::Updated);
+                    }
+                }
+                if !outdated.is_empty() {
+                    status.update(ProcessStatus::Updated);
+                }
+
+                txn.insert_ok(RepoSnapshot {
+                    repo,
+                    timestamp: now,
+                    stars: stargazers_count.unwrap_or(0) as i64,
+                    forks: forks_count.unwrap_or(0) as i64,
+                    // the api's `watchers_count` is the number of stars
+                    watchers: subscribers_count.unwrap_or(0),
+                    open_issues: open_issues_count.unwrap_or(0) as i64,
+                });
+
+                let txn = txn.downgrade();
+                for i in outdated {
+                    txn.delete_ok(i);
+                }
+                status
+            })
+            .await
+    }
+
+    pub async fn process_collaborator(
+        &self,
+        repo: Repo,
+        Collaborator {
+            author,
+            permissions,
+            ..
+        }: Collaborator,
+    ) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                let user = ensure_user_exists(txn, &mut status, author);
+                let permission = collaborator_permission(&permissions).as_str().to_string();
+                let now = Utc::now().timestamp();
+
+                match txn.insert(Collaborator {
+                    repo,
+                    user,
+                    permission: permission.clone(),
+                    synced_timestamp: now,
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut collaborator = txn.mutable(e);
+                        update!(tracked: collaborator.permission, permission);
+                        update!(collaborator.synced_timestamp, now);
+                    }
+                }
+                status
+            })
+            .await
+    }
+
+    pub async fn process_repo_team(
+        &self,
+        repo: Repo,
+        Team {
+            name,
+            slug,
+            permission,
+            ..
+        }: Team,
+    ) -> ProcessStatus {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                // teams with access to a repository are of its organization
+                let team = txn.find_or_insert(Team {
+                    organization: repo.organization.clone(),
+                    slug,
+                });
+                let repo = txn.find_or_insert(Repo {
+                    organization: repo.organization,
+                    name: repo.name,
+                });
+                let permission = team_permission(&permission).as_str().to_string();
+                let now = Utc::now().timestamp();
+
+                match txn.insert(RepoTeam {
+                    repo,
+                    team,
+                    name: name.clone(),
+                    permission: permission.clone(),
+                    synced_timestamp: now,
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut repo_team = txn.mutable(e);
+                        update!(tracked: repo_team.name, name);
+                        update!(tracked: repo_team.permission, permission);
+                        update!(repo_team.synced_timestamp, now);
+                    }
+                }
+                status
+            })
+            .await
+    }
+
+    pub async fn process_team_member(
+        &self,
+        organization: String,
+        member: Author,
+        slug: &str,
+    ) -> ProcessStatus {
+        let slug = slug.to_string();
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+                let mut status = ProcessStatus::Unchanged;
+                gen_update!(status);
+
+                let team = txn.find_or_insert(Team { organization, slug });
+                let user = ensure_user_exists(txn, &mut status, member);
+                let now = Utc::now().timestamp();
+
+                match txn.insert(TeamMember {
+                    team,
+                    user,
+                    synced_timestamp: now,
+                }) {
+                    Ok(_) => status.update(ProcessStatus::New),
+                    Err(e) => {
+                        let mut member = txn.mutable(e);
+                        update!(member.synced_timestamp, now);
+                    }
+                }
+                status
+            })
+            .await
+    }
+
+    /// Delete the collaborators of `repo` that weren't listed since `before`,
+    /// once a full listing is done.
+    pub(crate) async fn forget_collaborators(&self, repo: Repo, before: i64) {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let stale = txn.query(|rows| {
+                    let collaborator = rows.join(Collaborator);
+                    rows.filter(collaborator.repo.organization.eq(&repo.organization));
+                    rows.filter(collaborator.repo.name.eq(&repo.name));
+                    rows.filter(collaborator.synced_timestamp.lt(before));
+                    rows.into_vec(collaborator)
+                });
+                let txn = txn.downgrade();
+                for i in stale {
+                    txn.delete_ok(i);
+                }
+            })
+            .await
+    }
+
+    /// Like [`GithubDb::forget_collaborators`], for the teams with access to `repo`.
+    /// Their members are kept, they're only looked at through the teams that have access.
+    pub(crate) async fn forget_repo_teams(&self, repo: Repo, before: i64) {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let stale = txn.query(|rows| {
+                    let repo_team = rows.join(RepoTeam);
+                    rows.filter(repo_team.repo.organization.eq(&repo.organization));
+                    rows.filter(repo_team.repo.name.eq(&repo.name));
+                    rows.filter(repo_team.synced_timestamp.lt(before));
+                    rows.into_vec(repo_team)
+                });
+                let txn = txn.downgrade();
+                for i in stale {
+                    txn.delete_ok(i);
+                }
+            })
+            .await
+    }
+
+    /// Like [`GithubDb::forget_collaborators`], for the members of a team.
+    pub(crate) async fn forget_team_members(
+        &self,
+        organization: String,
+        slug: String,
+        before: i64,
+    ) {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let stale = txn.query(|rows| {
+                    let member = rows.join(TeamMember);
+                    rows.filter(member.team.organization.eq(&organization));
+                    rows.filter(member.team.slug.eq(&slug));
+                    rows.filter(member.synced_timestamp.lt(before));
+                    rows.into_vec(member)
+                });
+                let txn = txn.downgrade();
+                for i in stale {
+                    txn.delete_ok(i);
+                }
+            })
+            .await
+    }
+}
+
+/// Turn link rows that were added or are about to be removed into [`Event`]s.
+/// Must be called before the outdated links are deleted.
+fn link_events(
+    txn: &Transaction<Schema>,
+    events: &mut Vec<Event>,
+    repo: &Repo,
+    number: u64,
+    (added_labels, outdated_labels): (&[TableRow<schema::Label>], &[TableRow<schema::LabelLink>]),
+    (added_assignments, outdated_assignments): (
+        &[TableRow<schema::User>],
+        &[TableRow<schema::Assignment>],
+    ),
+    (added_review_requests, outdated_review_requests): (
+        &[TableRow<schema::User>],
+        &[TableRow<schema::ReviewRequest>],
+    ),
+) {
+    let repo = || repo.clone();
+
+    for &i in added_labels {
+        let label = txn.lazy(i).name.clone();
+        events.push(Event::LabelAdded {
+            repo: repo(),
+            number,
+            label,
+        });
+    }
+    for &i in outdated_labels {
+        let label = txn.lazy(i).label.name.clone();
+        events.push(Event::LabelRemoved {
+            repo: repo(),
+            number,
+            label,
+        });
+    }
+    for &i in added_assignments {
+        let user = txn.lazy(i).name.clone();
+        events.push(Event::Assigned {
+            repo: repo(),
+            number,
+            user,
+        });
+    }
+    for &i in outdated_assignments {
+        let user = txn.lazy(i).user.name.clone();
+        events.push(Event::Unassigned {
+            repo: repo(),
+            number,
+            user,
+        });
+    }
+    for &i in added_review_requests {
+        let reviewer = txn.lazy(i).name.clone();
+        events.push(Event::ReviewRequested {
+            repo: repo(),
+            number,
+            reviewer,
+        });
+    }
+    for &i in outdated_review_requests {
+        let reviewer = txn.lazy(i).user.name.clone();
+        events.push(Event::ReviewRequestRemoved {
+            repo: repo(),
+            number,
+            reviewer,
+        });
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -409,18 +1134,27 @@ fn ensure_label_exists(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn ensure_comment_exists(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
+    changed: &mut Vec<&'static str>,
+    search: &mut Vec<SearchUpdate>,
+    outdated_mentions: &mut Vec<TableRow<schema::Mention>>,
     comment_id: i64,
     author: TableRow<schema::User>,
     issue_or_pr: TableRow<schema::IssuePullRequestShared>,
     text: Option<String>,
     created_timestamp: i64,
     updated_timestamp: i64,
+    author_association: Option<AuthorAssociation>,
+    html_url: String,
+    node_id: String,
 ) -> TableRow<schema::Comment> {
     use crate::schema::*;
-    gen_update!(status);
+    gen_update!(status, changed);
+
+    let (association_given, author_association) = stored_association(author_association);
 
     match txn.insert(Comment {
         comment_id,
@@ -429,25 +1163,65 @@ fn ensure_comment_exists(
         issue_or_pr,
         created_timestamp,
         updated_timestamp,
+        author_association: author_association.clone(),
+        html_url: Some(html_url.clone()),
+        node_id: Some(node_id.clone()),
     }) {
         Err(e) => {
             let mut comment = txn.mutable(e);
             update!(comment.author, author);
-            if let Some(text) = text {
+            if association_given {
+                update!(comment.author_association, author_association);
+            }
+            update!(comment.html_url, Some(html_url));
+            update!(comment.node_id, Some(node_id));
+            let mut text_changed = false;
+            if let Some(text) = text.clone() {
+                if comment.text != text {
+                    search.push(SearchUpdate::Comment {
+                        comment_id,
+                        text: text.clone(),
+                    });
+                    text_changed = true;
+                }
                 update!(comment.text, text);
             }
             // don't issue pr, it can't change (I hope)
             update!(comment.created_timestamp, created_timestamp);
             update!(tracked: comment.updated_timestamp, updated_timestamp);
+            drop(comment);
+
+            if text_changed {
+                let text = text.unwrap_or_default();
+                mentions::update(txn, issue_or_pr, Some(e), &text, outdated_mentions);
+            }
             e
         }
         Ok(i) => {
             status.update(ProcessStatus::New);
+            let text = text.unwrap_or_default();
+            mentions::update(txn, issue_or_pr, Some(i), &text, outdated_mentions);
+            search.push(SearchUpdate::Comment { comment_id, text });
             i
         }
     }
 }
 
+/// How an author association is stored, and whether the payload had one at all.
+/// Values octocrab doesn't know are stored as [`Unknown`](enums::AuthorAssociation::Unknown).
+fn stored_association(author_association: Option<AuthorAssociation>) -> (bool, String) {
+    let association = author_association
+        .as_ref()
+        .map_or(Some(enums::AuthorAssociation::None), |association| {
+            enums::AuthorAssociation::from_api(association)
+        })
+        .unwrap_or(enums::AuthorAssociation::Unknown);
+    (
+        author_association.is_some(),
+        association.as_str().to_string(),
+    )
+}
+
 fn ensure_user_exists(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
@@ -456,28 +1230,63 @@ fn ensure_user_exists(
     use crate::schema::*;
     gen_update!(status);
 
-    let display_name = author.name.unwrap_or(author.login.clone());
+    let kind = author
+        .r#type
+        .parse::<UserKind>()
+        .ok()
+        .map(|kind| kind.as_str().to_string());
     match txn.insert(User {
         github_id: author.id.0 as i64,
         name: author.login.clone(),
-        display_name: display_name.clone(),
+        display_name: author.name.clone().unwrap_or(author.login.clone()),
+        kind: kind.clone(),
+        company: None::<String>,
+        location: None::<String>,
+        account_created_timestamp: None::<i64>,
+        profile_synced_timestamp: None::<i64>,
+        login: author.login.to_lowercase(),
     }) {
         Err(e) => {
             let mut user = txn.mutable(e);
+            update!(user.login, author.login.to_lowercase());
             update!(user.name, author.login);
-            update!(user.display_name, display_name);
+            // listings usually don't have the name, keep the one from the profile
+            if let Some(display_name) = author.name {
+                update!(user.display_name, display_name);
+            }
+            // bots can be marked by login, which the payload doesn't know about
+            if user.kind.is_none() {
+                update!(user.kind, kind);
+            }
             e
         }
         Ok(i) => {
             status.update(ProcessStatus::Updated);
+            mentions::link_user(txn, i, &author.login);
             i
         }
     }
 }
 
+/// The user that stands in for deleted accounts, see [`GHOST_ID`](crate::GHOST_ID).
+///
+/// Only for authors. A missing closer or merger means the payload doesn't say,
+/// listings don't include them.
+fn ensure_ghost_exists(
+    txn: &mut Transaction<Schema>,
+    status: &mut ProcessStatus,
+) -> TableRow<schema::User> {
+    let ghost = serde_json::from_value(json::ghost()).expect("ghost is a valid user");
+    ensure_user_exists(txn, status, ghost)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn ensure_shared_exists(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
+    changed: &mut Vec<&'static str>,
+    search: &mut Vec<SearchUpdate>,
+    outdated_mentions: &mut Vec<TableRow<schema::Mention>>,
     user: TableRow<schema::User>,
     repo: TableRow<schema::Repo>,
     number: u64,
@@ -492,22 +1301,17 @@ fn ensure_shared_exists(
     author_association: Option<AuthorAssociation>,
 ) -> TableRow<schema::IssuePullRequestShared> {
     use crate::schema::*;
-    gen_update!(status);
+    gen_update!(status, changed);
+
+    let state_reason = state_reason
+        .and_then(|reason| StateReason::from_api(&reason))
+        .map(|reason| reason.as_str().to_string());
+
+    let (association_given, author_association) = stored_association(author_association);
 
-    let state_reason = state_reason.map(|i| i as i64);
-
-    let association_given = author_association.is_some();
-    let author_association = match author_association.unwrap_or(AuthorAssociation::None) {
-        AuthorAssociation::Collaborator => "Collaborator".to_string(),
-        AuthorAssociation::Contributor => "Contributor".to_string(),
-        AuthorAssociation::FirstTimer => "FirstTimer".to_string(),
-        AuthorAssociation::FirstTimeContributor => "FirstTimeContributor".to_string(),
-        AuthorAssociation::Mannequin => "Mannequin".to_string(),
-        AuthorAssociation::Member => "Member".to_string(),
-        AuthorAssociation::None => "None".to_string(),
-        AuthorAssociation::Owner => "Owner".to_string(),
-        AuthorAssociation::Other(o) => o,
-        _ => "Unknown".to_string(),
+    let state = match closed_at_timestamp {
+        Some(_) => ItemState::Closed,
+        None => ItemState::Open,
     };
 
     match txn.insert(IssuePullRequestShared {
@@ -518,28 +1322,48 @@ fn ensure_shared_exists(
         lock_reason: lock_reason.clone(),
         created_timestamp,
         updated_timestamp,
+        state: state.as_str(),
         closed_at_timestamp,
         repo,
-        state_reason,
+        state_reason: state_reason.clone(),
         closed_by,
         author_association: author_association.clone(),
+        comments_synced_timestamp: None::<i64>,
     }) {
         Ok(i) => {
             status.update(ProcessStatus::New);
+            let description = body.unwrap_or_default();
+            mentions::update(txn, i, None, &description, outdated_mentions);
+            search.push(SearchUpdate::Shared {
+                number: number as i64,
+                title: title.unwrap_or_default(),
+                description,
+            });
+            if let Some(closed_at) = closed_at_timestamp {
+                record_transition(txn, i, TransitionKind::Closed, closed_by, closed_at);
+            }
             i
         }
         Err(e) => {
             let mut shared = txn.mutable(e);
-            if let Some(title) = title {
-                update!(shared.title, title);
-            }
-            if let Some(body) = body {
-                update!(shared.description, body);
+            let was_closed_at = shared.closed_at_timestamp;
+            let title = title.unwrap_or_else(|| shared.title.clone());
+            let body = body.unwrap_or_else(|| shared.description.clone());
+            let body_changed = shared.description != body;
+            if shared.title != title || body_changed {
+                search.push(SearchUpdate::Shared {
+                    number: number as i64,
+                    title: title.clone(),
+                    description: body.clone(),
+                });
             }
+            update!(shared.title, title);
+            update!(shared.description, body.clone());
             update!(shared.lock_reason, lock_reason);
             update!(shared.author, user);
             update!(shared.created_timestamp, created_timestamp);
             update!(tracked: shared.updated_timestamp, updated_timestamp);
+            update!(shared.state, state.as_str().to_string());
             update!(shared.closed_at_timestamp, closed_at_timestamp);
             update!(shared.state_reason, state_reason);
             update!(shared.closed_by, closed_by);
@@ -547,17 +1371,62 @@ fn ensure_shared_exists(
             if association_given {
                 update!(shared.author_association, author_association);
             }
+            drop(shared);
+
+            if body_changed {
+                mentions::update(txn, e, None, &body, outdated_mentions);
+            }
+            match (was_closed_at, closed_at_timestamp) {
+                (Some(_), None) => {
+                    record_transition(txn, e, TransitionKind::Reopened, None, updated_timestamp)
+                }
+                // also when it was reopened and closed again since it was last seen,
+                // or fills in who closed it when that's known now
+                (_, Some(closed_at)) => {
+                    record_transition(txn, e, TransitionKind::Closed, closed_by, closed_at)
+                }
+                (None, None) => {}
+            }
             e
         }
     }
 }
 
+/// Adds the transition, or fills in its actor if it was already recorded without one.
+fn record_transition(
+    txn: &mut Transaction<Schema>,
+    issue_or_pr: TableRow<schema::IssuePullRequestShared>,
+    kind: TransitionKind,
+    actor: Option<TableRow<schema::User>>,
+    timestamp: i64,
+) {
+    use crate::schema::*;
+
+    if let Err(e) = txn.insert(StateTransition {
+        issue_or_pr,
+        kind: kind.as_str(),
+        actor,
+        timestamp,
+    }) && actor.is_some()
+    {
+        let mut transition = txn.mutable(e);
+        if transition.actor.is_none() {
+            transition.actor = actor;
+        }
+    }
+}
+
+/// Returns the users for which a review request was added,
+/// and the review requests that are outdated and should be deleted.
 fn update_review_requests(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
     pr: TableRow<schema::PullRequest>,
     users: Vec<TableRow<schema::User>>,
-) -> Vec<TableRow<schema::ReviewRequest>> {
+) -> (
+    Vec<TableRow<schema::User>>,
+    Vec<TableRow<schema::ReviewRequest>>,
+) {
     use crate::schema::*;
     gen_update!(status);
 
@@ -571,6 +1440,7 @@ fn update_review_requests(
         txn.mutable(i).outdated = 1;
     }
 
+    let mut added = Vec::new();
     for user in users {
         match txn.insert(ReviewRequest {
             user,
@@ -579,6 +1449,7 @@ fn update_review_requests(
         }) {
             Ok(i) => {
                 status.update(ProcessStatus::New);
+                added.push(user);
                 i
             }
             Err(e) => {
@@ -589,20 +1460,27 @@ fn update_review_requests(
         };
     }
 
-    txn.query(|rows| {
+    let outdated = txn.query(|rows| {
         let assignments = rows.join(ReviewRequest);
         rows.filter(assignments.pr.eq(pr));
         rows.filter(assignments.outdated.eq(1));
         rows.into_vec(assignments)
-    })
+    });
+
+    (added, outdated)
 }
 
+/// Returns the users that were newly assigned,
+/// and the assignments that are outdated and should be deleted.
 fn update_assignments(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
     shared: TableRow<schema::IssuePullRequestShared>,
     users: Vec<TableRow<schema::User>>,
-) -> Vec<TableRow<schema::Assignment>> {
+) -> (
+    Vec<TableRow<schema::User>>,
+    Vec<TableRow<schema::Assignment>>,
+) {
     use crate::schema::*;
     gen_update!(status);
 
@@ -616,6 +1494,7 @@ fn update_assignments(
         txn.mutable(i).outdated = 1;
     }
 
+    let mut added = Vec::new();
     for user in users {
         match txn.insert(Assignment {
             user,
@@ -624,6 +1503,7 @@ fn update_assignments(
         }) {
             Ok(i) => {
                 status.update(ProcessStatus::New);
+                added.push(user);
                 i
             }
             Err(e) => {
@@ -634,20 +1514,27 @@ fn update_assignments(
         };
     }
 
-    txn.query(|rows| {
+    let outdated = txn.query(|rows| {
         let assignments = rows.join(Assignment);
         rows.filter(assignments.issue_or_pr.eq(shared));
         rows.filter(assignments.outdated.eq(1));
         rows.into_vec(assignments)
-    })
+    });
+
+    (added, outdated)
 }
 
+/// Returns the labels that were newly added,
+/// and the label links that are outdated and should be deleted.
 fn update_label_assignments(
     txn: &mut Transaction<Schema>,
     _status: &mut ProcessStatus,
     shared: TableRow<schema::IssuePullRequestShared>,
     labels: Vec<TableRow<schema::Label>>,
-) -> Vec<TableRow<schema::Assignment>> {
+) -> (
+    Vec<TableRow<schema::Label>>,
+    Vec<TableRow<schema::LabelLink>>,
+) {
     use crate::schema::*;
     gen_update!(status);
 
@@ -661,6 +1548,7 @@ fn update_label_assignments(
         txn.mutable(i).outdated = 1;
     }
 
+    let mut added = Vec::new();
     for label in labels {
         match txn.insert(LabelLink {
             label,
@@ -669,6 +1557,7 @@ fn update_label_assignments(
         }) {
             Ok(i) => {
                 // status.update(ProcessStatus::New);
+                added.push(label);
                 i
             }
             Err(e) => {
@@ -679,17 +1568,21 @@ fn update_label_assignments(
         };
     }
 
-    txn.query(|rows| {
-        let assignments = rows.join(Assignment);
+    let outdated = txn.query(|rows| {
+        let assignments = rows.join(LabelLink);
         rows.filter(assignments.issue_or_pr.eq(shared));
         rows.filter(assignments.outdated.eq(1));
         rows.into_vec(assignments)
-    })
+    });
+
+    (added, outdated)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn ensure_pr_exists(
     txn: &mut Transaction<Schema>,
     status: &mut ProcessStatus,
+    changed: &mut Vec<&'static str>,
     shared: TableRow<schema::IssuePullRequestShared>,
     draft: bool,
     maintainer_can_modify: bool,
@@ -707,8 +1600,8 @@ fn ensure_pr_exists(
     mergeable_state: MergeableState,
 ) -> TableRow<schema::PullRequest> {
     use crate::schema::*;
-    gen_update!(status);
-    match txn.insert(PullRequest {
+    gen_update!(status, changed);
+    let pr = match txn.insert(PullRequest {
         shared,
         draft: draft as i64,
         maintainer_can_modify: maintainer_can_modify as i64,
@@ -716,14 +1609,14 @@ fn ensure_pr_exists(
         num_deletions,
         num_changed_files,
         num_commits,
-        merged_at_timestamp: merged_at_timestamp,
+        merged_at_timestamp,
         merge_commit_sha: merge_commit_sha.clone(),
-        merged_by: merged_by,
+        merged_by,
         head_sha: Some(head_sha.clone()),
         base_sha: Some(base_sha.clone()),
         mergeable: mergeable as i64,
         rebaseable: rebaseable as i64,
-        mergeable_state: mergeable_state.clone() as i64,
+        mergeable_state: mergeable_state.as_str().to_string(),
     }) {
         Ok(i) => {
             status.update(ProcessStatus::New);
@@ -744,10 +1637,15 @@ fn ensure_pr_exists(
             update!(pr.base_sha, Some(base_sha));
             update!(pr.mergeable, mergeable as i64);
             update!(pr.rebaseable, rebaseable as i64);
-            update!(pr.mergeable_state, mergeable_state as i64);
+            update!(pr.mergeable_state, mergeable_state.as_str().to_string());
             e
         }
+    };
+
+    if let Some(merged_at) = merged_at_timestamp {
+        record_transition(txn, shared, TransitionKind::Merged, merged_by, merged_at);
     }
+    pr
 }
 
 fn ensure_issue_exists(
@@ -769,3 +1667,215 @@ fn ensure_issue_exists(
         }
     }
 }
+
+/// GraphQL spells author associations like the REST api, values this crate doesn't
+/// know are stored as [`Unknown`](enums::AuthorAssociation::Unknown).
+fn parse_association(association: &str) -> String {
+    association
+        .parse()
+        .unwrap_or(enums::AuthorAssociation::Unknown)
+        .as_str()
+        .to_string()
+}
+
+/// The highest permission in a collaborator's permission flags.
+fn collaborator_permission(permissions: &Permissions) -> Permission {
+    if permissions.admin {
+        Permission::Admin
+    } else if permissions.maintain {
+        Permission::Maintain
+    } else if permissions.push {
+        Permission::Write
+    } else if permissions.triage {
+        Permission::Triage
+    } else {
+        Permission::Read
+    }
+}
+
+/// Teams have the api's older names for read and write access. Custom roles are stored
+/// as [`Read`](Permission::Read), which they include.
+fn team_permission(permission: &str) -> Permission {
+    match permission {
+        "pull" => Permission::Read,
+        "push" => Permission::Write,
+        other => other.parse().unwrap_or(Permission::Read),
+    }
+}
+
+/// The user behind a GraphQL actor. Deleted accounts, and actors without the id
+/// the REST api uses, are the [ghost](crate::GHOST_LOGIN).
+fn ensure_actor_exists(
+    txn: &mut Transaction<Schema>,
+    status: &mut ProcessStatus,
+    actor: Option<Actor>,
+) -> TableRow<schema::User> {
+    let Some(Actor {
+        login,
+        database_id: Some(id),
+        typename,
+    }) = actor
+    else {
+        return ensure_ghost_exists(txn, status);
+    };
+    let mut user = json::user(&login, id, None);
+    user["type"] = typename.into();
+    let user = serde_json::from_value(user).expect("actor is a valid user");
+    ensure_user_exists(txn, status, user)
+}
+
+fn ensure_discussion_category_exists(
+    txn: &mut Transaction<Schema>,
+    repo: TableRow<schema::Repo>,
+    DiscussionCategory {
+        id,
+        name,
+        slug,
+        emoji,
+        is_answerable,
+    }: DiscussionCategory,
+) -> TableRow<schema::DiscussionCategory> {
+    use crate::schema::*;
+
+    match txn.insert(DiscussionCategory {
+        node_id: id,
+        repo,
+        name: name.clone(),
+        slug: slug.clone(),
+        emoji: emoji.clone(),
+        answerable: is_answerable as i64,
+    }) {
+        Ok(i) => i,
+        Err(e) => {
+            // categories can be renamed
+            let mut category = txn.mutable(e);
+            category.name = name;
+            category.slug = slug;
+            category.emoji = emoji;
+            category.answerable = is_answerable as i64;
+            e
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ensure_discussion_exists(
+    txn: &mut Transaction<Schema>,
+    status: &mut ProcessStatus,
+    repo: TableRow<schema::Repo>,
+    number: u64,
+    node_id: String,
+    category: TableRow<schema::DiscussionCategory>,
+    title: String,
+    body: String,
+    author: TableRow<schema::User>,
+    author_association: String,
+    html_url: String,
+    created_timestamp: i64,
+    updated_timestamp: i64,
+    closed_at_timestamp: Option<i64>,
+    locked: bool,
+    answer_chosen_timestamp: Option<i64>,
+    answer_chosen_by: Option<TableRow<schema::User>>,
+) -> TableRow<schema::Discussion> {
+    use crate::schema::*;
+    gen_update!(status);
+
+    match txn.insert(Discussion {
+        repo,
+        number: number as i64,
+        node_id: node_id.clone(),
+        category,
+        title: title.clone(),
+        body: body.clone(),
+        author,
+        author_association: author_association.clone(),
+        html_url: html_url.clone(),
+        created_timestamp,
+        updated_timestamp,
+        closed_at_timestamp,
+        locked: locked as i64,
+        answer_chosen_timestamp,
+        answer_chosen_by,
+    }) {
+        Ok(i) => {
+            status.update(ProcessStatus::New);
+            i
+        }
+        Err(e) => {
+            let mut discussion = txn.mutable(e);
+            update!(discussion.node_id, node_id);
+            update!(discussion.category, category);
+            update!(discussion.title, title);
+            update!(discussion.body, body);
+            update!(discussion.author, author);
+            update!(discussion.author_association, author_association);
+            update!(discussion.html_url, html_url);
+            update!(discussion.created_timestamp, created_timestamp);
+            update!(tracked: discussion.updated_timestamp, updated_timestamp);
+            update!(discussion.closed_at_timestamp, closed_at_timestamp);
+            update!(discussion.locked, locked as i64);
+            update!(discussion.answer_chosen_timestamp, answer_chosen_timestamp);
+            update!(discussion.answer_chosen_by, answer_chosen_by);
+            e
+        }
+    }
+}
+
+/// Store a comment with the replies to it that were listed with it.
+///
+/// Comments aren't deleted when they're gone from the listing, like on issues.
+fn ensure_discussion_comment_exists(
+    txn: &mut Transaction<Schema>,
+    status: &mut ProcessStatus,
+    discussion: TableRow<schema::Discussion>,
+    parent: Option<TableRow<schema::DiscussionComment>>,
+    DiscussionComment {
+        id,
+        body,
+        url,
+        author,
+        author_association,
+        created_at,
+        updated_at,
+        is_answer,
+        replies,
+    }: DiscussionComment,
+) -> TableRow<schema::DiscussionComment> {
+    use crate::schema::*;
+    gen_update!(status);
+
+    let author = ensure_actor_exists(txn, status, author);
+    let author_association = parse_association(&author_association);
+    let row = match txn.insert(DiscussionComment {
+        node_id: id,
+        discussion,
+        parent,
+        author,
+        author_association: author_association.clone(),
+        body: body.clone(),
+        html_url: url,
+        created_timestamp: created_at.timestamp(),
+        updated_timestamp: updated_at.timestamp(),
+        is_answer: is_answer as i64,
+    }) {
+        Ok(i) => {
+            status.update(ProcessStatus::Updated);
+            i
+        }
+        Err(e) => {
+            let mut comment = txn.mutable(e);
+            update!(comment.author, author);
+            update!(comment.author_association, author_association);
+            update!(tracked: comment.body, body);
+            update!(tracked: comment.updated_timestamp, updated_at.timestamp());
+            update!(tracked: comment.is_answer, is_answer as i64);
+            e
+        }
+    };
+
+    for reply in replies.nodes {
+        ensure_discussion_comment_exists(txn, status, discussion, Some(row), reply);
+    }
+    row
+}
@@ -0,0 +1,145 @@
+//! Enums that are stored as text in the [`schema`](crate::schema) tables.
+//!
+//! They're stored in GitHub's own spelling, like `not_planned` and `FIRST_TIME_CONTRIBUTOR`,
+//! which doesn't depend on the version of octocrab. Read a column with [`str::parse`],
+//! and compare it against [`as_str`](StateReason::as_str) in queries.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::Serialize;
+
+macro_rules! stored_enum {
+    (
+        $(#[$meta: meta])*
+        $name: ident {
+            $($(#[$variant_meta: meta])* $variant: ident = $text: literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+        }
+
+        impl $name {
+            /// The text stored in the database.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $text,)*
+                }
+            }
+
+            /// Converts the octocrab enum by the name GitHub gives the value,
+            /// None for values this enum doesn't have.
+            // not every enum has an octocrab counterpart
+            #[allow(dead_code)]
+            pub(crate) fn from_api(value: &impl Serialize) -> Option<Self> {
+                match serde_json::to_value(value) {
+                    Ok(serde_json::Value::String(text)) => text.parse().ok(),
+                    _ => None,
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($text => Ok(Self::$variant),)*
+                    _ => Err(()),
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+stored_enum! {
+    /// Why an issue or pull request was closed, the `state_reason` column.
+    StateReason {
+        Completed = "completed",
+        NotPlanned = "not_planned",
+        Reopened = "reopened",
+        Duplicate = "duplicate",
+    }
+}
+
+stored_enum! {
+    /// Whether a pull request can be merged, the `mergeable_state` column.
+    MergeableState {
+        Behind = "behind",
+        Blocked = "blocked",
+        Clean = "clean",
+        Dirty = "dirty",
+        Draft = "draft",
+        HasHooks = "has_hooks",
+        Unstable = "unstable",
+        /// Not determined yet, or not known to this crate.
+        Unknown = "unknown",
+    }
+}
+
+stored_enum! {
+    /// How the author of an issue or pull request is related to its repository,
+    /// the `author_association` column.
+    AuthorAssociation {
+        Collaborator = "COLLABORATOR",
+        Contributor = "CONTRIBUTOR",
+        FirstTimer = "FIRST_TIMER",
+        FirstTimeContributor = "FIRST_TIME_CONTRIBUTOR",
+        Mannequin = "MANNEQUIN",
+        Member = "MEMBER",
+        None = "NONE",
+        Owner = "OWNER",
+        /// Stored before the association was synced, or not known to this crate.
+        Unknown = "UNKNOWN",
+    }
+}
+
+stored_enum! {
+    /// Whether an issue or pull request is open, the `state` column.
+    ItemState {
+        Open = "open",
+        Closed = "closed",
+    }
+}
+
+stored_enum! {
+    /// What happened in a [`StateTransition`](crate::schema::StateTransition), the `kind` column.
+    TransitionKind {
+        Closed = "closed",
+        Reopened = "reopened",
+        Merged = "merged",
+    }
+}
+
+stored_enum! {
+    /// What kind of account a user is, the `kind` column of [`User`](crate::schema::User).
+    UserKind {
+        User = "User",
+        Bot = "Bot",
+        Organization = "Organization",
+        Mannequin = "Mannequin",
+    }
+}
+
+stored_enum! {
+    /// The access a user or team has to a repository, the `permission` column of
+    /// [`Collaborator`](crate::schema::Collaborator) and [`RepoTeam`](crate::schema::RepoTeam).
+    ///
+    /// Ordered from least to most access, each level includes the ones below it.
+    #[derive(PartialOrd, Ord)]
+    Permission {
+        Read = "read",
+        Triage = "triage",
+        Write = "write",
+        Maintain = "maintain",
+        Admin = "admin",
+    }
+}
@@ -0,0 +1,111 @@
+use tokio::sync::broadcast;
+
+use crate::{GithubDb, Repo};
+
+/// Something that changed in the database.
+///
+/// Events are sent after the transaction that made the change has committed,
+/// so the change is always visible to queries by the time an event arrives.
+#[derive(Debug, Clone)]
+pub enum Event {
+    IssueCreated {
+        repo: Repo,
+        number: u64,
+    },
+    IssueUpdated {
+        repo: Repo,
+        number: u64,
+        /// Names of the columns that changed
+        changed_fields: Vec<&'static str>,
+    },
+    PrCreated {
+        repo: Repo,
+        number: u64,
+    },
+    PrUpdated {
+        repo: Repo,
+        number: u64,
+        /// Names of the columns that changed
+        changed_fields: Vec<&'static str>,
+    },
+    CommentAdded {
+        repo: Repo,
+        issue_number: u64,
+        comment_id: u64,
+    },
+    CommentEdited {
+        repo: Repo,
+        issue_number: u64,
+        comment_id: u64,
+    },
+    LabelAdded {
+        repo: Repo,
+        number: u64,
+        label: String,
+    },
+    LabelRemoved {
+        repo: Repo,
+        number: u64,
+        label: String,
+    },
+    Assigned {
+        repo: Repo,
+        number: u64,
+        user: String,
+    },
+    Unassigned {
+        repo: Repo,
+        number: u64,
+        user: String,
+    },
+    ReviewRequested {
+        repo: Repo,
+        number: u64,
+        reviewer: String,
+    },
+    ReviewRequestRemoved {
+        repo: Repo,
+        number: u64,
+        reviewer: String,
+    },
+}
+
+/// Stream of [`Event`]s, created with [`GithubDb::subscribe`].
+pub struct EventStream {
+    rx: broadcast::Receiver<Event>,
+}
+
+impl EventStream {
+    /// Wait for the next event. Returns `None` once the [`GithubDb`] is dropped.
+    ///
+    /// A subscriber that can't keep up skips the events it missed
+    /// (with a warning) rather than slowing down the scraper.
+    pub async fn next(&mut self) -> Option<Event> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("event subscriber lagged behind, skipped {n} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl GithubDb {
+    /// Subscribe to changes in the database. Only events that happen after
+    /// subscribing are received.
+    pub fn subscribe(&self) -> EventStream {
+        EventStream {
+            rx: self.events.subscribe(),
+        }
+    }
+
+    pub(crate) fn emit(&self, events: Vec<Event>) {
+        for event in events {
+            // an error only means that nobody is subscribed
+            let _ = self.events.send(event);
+        }
+    }
+}
@@ -0,0 +1,652 @@
+//! Dumps of the database for analysis elsewhere, like in pandas or DuckDB.
+//!
+//! Every table becomes one file in the export directory:
+//!
+//! - `issues`: issues and pull requests, like GitHub's issues api
+//! - `pull_requests`: the columns only pull requests have, by `repo` and `number`
+//! - `comments`, `users`, `labels`
+//! - `label_links`, `assignments`, `review_requests` and `issue_links`, by `repo` and `number`
+//! - `state_transitions`: every close, reopen and merge, by `repo` and `number`
+//!
+//! Timestamps are RFC 3339 strings and [enums](crate::enums) are written like they're stored,
+//! like `not_planned`.
+
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rust_query::{Lazy, Transaction};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GithubDb,
+    database::schema::{self, Schema},
+    filter::timestamp,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One json object per line
+    Jsonl,
+    /// With a header row, empty fields for missing values
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Only export issues, pull requests and comments updated at or after this time,
+    /// together with their links. Users and labels are always exported completely.
+    ///
+    /// Links that were removed aren't exported, so the links of every exported
+    /// issue or pull request replace the ones from earlier exports.
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Jsonl,
+            since: None,
+        }
+    }
+}
+
+/// Returned by [`GithubDb::export`].
+#[derive(Clone, Debug, Default)]
+pub struct ExportSummary {
+    /// Every file that was written, with its number of rows
+    pub files: Vec<(PathBuf, usize)>,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Csv(csv::Error),
+    #[cfg(feature = "parquet")]
+    Parquet(String),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{e}"),
+            ExportError::Csv(e) => write!(f, "{e}"),
+            #[cfg(feature = "parquet")]
+            ExportError::Parquet(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<io::Error> for ExportError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(value: csv::Error) -> Self {
+        Self::Csv(value)
+    }
+}
+
+impl GithubDb {
+    /// Write every table to a file in `dir`, see the [module documentation](crate::export).
+    ///
+    /// Everything is read in one transaction, so the files are consistent with each other
+    /// even while syncing continues.
+    pub async fn export(
+        &self,
+        dir: impl AsRef<Path>,
+        options: ExportOptions,
+    ) -> Result<ExportSummary, ExportError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        self.db
+            .transaction(move |txn| {
+                let mut export = Export {
+                    txn,
+                    dir,
+                    format: options.format,
+                    since: options.since.map_or(i64::MIN, |since| since.timestamp()),
+                    summary: ExportSummary::default(),
+                };
+                export.issues()?;
+                export.pull_requests()?;
+                export.comments()?;
+                export.users()?;
+                export.labels()?;
+                export.links()?;
+                export.state_transitions()?;
+                Ok(export.summary)
+            })
+            .await
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct IssueRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) is_pull_request: bool,
+    pub(crate) title: String,
+    pub(crate) body: String,
+    pub(crate) author: String,
+    pub(crate) author_id: i64,
+    pub(crate) author_association: String,
+    pub(crate) state: String,
+    pub(crate) state_reason: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
+    pub(crate) closed_at: Option<String>,
+    pub(crate) closed_by: Option<String>,
+    pub(crate) locked: bool,
+    pub(crate) lock_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct PullRequestRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) draft: bool,
+    pub(crate) merged_at: Option<String>,
+    pub(crate) merged_by: Option<String>,
+    pub(crate) merge_commit_sha: Option<String>,
+    pub(crate) head_sha: Option<String>,
+    pub(crate) base_sha: Option<String>,
+    pub(crate) mergeable_state: String,
+    pub(crate) mergeable: bool,
+    pub(crate) rebaseable: bool,
+    pub(crate) maintainer_can_modify: bool,
+    pub(crate) additions: i64,
+    pub(crate) deletions: i64,
+    pub(crate) changed_files: i64,
+    pub(crate) commits: i64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct CommentRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) comment_id: i64,
+    pub(crate) author: String,
+    pub(crate) author_id: i64,
+    pub(crate) body: String,
+    pub(crate) created_at: String,
+    pub(crate) updated_at: String,
+    /// Missing in exports made before comments had it
+    #[serde(default)]
+    pub(crate) author_association: String,
+    pub(crate) html_url: Option<String>,
+    pub(crate) node_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct UserRecord {
+    pub(crate) github_id: i64,
+    pub(crate) login: String,
+    pub(crate) display_name: String,
+    pub(crate) kind: Option<String>,
+    pub(crate) company: Option<String>,
+    pub(crate) location: Option<String>,
+    pub(crate) created_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct LabelRecord {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) color: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct LabelLinkRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) label: String,
+}
+
+/// Used for both assignments and review requests.
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct UserLinkRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) user: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct IssueLinkRecord {
+    pub(crate) repo: String,
+    pub(crate) from_number: i64,
+    pub(crate) to_number: i64,
+    pub(crate) pr_closes_issue: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct StateTransitionRecord {
+    pub(crate) repo: String,
+    pub(crate) number: i64,
+    pub(crate) kind: String,
+    pub(crate) actor: Option<String>,
+    pub(crate) at: String,
+}
+
+struct Export<'t> {
+    txn: &'t Transaction<Schema>,
+    dir: PathBuf,
+    format: ExportFormat,
+    /// Unix timestamp, `i64::MIN` for a full export
+    since: i64,
+    summary: ExportSummary,
+}
+
+impl Export<'_> {
+    fn write<R: Serialize + Default>(
+        &mut self,
+        name: &str,
+        records: impl Iterator<Item = R>,
+    ) -> Result<(), ExportError> {
+        let path = self.dir.join(name).with_extension(self.format.extension());
+        let file = File::create(&path)?;
+
+        let rows = match self.format {
+            ExportFormat::Jsonl => {
+                let mut out = BufWriter::new(file);
+                let mut rows = 0;
+                for record in records {
+                    serde_json::to_writer(&mut out, &record).map_err(io::Error::from)?;
+                    out.write_all(b"\n")?;
+                    rows += 1;
+                }
+                out.flush()?;
+                rows
+            }
+            ExportFormat::Csv => {
+                let mut out = csv::Writer::from_writer(file);
+                let mut rows = 0;
+                for record in records {
+                    out.serialize(record)?;
+                    rows += 1;
+                }
+                if rows == 0 {
+                    // csv only writes the header together with the first record
+                    let mut header = csv::Writer::from_writer(Vec::new());
+                    header.serialize(R::default())?;
+                    let header = header.into_inner().map_err(|e| e.into_error())?;
+                    let end = header
+                        .iter()
+                        .position(|b| *b == b'\n')
+                        .unwrap_or(header.len());
+                    out.write_record(header[..end].split(|b| *b == b','))?;
+                }
+                out.flush()?;
+                rows
+            }
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => parquet_file::write(file, records)?,
+        };
+
+        self.summary.files.push((path, rows));
+        Ok(())
+    }
+
+    fn issues(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let rows = txn.query(|rows| {
+            let shared = rows.join(IssuePullRequestShared);
+            rows.filter(shared.updated_timestamp.gte(self.since));
+            rows.into_vec(shared)
+        });
+        self.write(
+            "issues",
+            rows.into_iter().map(|row| {
+                let is_pull_request = txn.query_one(PullRequest.shared(row)).is_some();
+                let shared = txn.lazy(row);
+                IssueRecord {
+                    repo: repo_name(&shared.repo),
+                    number: shared.number,
+                    is_pull_request,
+                    title: shared.title.clone(),
+                    body: shared.description.clone(),
+                    author: shared.author.name.clone(),
+                    author_id: shared.author.github_id,
+                    author_association: shared.author_association.clone(),
+                    state: shared.state.clone(),
+                    state_reason: shared.state_reason.clone(),
+                    created_at: rfc3339(shared.created_timestamp),
+                    updated_at: rfc3339(shared.updated_timestamp),
+                    closed_at: shared.closed_at_timestamp.map(rfc3339),
+                    closed_by: shared.closed_by.as_ref().map(|user| user.name.clone()),
+                    locked: shared.lock_reason.is_some(),
+                    lock_reason: shared.lock_reason.clone().filter(|r| !r.is_empty()),
+                }
+            }),
+        )
+    }
+
+    fn pull_requests(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let rows = txn.query(|rows| {
+            let pr = rows.join(PullRequest);
+            rows.filter(pr.shared.updated_timestamp.gte(self.since));
+            rows.into_vec(pr)
+        });
+        self.write(
+            "pull_requests",
+            rows.into_iter().map(|row| {
+                let pr = txn.lazy(row);
+                PullRequestRecord {
+                    repo: repo_name(&pr.shared.repo),
+                    number: pr.shared.number,
+                    draft: pr.draft != 0,
+                    merged_at: pr.merged_at_timestamp.map(rfc3339),
+                    merged_by: pr.merged_by.as_ref().map(|user| user.name.clone()),
+                    merge_commit_sha: pr.merge_commit_sha.clone(),
+                    head_sha: pr.head_sha.clone(),
+                    base_sha: pr.base_sha.clone(),
+                    mergeable_state: pr.mergeable_state.clone(),
+                    mergeable: pr.mergeable != 0,
+                    rebaseable: pr.rebaseable != 0,
+                    maintainer_can_modify: pr.maintainer_can_modify != 0,
+                    additions: pr.num_additions,
+                    deletions: pr.num_deletions,
+                    changed_files: pr.num_changed_files,
+                    commits: pr.num_commits,
+                }
+            }),
+        )
+    }
+
+    fn comments(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let rows = txn.query(|rows| {
+            let comment = rows.join(Comment);
+            rows.filter(comment.updated_timestamp.gte(self.since));
+            rows.into_vec(comment)
+        });
+        self.write(
+            "comments",
+            rows.into_iter().map(|row| {
+                let comment = txn.lazy(row);
+                CommentRecord {
+                    repo: repo_name(&comment.issue_or_pr.repo),
+                    number: comment.issue_or_pr.number,
+                    comment_id: comment.comment_id,
+                    author: comment.author.name.clone(),
+                    author_id: comment.author.github_id,
+                    body: comment.text.clone(),
+                    created_at: rfc3339(comment.created_timestamp),
+                    updated_at: rfc3339(comment.updated_timestamp),
+                    author_association: comment.author_association.clone(),
+                    html_url: comment.html_url.clone(),
+                    node_id: comment.node_id.clone(),
+                }
+            }),
+        )
+    }
+
+    fn users(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let rows = txn.query(|rows| {
+            let user = rows.join(User);
+            rows.into_vec(user)
+        });
+        self.write(
+            "users",
+            rows.into_iter().map(|row| {
+                let user = txn.lazy(row);
+                UserRecord {
+                    github_id: user.github_id,
+                    login: user.name.clone(),
+                    display_name: user.display_name.clone(),
+                    kind: user.kind.clone(),
+                    company: user.company.clone(),
+                    location: user.location.clone(),
+                    created_at: user.account_created_timestamp.map(rfc3339),
+                }
+            }),
+        )
+    }
+
+    fn labels(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let labels = self.txn.query(|rows| {
+            let label = rows.join(Label);
+            rows.into_iter((&label.name, (&label.description, &label.color)))
+                .map(|(name, (description, color))| LabelRecord {
+                    name,
+                    description,
+                    color,
+                })
+                .collect::<Vec<_>>()
+        });
+        self.write("labels", labels.into_iter())
+    }
+
+    fn links(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let since = self.since;
+
+        let label_links = txn.query(|rows| {
+            let link = rows.join(LabelLink);
+            rows.filter(link.outdated.eq(0));
+            rows.filter(link.issue_or_pr.updated_timestamp.gte(since));
+            rows.into_vec(link)
+        });
+        self.write(
+            "label_links",
+            label_links.into_iter().map(|row| {
+                let link = txn.lazy(row);
+                LabelLinkRecord {
+                    repo: repo_name(&link.issue_or_pr.repo),
+                    number: link.issue_or_pr.number,
+                    label: link.label.name.clone(),
+                }
+            }),
+        )?;
+
+        let assignments = txn.query(|rows| {
+            let assignment = rows.join(Assignment);
+            rows.filter(assignment.outdated.eq(0));
+            rows.filter(assignment.issue_or_pr.updated_timestamp.gte(since));
+            rows.into_vec(assignment)
+        });
+        self.write(
+            "assignments",
+            assignments.into_iter().map(|row| {
+                let assignment = txn.lazy(row);
+                UserLinkRecord {
+                    repo: repo_name(&assignment.issue_or_pr.repo),
+                    number: assignment.issue_or_pr.number,
+                    user: assignment.user.name.clone(),
+                }
+            }),
+        )?;
+
+        let review_requests = txn.query(|rows| {
+            let request = rows.join(ReviewRequest);
+            rows.filter(request.outdated.eq(0));
+            rows.filter(request.pr.shared.updated_timestamp.gte(since));
+            rows.into_vec(request)
+        });
+        self.write(
+            "review_requests",
+            review_requests.into_iter().map(|row| {
+                let request = txn.lazy(row);
+                UserLinkRecord {
+                    repo: repo_name(&request.pr.shared.repo),
+                    number: request.pr.shared.number,
+                    user: request.user.name.clone(),
+                }
+            }),
+        )?;
+
+        let issue_links = txn.query(|rows| {
+            let link = rows.join(IssuePrLink);
+            rows.filter(link.from.updated_timestamp.gte(since));
+            rows.into_vec(link)
+        });
+        self.write(
+            "issue_links",
+            issue_links.into_iter().map(|row| {
+                let link = txn.lazy(row);
+                IssueLinkRecord {
+                    repo: repo_name(&link.from.repo),
+                    from_number: link.from.number,
+                    to_number: link.to.number,
+                    pr_closes_issue: link.pr_closes_issue != 0,
+                }
+            }),
+        )
+    }
+
+    fn state_transitions(&mut self) -> Result<(), ExportError> {
+        use schema::*;
+
+        let txn = self.txn;
+        let rows = txn.query(|rows| {
+            let transition = rows.join(StateTransition);
+            rows.filter(transition.issue_or_pr.updated_timestamp.gte(self.since));
+            rows.order_by()
+                .asc(&transition.timestamp)
+                .asc(&transition.kind)
+                .into_iter(&transition)
+                .collect::<Vec<_>>()
+        });
+        self.write(
+            "state_transitions",
+            rows.into_iter().map(|row| {
+                let transition = txn.lazy(row);
+                StateTransitionRecord {
+                    repo: repo_name(&transition.issue_or_pr.repo),
+                    number: transition.issue_or_pr.number,
+                    kind: transition.kind.clone(),
+                    actor: transition.actor.as_ref().map(|user| user.name.clone()),
+                    at: rfc3339(transition.timestamp),
+                }
+            }),
+        )
+    }
+}
+
+fn repo_name(repo: &Lazy<'_, schema::Repo>) -> String {
+    format!("{}/{}", repo.organization, repo.name)
+}
+
+fn rfc3339(t: i64) -> String {
+    timestamp(t).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_file {
+    use std::{fs::File, sync::Arc};
+
+    use arrow_json::reader::{ReaderBuilder, infer_json_schema_from_iterator};
+    use arrow_schema::{ArrowError, DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+    use serde::Serialize;
+
+    use super::ExportError;
+
+    const BATCH: usize = 8192;
+
+    impl From<parquet::errors::ParquetError> for ExportError {
+        fn from(value: parquet::errors::ParquetError) -> Self {
+            Self::Parquet(value.to_string())
+        }
+    }
+
+    impl From<ArrowError> for ExportError {
+        fn from(value: ArrowError) -> Self {
+            Self::Parquet(value.to_string())
+        }
+    }
+
+    pub fn write<R: Serialize>(
+        file: File,
+        records: impl Iterator<Item = R>,
+    ) -> Result<usize, ExportError> {
+        let mut records = records.peekable();
+        let mut writer: Option<(ArrowWriter<File>, Arc<Schema>)> = None;
+        let mut rows = 0;
+
+        loop {
+            let batch: Vec<R> = records.by_ref().take(BATCH).collect();
+            if batch.is_empty() && writer.is_some() {
+                break;
+            }
+
+            let (out, schema) = match &mut writer {
+                Some(writer) => writer,
+                None => {
+                    let schema = Arc::new(schema(&batch)?);
+                    let out = ArrowWriter::try_new(file.try_clone()?, schema.clone(), None)?;
+                    writer.insert((out, schema))
+                }
+            };
+
+            let mut decoder = ReaderBuilder::new(schema.clone()).build_decoder()?;
+            decoder.serialize(&batch)?;
+            if let Some(batch) = decoder.flush()? {
+                out.write(&batch)?;
+            }
+            rows += batch.len();
+        }
+
+        if let Some((out, _)) = writer {
+            out.close()?;
+        }
+        Ok(rows)
+    }
+
+    /// The schema of the records, inferred from the first batch.
+    ///
+    /// All nullable columns hold strings, so columns that are always null
+    /// in the first batch become nullable strings.
+    fn schema<R: Serialize>(batch: &[R]) -> Result<Schema, ExportError> {
+        let values = batch.iter().map(|record| {
+            serde_json::to_value(record).map_err(|e| ArrowError::JsonError(e.to_string()))
+        });
+        let inferred = infer_json_schema_from_iterator(values)?;
+        Ok(Schema::new(
+            inferred
+                .fields()
+                .iter()
+                .map(|field| match field.data_type() {
+                    DataType::Null => Field::new(field.name(), DataType::Utf8, true),
+                    _ => field.as_ref().clone(),
+                })
+                .collect::<Vec<_>>(),
+        ))
+    }
+}
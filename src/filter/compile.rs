@@ -0,0 +1,143 @@
+use rust_query::{Expr, IntoExpr, TableRow, Transaction, aggregate};
+
+use crate::{
+    database::schema::{self, Schema},
+    enums::ItemState,
+    filter::{DateField, Filter, ItemSummary, SortField, TermKind},
+};
+
+type Shared<'t> = Expr<'t, Schema, schema::IssuePullRequestShared>;
+
+pub(super) fn find(txn: &Transaction<Schema>, filter: &Filter, limit: usize) -> Vec<ItemSummary> {
+    use schema::*;
+
+    let rows: Vec<TableRow<IssuePullRequestShared>> = txn.query(|rows| {
+        let shared = rows.join(IssuePullRequestShared);
+        for term in &filter.terms {
+            let condition = condition(&term.kind, &shared);
+            rows.filter(if term.negated {
+                condition.not()
+            } else {
+                condition
+            });
+        }
+
+        let key = match filter.sort.field {
+            SortField::Created => &shared.created_timestamp,
+            SortField::Updated => &shared.updated_timestamp,
+        };
+        let order = rows.order_by();
+        let order = if filter.sort.descending {
+            order.desc(key).desc(&shared.number)
+        } else {
+            order.asc(key).asc(&shared.number)
+        };
+        order.into_iter(&shared).take(limit).collect()
+    });
+
+    rows.into_iter()
+        .map(|row| ItemSummary::load(txn, row))
+        .collect()
+}
+
+/// Whether `shared` matches the (not negated) term.
+fn condition<'t>(kind: &TermKind, shared: &Shared<'t>) -> Expr<'t, Schema, bool> {
+    use schema::*;
+
+    match kind {
+        TermKind::Text(word) => {
+            let word = word.to_lowercase();
+            shared
+                .title
+                .lower()
+                .contains(word.as_str())
+                .or(shared.description.lower().contains(word))
+        }
+        TermKind::Open => shared.state.eq(ItemState::Open.as_str()),
+        TermKind::Locked => shared.lock_reason.is_some(),
+        TermKind::PullRequest => aggregate(|rows| {
+            let pr = rows.join(PullRequest);
+            rows.filter(pr.shared.eq(shared));
+            rows.exists()
+        }),
+        TermKind::Merged => aggregate(|rows| {
+            let pr = rows.join(PullRequest);
+            rows.filter(pr.shared.eq(shared));
+            rows.filter(pr.merged_at_timestamp.is_some());
+            rows.exists()
+        }),
+        TermKind::Draft => aggregate(|rows| {
+            let pr = rows.join(PullRequest);
+            rows.filter(pr.shared.eq(shared));
+            rows.filter(pr.draft.eq(1));
+            rows.exists()
+        }),
+        TermKind::Label(name) => label(shared, Some(name)),
+        TermKind::NoLabel => label(shared, None).not(),
+        TermKind::Assignee(login) => assignee(shared, Some(login)),
+        TermKind::NoAssignee => assignee(shared, None).not(),
+        TermKind::ReviewRequested(login) => review_requested(shared, Some(login)),
+        TermKind::NoReviewRequest => review_requested(shared, None).not(),
+        TermKind::Author(login) => shared.author.name.lower().eq(login.to_lowercase()),
+        TermKind::Repo(repo) => shared
+            .repo
+            .organization
+            .eq(repo.organization.as_str())
+            .and(shared.repo.name.eq(repo.name.as_str())),
+        TermKind::Date { field, from, to } => {
+            let (timestamp, mut condition) = match field {
+                DateField::Created => (shared.created_timestamp.clone(), true.into_expr()),
+                DateField::Updated => (shared.updated_timestamp.clone(), true.into_expr()),
+                DateField::Closed => (
+                    shared.closed_at_timestamp.unwrap_or(0),
+                    shared.closed_at_timestamp.is_some(),
+                ),
+            };
+            if let Some(from) = from {
+                condition = condition.and(timestamp.gte(*from));
+            }
+            if let Some(to) = to {
+                condition = condition.and(timestamp.lt(*to));
+            }
+            condition
+        }
+    }
+}
+
+/// Whether `shared` has the label `name`, or any label if `name` is `None`.
+/// Label names are case-insensitive like on GitHub.
+fn label<'t>(shared: &Shared<'t>, name: Option<&String>) -> Expr<'t, Schema, bool> {
+    aggregate(|rows| {
+        let link = rows.join(schema::LabelLink);
+        rows.filter(link.issue_or_pr.eq(shared));
+        rows.filter(link.outdated.eq(0));
+        if let Some(name) = name {
+            rows.filter(link.label.name.lower().eq(name.to_lowercase()));
+        }
+        rows.exists()
+    })
+}
+
+fn assignee<'t>(shared: &Shared<'t>, login: Option<&String>) -> Expr<'t, Schema, bool> {
+    aggregate(|rows| {
+        let assignment = rows.join(schema::Assignment);
+        rows.filter(assignment.issue_or_pr.eq(shared));
+        rows.filter(assignment.outdated.eq(0));
+        if let Some(login) = login {
+            rows.filter(assignment.user.name.lower().eq(login.to_lowercase()));
+        }
+        rows.exists()
+    })
+}
+
+fn review_requested<'t>(shared: &Shared<'t>, login: Option<&String>) -> Expr<'t, Schema, bool> {
+    aggregate(|rows| {
+        let request = rows.join(schema::ReviewRequest);
+        rows.filter(request.pr.shared.eq(shared));
+        rows.filter(request.outdated.eq(0));
+        if let Some(login) = login {
+            rows.filter(request.user.name.lower().eq(login.to_lowercase()));
+        }
+        rows.exists()
+    })
+}
@@ -0,0 +1,180 @@
+//! Filters in the syntax of GitHub's issue search, like
+//! `is:pr is:open label:T-compiler -label:S-blocked updated:>2025-01-01`,
+//! evaluated against the local database.
+//!
+//! Supported qualifiers:
+//! - `is:open`, `is:closed`, `is:pr`, `is:issue`, `is:merged`, `is:draft`, `is:locked`, `state:open`, `state:closed`
+//! - `label:NAME`, `author:LOGIN`, `assignee:LOGIN`, `review-requested:LOGIN`, `repo:OWNER/NAME`
+//! - `no:label`, `no:assignee`, `no:review-requested`
+//! - `created:`, `updated:` and `closed:` with `>D`, `>=D`, `<D`, `<=D`, `D..D` or `D`,
+//!   where `D` is `YYYY-MM-DD`, an RFC 3339 timestamp or `*` in ranges
+//! - `sort:created`, `sort:updated`, optionally followed by `-asc` or `-desc`
+//!
+//! Every qualifier can be negated with a leading `-`. Values with spaces can be quoted:
+//! `label:"good first issue"`. Other words must appear in the title or description.
+
+use chrono::{DateTime, Utc};
+use rust_query::{TableRow, Transaction};
+
+use crate::{
+    GithubDb, ItemKind, Repo,
+    database::schema::{self, Schema},
+    enums::{AuthorAssociation, ItemState, StateReason},
+};
+
+mod compile;
+mod parse;
+
+pub use parse::FilterError;
+
+/// A parsed filter, see the [module documentation](self) for the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    terms: Vec<Term>,
+    sort: Sort,
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Self, FilterError> {
+        parse::parse(query)
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    kind: TermKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TermKind {
+    /// A word in the title or description
+    Text(String),
+    Open,
+    PullRequest,
+    Merged,
+    Draft,
+    Locked,
+    NoLabel,
+    NoAssignee,
+    NoReviewRequest,
+    Label(String),
+    Author(String),
+    Assignee(String),
+    ReviewRequested(String),
+    Repo(Repo),
+    /// Half open range of unix timestamps
+    Date {
+        field: DateField,
+        from: Option<i64>,
+        to: Option<i64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateField {
+    Created,
+    Updated,
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sort {
+    field: SortField,
+    descending: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortField {
+    Created,
+    Updated,
+}
+
+/// An issue or pull request matched by a [`Filter`].
+#[derive(Debug, Clone)]
+pub struct ItemSummary {
+    pub repo: Repo,
+    pub number: u64,
+    pub kind: ItemKind,
+    pub title: String,
+    /// Login of the author
+    pub author: String,
+    pub open: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// None if open or closed without a particular reason
+    pub state_reason: Option<StateReason>,
+    pub author_association: AuthorAssociation,
+}
+
+impl ItemSummary {
+    pub(crate) fn load(
+        txn: &Transaction<Schema>,
+        row: TableRow<schema::IssuePullRequestShared>,
+    ) -> Self {
+        use schema::*;
+
+        let kind = match txn.query_one(PullRequest.shared(row)) {
+            Some(_) => ItemKind::PullRequest,
+            None => ItemKind::Issue,
+        };
+        let shared = txn.lazy(row);
+
+        ItemSummary {
+            repo: crate::Repo {
+                organization: shared.repo.organization.clone(),
+                name: shared.repo.name.clone(),
+            },
+            number: shared.number as u64,
+            kind,
+            title: shared.title.clone(),
+            author: shared.author.name.clone(),
+            open: shared.state == ItemState::Open.as_str(),
+            created_at: timestamp(shared.created_timestamp),
+            updated_at: timestamp(shared.updated_timestamp),
+            closed_at: shared.closed_at_timestamp.map(timestamp),
+            state_reason: shared
+                .state_reason
+                .as_deref()
+                .and_then(|reason| reason.parse().ok()),
+            author_association: shared
+                .author_association
+                .parse()
+                .unwrap_or(AuthorAssociation::Unknown),
+        }
+    }
+}
+
+/// Converts a timestamp as stored in the database.
+pub(crate) fn timestamp(t: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_secs(t).unwrap_or_default()
+}
+
+impl GithubDb {
+    /// The first `limit` issues and pull requests matching `filter`.
+    ///
+    /// ```no_run
+    /// # async fn example(gh: &github_db::GithubDb) -> Result<(), github_db::FilterError> {
+    /// let filter = "is:pr is:open label:T-compiler -label:S-blocked".parse()?;
+    /// for pr in gh.find(&filter, 50).await {
+    ///     println!("#{} {}", pr.number, pr.title);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find(&self, filter: &Filter, limit: usize) -> Vec<ItemSummary> {
+        let filter = filter.clone();
+        self.db
+            .transaction(move |txn| compile::find(txn, &filter, limit))
+            .await
+    }
+}
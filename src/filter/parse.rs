@@ -0,0 +1,384 @@
+use std::{fmt::Display, ops::Range};
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+
+use crate::{
+    Repo,
+    filter::{DateField, Filter, Sort, SortField, Term, TermKind},
+};
+
+/// Why a filter couldn't be parsed, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    pub message: String,
+    /// The byte range in the query the error is about
+    pub span: Range<usize>,
+    query: String,
+}
+
+impl Display for FilterError {
+    /// Renders the query with the offending part underlined:
+    ///
+    /// ```text
+    /// unknown qualifier `lable`, did you mean `label`?
+    ///   is:pr lable:foo
+    ///         ^^^^^
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // a span that isn't on char boundaries points past the end rather than panicking
+        let (before, underlined) = match (
+            self.query.get(..self.span.start),
+            self.query.get(self.span.clone()),
+        ) {
+            (Some(before), Some(underlined)) => (before, underlined),
+            _ => (self.query.as_str(), ""),
+        };
+        let offset = before.chars().count();
+        let width = underlined.chars().count().max(1);
+        writeln!(f, "{}", self.message)?;
+        writeln!(f, "  {}", self.query)?;
+        write!(f, "  {}{}", " ".repeat(offset), "^".repeat(width))
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+const QUALIFIERS: &[&str] = &[
+    "is",
+    "state",
+    "no",
+    "label",
+    "author",
+    "assignee",
+    "review-requested",
+    "repo",
+    "created",
+    "updated",
+    "closed",
+    "sort",
+];
+
+/// One whitespace separated part of the query, like `-label:"good first issue"`.
+struct Token<'a> {
+    negated: bool,
+    key: Option<(&'a str, Range<usize>)>,
+    value: String,
+    /// Without the quotes of a quoted value
+    value_span: Range<usize>,
+    span: Range<usize>,
+}
+
+struct Parser<'a> {
+    query: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, span: Range<usize>, message: impl Into<String>) -> FilterError {
+        FilterError {
+            message: message.into(),
+            span,
+            query: self.query.to_string(),
+        }
+    }
+
+    fn tokens(&self) -> Result<Vec<Token<'a>>, FilterError> {
+        let bytes = self.query.as_bytes();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            if bytes[i].is_ascii_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            let negated = bytes[i] == b'-';
+            if negated {
+                i += 1;
+            }
+
+            // a key is everything up to a colon, unless the token ends or a quote starts first
+            let key_start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b":\"".contains(&bytes[i])
+            {
+                i += 1;
+            }
+            let key = if i < bytes.len() && bytes[i] == b':' {
+                let key = (&self.query[key_start..i], key_start..i);
+                i += 1;
+                Some(key)
+            } else {
+                i = key_start;
+                None
+            };
+
+            let value_span = if i < bytes.len() && bytes[i] == b'"' {
+                let Some(len) = self.query[i + 1..].find('"') else {
+                    return Err(self.error(i..bytes.len(), "unterminated quote"));
+                };
+                let value_span = i + 1..i + 1 + len;
+                i += len + 2;
+                value_span
+            } else {
+                let value_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                value_start..i
+            };
+
+            tokens.push(Token {
+                negated,
+                key,
+                value: self.query[value_span.clone()].to_string(),
+                value_span,
+                span: start..i,
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    fn term(&self, token: Token) -> Result<Option<Term>, FilterError> {
+        let Token {
+            mut negated,
+            key,
+            value,
+            value_span,
+            span,
+        } = token;
+
+        let Some((key, key_span)) = key else {
+            if value.is_empty() {
+                return Err(self.error(span, "expected a word or qualifier after `-`"));
+            }
+            return Ok(Some(Term {
+                negated,
+                kind: TermKind::Text(value),
+            }));
+        };
+
+        if value.is_empty() {
+            return Err(self.error(
+                span,
+                format!("`{key}:` needs a value, like `{}`", example(key)),
+            ));
+        }
+
+        let kind = match key {
+            "is" | "state" => match (key, value.as_str()) {
+                (_, "open") => TermKind::Open,
+                (_, "closed") => {
+                    negated = !negated;
+                    TermKind::Open
+                }
+                ("is", "pr") => TermKind::PullRequest,
+                ("is", "issue") => {
+                    negated = !negated;
+                    TermKind::PullRequest
+                }
+                ("is", "merged") => TermKind::Merged,
+                ("is", "draft") => TermKind::Draft,
+                ("is", "locked") => TermKind::Locked,
+                _ => {
+                    let expected = if key == "is" {
+                        "`open`, `closed`, `pr`, `issue`, `merged`, `draft` or `locked`"
+                    } else {
+                        "`open` or `closed`"
+                    };
+                    return Err(self.error(
+                        value_span,
+                        format!("unknown value `{value}` for `{key}:`, expected {expected}"),
+                    ));
+                }
+            },
+            "no" => match value.as_str() {
+                "label" => TermKind::NoLabel,
+                "assignee" => TermKind::NoAssignee,
+                "review-requested" => TermKind::NoReviewRequest,
+                _ => {
+                    return Err(self.error(
+                        value_span,
+                        format!(
+                            "unknown value `{value}` for `no:`, expected `label`, `assignee` or `review-requested`"
+                        ),
+                    ));
+                }
+            },
+            "label" => TermKind::Label(value),
+            "author" => TermKind::Author(value),
+            "assignee" => TermKind::Assignee(value),
+            "review-requested" => TermKind::ReviewRequested(value),
+            "repo" => match value.parse::<Repo>() {
+                Ok(repo) => TermKind::Repo(repo),
+                Err(()) => {
+                    return Err(self.error(
+                        value_span,
+                        format!("`{value}` isn't a repository, expected `owner/name`"),
+                    ));
+                }
+            },
+            "created" | "updated" | "closed" => {
+                let field = match key {
+                    "created" => DateField::Created,
+                    "updated" => DateField::Updated,
+                    _ => DateField::Closed,
+                };
+                let (from, to) = self.date_range(&value, value_span)?;
+                TermKind::Date { field, from, to }
+            }
+            "sort" => {
+                if negated {
+                    return Err(self.error(span, "`sort:` can't be negated"));
+                }
+                return Ok(None);
+            }
+            _ => {
+                let mut message = format!("unknown qualifier `{key}`");
+                if let Some(suggestion) = QUALIFIERS
+                    .iter()
+                    .min_by_key(|q| edit_distance(key, q))
+                    .filter(|q| edit_distance(key, q) <= 2)
+                {
+                    message.push_str(&format!(", did you mean `{suggestion}`?"));
+                }
+                return Err(self.error(key_span, message));
+            }
+        };
+
+        Ok(Some(Term { negated, kind }))
+    }
+
+    fn sort(&self, value: &str, span: Range<usize>) -> Result<Sort, FilterError> {
+        let (field, descending) = match value.rsplit_once('-') {
+            Some((field, "asc")) => (field, false),
+            Some((field, "desc")) => (field, true),
+            _ => (value, true),
+        };
+        let field = match field {
+            "created" => SortField::Created,
+            "updated" => SortField::Updated,
+            _ => {
+                return Err(self.error(
+                    span,
+                    format!(
+                        "can't sort by `{value}`, expected `created` or `updated`, optionally followed by `-asc` or `-desc`"
+                    ),
+                ));
+            }
+        };
+        Ok(Sort { field, descending })
+    }
+
+    /// Parses `>D`, `>=D`, `<D`, `<=D`, `D..D` and `D` into a half open range of timestamps.
+    /// `*` can be used for an open end in ranges.
+    fn date_range(
+        &self,
+        value: &str,
+        span: Range<usize>,
+    ) -> Result<(Option<i64>, Option<i64>), FilterError> {
+        let date = |s: &str, offset: usize| -> Result<Option<(i64, i64)>, FilterError> {
+            if s == "*" {
+                return Ok(None);
+            }
+            let start = span.start + offset;
+            parse_date(s).map(Some).ok_or_else(|| {
+                self.error(
+                    start..start + s.len(),
+                    format!("`{s}` isn't a date, expected `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SSZ`"),
+                )
+            })
+        };
+        Ok(if let Some(rest) = value.strip_prefix(">=") {
+            (date(rest, 2)?.map(|(start, _)| start), None)
+        } else if let Some(rest) = value.strip_prefix('>') {
+            (date(rest, 1)?.map(|(_, end)| end), None)
+        } else if let Some(rest) = value.strip_prefix("<=") {
+            (None, date(rest, 2)?.map(|(_, end)| end))
+        } else if let Some(rest) = value.strip_prefix('<') {
+            (None, date(rest, 1)?.map(|(start, _)| start))
+        } else if let Some((from, to)) = value.split_once("..") {
+            (
+                date(from, 0)?.map(|(start, _)| start),
+                date(to, from.len() + 2)?.map(|(_, end)| end),
+            )
+        } else {
+            match date(value, 0)? {
+                Some((start, end)) => (Some(start), Some(end)),
+                None => (None, None),
+            }
+        })
+    }
+}
+
+/// Start and end (exclusive) of a date, or of a single second for full timestamps.
+fn parse_date(s: &str) -> Option<(i64, i64)> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let start = date.and_hms_opt(0, 0, 0)?.and_utc();
+        let end = date
+            .checked_add_days(Days::new(1))?
+            .and_hms_opt(0, 0, 0)?
+            .and_utc();
+        return Some((start.timestamp(), end.timestamp()));
+    }
+    let time = DateTime::parse_from_rfc3339(s).ok()?.with_timezone(&Utc);
+    Some((time.timestamp(), time.timestamp() + 1))
+}
+
+fn example(key: &str) -> &'static str {
+    match key {
+        "is" => "is:open",
+        "state" => "state:closed",
+        "no" => "no:label",
+        "label" => "label:T-compiler",
+        "repo" => "repo:rust-lang/rust",
+        "created" | "updated" | "closed" => "updated:>2025-01-01",
+        "sort" => "sort:updated-desc",
+        _ => "author:octocat",
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != *cb);
+            curr.push(substitute.min(prev[j + 1] + 1).min(curr[j] + 1));
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+pub(super) fn parse(query: &str) -> Result<Filter, FilterError> {
+    let parser = Parser { query };
+    let mut terms = Vec::new();
+    let mut sort = None;
+
+    for token in parser.tokens()? {
+        if let Some(("sort", _)) = token.key
+            && !token.negated
+            && !token.value.is_empty()
+        {
+            if sort.is_some() {
+                return Err(parser.error(token.span, "only one `sort:` is allowed"));
+            }
+            sort = Some(parser.sort(&token.value, token.value_span)?);
+            continue;
+        }
+        if let Some(term) = parser.term(token)? {
+            terms.push(term);
+        }
+    }
+
+    Ok(Filter {
+        terms,
+        sort: sort.unwrap_or(Sort {
+            field: SortField::Created,
+            descending: true,
+        }),
+    })
+}
@@ -0,0 +1,609 @@
+//! Record the responses of a [`Forge`] to a cassette file and replay them later.
+//!
+//! A cassette is a JSON lines file with one request and its response per line.
+//! [`Recorder`] wraps a real forge (usually an [`OctocrabForge`](super::OctocrabForge))
+//! and appends every interaction to the cassette. [`Replayer`] serves those interactions
+//! back without touching the network, and fails requests that were never recorded.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use octocrab::models::{
+    Author, Collaborator, Repository, UserProfile,
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+    repos::{Release, Tag},
+    teams::Team,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    Repo,
+    forge::{
+        Forge, ForgeError, ForgePage, ListType, TokenBudget, async_trait,
+        discussion::{Discussion, DiscussionComment},
+    },
+};
+
+/// A request made to a [`Forge`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "call")]
+pub enum Call {
+    ListPrs {
+        repo: String,
+        list_type: ListType,
+        page: usize,
+        url: Option<String>,
+    },
+    ListIssues {
+        repo: String,
+        list_type: ListType,
+        page: usize,
+        url: Option<String>,
+    },
+    ListComments {
+        repo: String,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<String>,
+    },
+    ListDiscussions {
+        repo: String,
+        list_type: ListType,
+        page: usize,
+        url: Option<String>,
+    },
+    ListDiscussionComments {
+        node_id: String,
+        page: usize,
+        url: Option<String>,
+    },
+    ListReleases {
+        repo: String,
+        page: usize,
+        url: Option<String>,
+    },
+    ListTags {
+        repo: String,
+        page: usize,
+        url: Option<String>,
+    },
+    ListCollaborators {
+        repo: String,
+        page: usize,
+        url: Option<String>,
+    },
+    ListRepoTeams {
+        repo: String,
+        page: usize,
+        url: Option<String>,
+    },
+    ListTeamMembers {
+        organization: String,
+        slug: String,
+        page: usize,
+        url: Option<String>,
+    },
+    Repository {
+        repo: String,
+    },
+    UserProfile {
+        user_id: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Interaction {
+    request: Call,
+    response: Result<ForgePage<Value>, String>,
+}
+
+fn repo_name(repo: &Repo) -> String {
+    format!("{}/{}", repo.organization, repo.name)
+}
+
+/// Single objects are recorded as a page with one item.
+fn single<T>(item: T) -> ForgePage<T> {
+    ForgePage {
+        items: vec![item],
+        next: None,
+        last_page: None,
+    }
+}
+
+/// Wraps a [`Forge`] and writes every request and response to a cassette.
+pub struct Recorder {
+    inner: Arc<dyn Forge>,
+    cassette: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Start recording to `path`. An existing cassette is overwritten.
+    pub fn create(inner: Arc<dyn Forge>, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            inner,
+            cassette: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    fn record<T: Serialize>(&self, request: Call, response: &Result<ForgePage<T>, ForgeError>) {
+        let response = match response {
+            Ok(page) => Ok(ForgePage {
+                items: page
+                    .items
+                    .iter()
+                    .map(|i| serde_json::to_value(i).expect("serializable"))
+                    .collect(),
+                next: page.next.clone(),
+                last_page: page.last_page,
+            }),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let mut cassette = self.cassette.lock().unwrap();
+        let res = serde_json::to_writer(&mut *cassette, &Interaction { request, response })
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(cassette))
+            // flush every interaction so a crashed session still leaves a usable cassette
+            .and_then(|()| cassette.flush());
+        if let Err(e) = res {
+            tracing::error!("couldn't write to cassette: {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for Recorder {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        let res = self.inner.list_prs(repo, list_type, page, url).await;
+        self.record(
+            Call::ListPrs {
+                repo: repo_name(repo),
+                list_type,
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        let res = self.inner.list_issues(repo, list_type, page, url).await;
+        self.record(
+            Call::ListIssues {
+                repo: repo_name(repo),
+                list_type,
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        let res = self
+            .inner
+            .list_comments(repo, issue_number, since, page, url)
+            .await;
+        self.record(
+            Call::ListComments {
+                repo: repo_name(repo),
+                issue_number,
+                since,
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_discussions(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Discussion>, ForgeError> {
+        let res = self
+            .inner
+            .list_discussions(repo, list_type, page, url)
+            .await;
+        self.record(
+            Call::ListDiscussions {
+                repo: repo_name(repo),
+                list_type,
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_discussion_comments(
+        &self,
+        node_id: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<DiscussionComment>, ForgeError> {
+        let res = self
+            .inner
+            .list_discussion_comments(node_id, page, url)
+            .await;
+        self.record(
+            Call::ListDiscussionComments {
+                node_id: node_id.to_string(),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_releases(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Release>, ForgeError> {
+        let res = self.inner.list_releases(repo, page, url).await;
+        self.record(
+            Call::ListReleases {
+                repo: repo_name(repo),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_tags(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Tag>, ForgeError> {
+        let res = self.inner.list_tags(repo, page, url).await;
+        self.record(
+            Call::ListTags {
+                repo: repo_name(repo),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_collaborators(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Collaborator>, ForgeError> {
+        let res = self.inner.list_collaborators(repo, page, url).await;
+        self.record(
+            Call::ListCollaborators {
+                repo: repo_name(repo),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_repo_teams(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Team>, ForgeError> {
+        let res = self.inner.list_repo_teams(repo, page, url).await;
+        self.record(
+            Call::ListRepoTeams {
+                repo: repo_name(repo),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn list_team_members(
+        &self,
+        organization: &str,
+        slug: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Author>, ForgeError> {
+        let res = self
+            .inner
+            .list_team_members(organization, slug, page, url)
+            .await;
+        self.record(
+            Call::ListTeamMembers {
+                organization: organization.to_string(),
+                slug: slug.to_string(),
+                page,
+                url: url.map(ToString::to_string),
+            },
+            &res,
+        );
+        res
+    }
+
+    async fn repository(&self, repo: &Repo) -> Result<Repository, ForgeError> {
+        let res = self.inner.repository(repo).await.map(single);
+        self.record(
+            Call::Repository {
+                repo: repo_name(repo),
+            },
+            &res,
+        );
+        res.map(|mut page| page.items.remove(0))
+    }
+
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        let res = self.inner.user_profile(user_id).await.map(single);
+        self.record(Call::UserProfile { user_id }, &res);
+        res.map(|mut page| page.items.remove(0))
+    }
+
+    async fn rate_limits(&self) -> Result<Vec<TokenBudget>, ForgeError> {
+        self.inner.rate_limits().await
+    }
+}
+
+/// Serves the interactions in a cassette made by a [`Recorder`].
+///
+/// Requests are matched exactly, and each recorded interaction is used once.
+/// When the same request was recorded multiple times, the responses are served
+/// in the order they were recorded.
+pub struct Replayer {
+    interactions: Mutex<Vec<Option<Interaction>>>,
+}
+
+impl Replayer {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut interactions = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            interactions.push(Some(serde_json::from_str(&line)?));
+        }
+
+        Ok(Self {
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    /// Number of recorded interactions that haven't been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.interactions.lock().unwrap().iter().flatten().count()
+    }
+
+    fn replay<T: DeserializeOwned>(&self, request: Call) -> Result<ForgePage<T>, ForgeError> {
+        let interaction = self
+            .interactions
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|i| i.as_ref().is_some_and(|i| i.request == request))
+            .and_then(Option::take);
+
+        let Some(interaction) = interaction else {
+            tracing::error!("request not in cassette: {request:?}");
+            return Err(ForgeError::Other(format!(
+                "request not in cassette: {request:?}"
+            )));
+        };
+
+        let page = interaction.response.map_err(ForgeError::Other)?;
+        Ok(ForgePage {
+            items: page
+                .items
+                .into_iter()
+                .map(serde_json::from_value)
+                .collect::<Result<_, _>>()
+                .map_err(|e| ForgeError::Other(format!("corrupt cassette: {e}")))?,
+            next: page.next,
+            last_page: page.last_page,
+        })
+    }
+}
+
+#[async_trait]
+impl Forge for Replayer {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        self.replay(Call::ListPrs {
+            repo: repo_name(repo),
+            list_type,
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        self.replay(Call::ListIssues {
+            repo: repo_name(repo),
+            list_type,
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        self.replay(Call::ListComments {
+            repo: repo_name(repo),
+            issue_number,
+            since,
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_discussions(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Discussion>, ForgeError> {
+        self.replay(Call::ListDiscussions {
+            repo: repo_name(repo),
+            list_type,
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_discussion_comments(
+        &self,
+        node_id: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<DiscussionComment>, ForgeError> {
+        self.replay(Call::ListDiscussionComments {
+            node_id: node_id.to_string(),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_releases(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Release>, ForgeError> {
+        self.replay(Call::ListReleases {
+            repo: repo_name(repo),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_tags(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Tag>, ForgeError> {
+        self.replay(Call::ListTags {
+            repo: repo_name(repo),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_collaborators(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Collaborator>, ForgeError> {
+        self.replay(Call::ListCollaborators {
+            repo: repo_name(repo),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_repo_teams(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Team>, ForgeError> {
+        self.replay(Call::ListRepoTeams {
+            repo: repo_name(repo),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn list_team_members(
+        &self,
+        organization: &str,
+        slug: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Author>, ForgeError> {
+        self.replay(Call::ListTeamMembers {
+            organization: organization.to_string(),
+            slug: slug.to_string(),
+            page,
+            url: url.map(ToString::to_string),
+        })
+    }
+
+    async fn repository(&self, repo: &Repo) -> Result<Repository, ForgeError> {
+        self.replay(Call::Repository {
+            repo: repo_name(repo),
+        })?
+        .items
+        .pop()
+        .ok_or_else(|| ForgeError::Other("corrupt cassette: no repository".to_string()))
+    }
+
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        self.replay(Call::UserProfile { user_id })?
+            .items
+            .pop()
+            .ok_or_else(|| ForgeError::Other("corrupt cassette: no profile".to_string()))
+    }
+}
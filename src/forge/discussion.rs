@@ -0,0 +1,127 @@
+//! GitHub Discussions, which are only available over the GraphQL api.
+//!
+//! octocrab has no models for them, these match the json of the query that
+//! [`OctocrabForge`](super::OctocrabForge) sends, so they use GraphQL's camelCase.
+//!
+//! A page of discussions includes their first [`COMMENTS_PER_DISCUSSION`] comments and
+//! the first [`REPLIES_PER_COMMENT`] replies to those. The [`PageInfo`] of those says
+//! whether there are more, which are listed with
+//! [`Forge::list_discussion_comments`](super::Forge::list_discussion_comments).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Discussions per page of a listing.
+pub const DISCUSSIONS_PER_PAGE: usize = 25;
+/// The first comments of a discussion that are listed with it, and the page size after that.
+pub const COMMENTS_PER_DISCUSSION: usize = 50;
+/// The first replies to a comment that are listed with it, and the page size after that.
+pub const REPLIES_PER_COMMENT: usize = 20;
+
+/// The points GitHub charges for a page of discussions, a point per hundred connections
+/// it resolves: the discussions, the comments of each and the replies to each comment.
+pub const DISCUSSIONS_PAGE_POINTS: usize =
+    (1 + DISCUSSIONS_PER_PAGE + DISCUSSIONS_PER_PAGE * COMMENTS_PER_DISCUSSION).div_ceil(100);
+/// The points for a page of comments with their replies, or of replies.
+pub const COMMENTS_PAGE_POINTS: usize = (1 + COMMENTS_PER_DISCUSSION).div_ceil(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discussion {
+    /// The GraphQL node id
+    pub id: String,
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub url: String,
+    /// None for deleted accounts
+    pub author: Option<Actor>,
+    /// Like `MEMBER`, in the same spelling as the REST api
+    pub author_association: String,
+    pub category: DiscussionCategory,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed: bool,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub locked: bool,
+    /// None if no comment was marked as the answer
+    pub answer_chosen_at: Option<DateTime<Utc>>,
+    pub answer_chosen_by: Option<Actor>,
+    pub comments: Connection<DiscussionComment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscussionCategory {
+    /// The GraphQL node id
+    pub id: String,
+    pub name: String,
+    pub slug: String,
+    /// Like `:bulb:`
+    pub emoji: String,
+    /// Whether comments can be marked as the answer, like in Q&A categories
+    pub is_answerable: bool,
+}
+
+/// A comment on a discussion, or a reply to one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscussionComment {
+    /// The GraphQL node id
+    pub id: String,
+    pub body: String,
+    pub url: String,
+    /// None for deleted accounts
+    pub author: Option<Actor>,
+    pub author_association: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub is_answer: bool,
+    /// Always empty for replies, they can't be replied to
+    #[serde(default)]
+    pub replies: Connection<DiscussionComment>,
+}
+
+/// Who wrote something, a user, bot or organization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Actor {
+    pub login: String,
+    /// The id the REST api uses, None for accounts that don't have one
+    pub database_id: Option<u64>,
+    /// Like `User` or `Bot`
+    #[serde(rename = "__typename")]
+    pub typename: String,
+}
+
+/// A page of a GraphQL connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    pub nodes: Vec<T>,
+    #[serde(default)]
+    pub page_info: PageInfo,
+}
+
+impl<T> Default for Connection<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            page_info: PageInfo::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+impl PageInfo {
+    /// The cursor to list the next page after, None if this is the last page.
+    pub fn next(&self) -> Option<&str> {
+        self.end_cursor.as_deref().filter(|_| self.has_next_page)
+    }
+}
@@ -0,0 +1,1314 @@
+//! An in-memory GitHub for tests.
+//!
+//! Repositories are scripted through [`FakeGithub`]'s methods. Every change moves a
+//! fake clock forward by a minute and bumps the `updated_at` of whatever changed,
+//! so the update-ordered listings behave like they do on GitHub.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, Utc};
+use octocrab::models::{
+    Author, Collaborator, Repository, UserProfile,
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+    repos::{Release, Tag},
+    teams::Team,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::{
+    Repo,
+    forge::{
+        Forge, ForgeError, ForgePage, ListType, async_trait,
+        discussion::{Discussion, DiscussionComment},
+        json,
+    },
+};
+
+/// Timestamp of the first change made to a [`FakeGithub`].
+const EPOCH: i64 = 1_577_836_800; // 2020-01-01
+
+/// An issue or pull request on a [`FakeGithub`].
+#[derive(Debug, Clone)]
+pub struct FakeItem {
+    pub title: String,
+    pub body: Option<String>,
+    pub author: String,
+    pub labels: Vec<String>,
+    pub assignees: Vec<String>,
+    /// Only used for pull requests
+    pub requested_reviewers: Vec<String>,
+    pub closed: bool,
+    /// Only used for pull requests
+    pub merged: bool,
+    /// Login of who closed or merged it, only used while it's closed
+    pub closed_by: Option<String>,
+
+    is_pr: bool,
+    created_at: i64,
+    updated_at: i64,
+    closed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct FakeComment {
+    id: u64,
+    author: String,
+    body: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// A discussion on a [`FakeGithub`].
+#[derive(Debug, Clone)]
+pub struct FakeDiscussion {
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    /// The name of its category. Only `Q&A` is answerable
+    pub category: String,
+    pub closed: bool,
+    pub locked: bool,
+
+    comments: Vec<FakeDiscussionComment>,
+    /// The id of the comment that was marked as the answer, who did that and when
+    answer: Option<(u64, String, i64)>,
+    created_at: i64,
+    updated_at: i64,
+    closed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct FakeDiscussionComment {
+    id: u64,
+    /// The comment this replies to
+    parent: Option<u64>,
+    author: String,
+    body: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// A release on a [`FakeGithub`].
+#[derive(Debug, Clone)]
+pub struct FakeRelease {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub body: Option<String>,
+    pub author: String,
+    pub draft: bool,
+    pub prerelease: bool,
+
+    id: u64,
+    created_at: i64,
+    /// None for drafts
+    published_at: Option<i64>,
+}
+
+/// What a [`FakeGithub`] reports about a repository, see [`FakeGithub::edit_repo`].
+/// The open issue count is that of its open issues and pull requests.
+#[derive(Debug, Clone)]
+pub struct FakeRepoMetadata {
+    pub description: Option<String>,
+    pub default_branch: String,
+    pub topics: Vec<String>,
+    pub visibility: String,
+    pub archived: bool,
+    pub stars: u32,
+    pub forks: u32,
+    pub watchers: i64,
+}
+
+impl Default for FakeRepoMetadata {
+    fn default() -> Self {
+        Self {
+            description: None,
+            default_branch: "main".to_string(),
+            topics: Vec::new(),
+            visibility: "public".to_string(),
+            archived: false,
+            stars: 0,
+            forks: 0,
+            watchers: 0,
+        }
+    }
+}
+
+/// The profile of a user on a [`FakeGithub`], see [`FakeGithub::set_profile`].
+#[derive(Debug, Clone, Default)]
+pub struct FakeProfile {
+    pub name: Option<String>,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    /// Also shows up as the user's type in listings
+    pub bot: bool,
+}
+
+#[derive(Default)]
+struct FakeRepo {
+    items: BTreeMap<u64, FakeItem>,
+    /// Issue number -> comments
+    comments: BTreeMap<u64, Vec<FakeComment>>,
+    /// Numbered like issues and pull requests, like on GitHub
+    discussions: BTreeMap<u64, FakeDiscussion>,
+    /// In the order they were created
+    releases: Vec<FakeRelease>,
+    /// Tag name -> commit sha
+    tags: BTreeMap<String, String>,
+    metadata: FakeRepoMetadata,
+    /// Login -> permission, like `triage`
+    collaborators: BTreeMap<String, String>,
+    /// Slug of a team of the repository's organization -> its permission
+    teams: BTreeMap<String, String>,
+}
+
+impl FakeRepo {
+    fn next_number(&self) -> u64 {
+        let last = |numbers: Option<&u64>| numbers.copied().unwrap_or(0);
+        last(self.items.keys().next_back()).max(last(self.discussions.keys().next_back())) + 1
+    }
+}
+
+#[derive(Default)]
+struct State {
+    clock: i64,
+    page_size: usize,
+    next_comment_id: u64,
+    next_release_id: u64,
+    repos: BTreeMap<String, FakeRepo>,
+    users: BTreeMap<String, u64>,
+    profiles: BTreeMap<String, FakeProfile>,
+    deleted: BTreeSet<String>,
+    associations: BTreeMap<String, String>,
+    /// `organization/slug` -> name and members of the team
+    teams: BTreeMap<String, (String, Vec<String>)>,
+    failures: usize,
+    calls: Vec<String>,
+}
+
+/// See the [module documentation](self).
+pub struct FakeGithub {
+    state: Mutex<State>,
+}
+
+impl Default for FakeGithub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeGithub {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                page_size: 100,
+                next_comment_id: 1,
+                next_release_id: 1,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Number of items per page, 100 by default.
+    pub fn with_page_size(self, page_size: usize) -> Self {
+        self.state().page_size = page_size.max(1);
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn add_item(&self, repo: &str, title: &str, author: &str, is_pr: bool) -> u64 {
+        let mut state = self.state();
+        let now = state.tick();
+        let repo = state.repos.entry(repo.to_string()).or_default();
+        let number = repo.next_number();
+
+        repo.items.insert(
+            number,
+            FakeItem {
+                title: title.to_string(),
+                body: None,
+                author: author.to_string(),
+                labels: Vec::new(),
+                assignees: Vec::new(),
+                requested_reviewers: Vec::new(),
+                closed: false,
+                merged: false,
+                closed_by: None,
+                is_pr,
+                created_at: now,
+                updated_at: now,
+                closed_at: None,
+            },
+        );
+        number
+    }
+
+    /// Open a new issue, returns its number.
+    pub fn add_issue(&self, repo: &str, title: &str, author: &str) -> u64 {
+        self.add_item(repo, title, author, false)
+    }
+
+    /// Open a new pull request, returns its number.
+    pub fn add_pr(&self, repo: &str, title: &str, author: &str) -> u64 {
+        self.add_item(repo, title, author, true)
+    }
+
+    /// Change an issue or pull request.
+    ///
+    /// # Panics
+    /// If it doesn't exist.
+    pub fn edit(&self, repo: &str, number: u64, f: impl FnOnce(&mut FakeItem)) {
+        let mut state = self.state();
+        let now = state.tick();
+        let item = state
+            .repos
+            .get_mut(repo)
+            .and_then(|r| r.items.get_mut(&number))
+            .unwrap_or_else(|| panic!("no item {repo}#{number}"));
+
+        let was_closed = item.closed;
+        f(item);
+        item.updated_at = now;
+        item.merged &= item.is_pr;
+        item.closed |= item.merged;
+        if item.closed && !was_closed {
+            item.closed_at = Some(now);
+        } else if !item.closed {
+            item.closed_at = None;
+        }
+    }
+
+    /// Comment on an issue or pull request, returns the id of the comment.
+    pub fn add_comment(&self, repo: &str, number: u64, author: &str, body: &str) -> u64 {
+        let mut state = self.state();
+        let now = state.tick();
+        let id = state.next_comment_id;
+        state.next_comment_id += 1;
+
+        let repo = state.repos.get_mut(repo).expect("no such repo");
+        let item = repo.items.get_mut(&number).expect("no such item");
+        item.updated_at = now;
+
+        repo.comments.entry(number).or_default().push(FakeComment {
+            id,
+            author: author.to_string(),
+            body: body.to_string(),
+            created_at: now,
+            updated_at: now,
+        });
+        id
+    }
+
+    /// Change the text of a comment.
+    pub fn edit_comment(&self, repo: &str, comment_id: u64, body: &str) {
+        let mut state = self.state();
+        let now = state.tick();
+        let repo = state.repos.get_mut(repo).expect("no such repo");
+
+        for (number, comments) in &mut repo.comments {
+            if let Some(comment) = comments.iter_mut().find(|c| c.id == comment_id) {
+                comment.body = body.to_string();
+                comment.updated_at = now;
+                repo.items.get_mut(number).unwrap().updated_at = now;
+                return;
+            }
+        }
+        panic!("no comment {comment_id}");
+    }
+
+    /// Start a new discussion in the category named `category`, returns its number.
+    pub fn add_discussion(&self, repo: &str, category: &str, title: &str, author: &str) -> u64 {
+        let mut state = self.state();
+        let now = state.tick();
+        let repo = state.repos.entry(repo.to_string()).or_default();
+        let number = repo.next_number();
+
+        repo.discussions.insert(
+            number,
+            FakeDiscussion {
+                title: title.to_string(),
+                body: String::new(),
+                author: author.to_string(),
+                category: category.to_string(),
+                closed: false,
+                locked: false,
+                comments: Vec::new(),
+                answer: None,
+                created_at: now,
+                updated_at: now,
+                closed_at: None,
+            },
+        );
+        number
+    }
+
+    /// Change a discussion.
+    ///
+    /// # Panics
+    /// If it doesn't exist.
+    pub fn edit_discussion(&self, repo: &str, number: u64, f: impl FnOnce(&mut FakeDiscussion)) {
+        let mut state = self.state();
+        let now = state.tick();
+        let discussion = state.discussion(repo, number);
+
+        let was_closed = discussion.closed;
+        f(discussion);
+        discussion.updated_at = now;
+        if discussion.closed && !was_closed {
+            discussion.closed_at = Some(now);
+        } else if !discussion.closed {
+            discussion.closed_at = None;
+        }
+    }
+
+    /// Comment on a discussion, or reply to the comment with id `reply_to`.
+    /// Returns the id of the new comment.
+    pub fn add_discussion_comment(
+        &self,
+        repo: &str,
+        number: u64,
+        reply_to: Option<u64>,
+        author: &str,
+        body: &str,
+    ) -> u64 {
+        let mut state = self.state();
+        let now = state.tick();
+        let id = state.next_comment_id;
+        state.next_comment_id += 1;
+
+        let discussion = state.discussion(repo, number);
+        discussion.updated_at = now;
+        discussion.comments.push(FakeDiscussionComment {
+            id,
+            parent: reply_to,
+            author: author.to_string(),
+            body: body.to_string(),
+            created_at: now,
+            updated_at: now,
+        });
+        id
+    }
+
+    /// Mark a comment on a discussion as its answer.
+    pub fn mark_answer(&self, repo: &str, number: u64, comment_id: u64, by: &str) {
+        let mut state = self.state();
+        let now = state.tick();
+        let discussion = state.discussion(repo, number);
+        discussion.updated_at = now;
+        discussion.answer = Some((comment_id, by.to_string(), now));
+    }
+
+    /// Tag `commit_sha`, or move the tag if it exists.
+    pub fn add_tag(&self, repo: &str, name: &str, commit_sha: &str) {
+        let mut state = self.state();
+        state.tick();
+        let repo = state.repos.entry(repo.to_string()).or_default();
+        repo.tags.insert(name.to_string(), commit_sha.to_string());
+    }
+
+    /// Publish a release of `commit_sha`, tagged `tag_name`. Returns the id of the release.
+    pub fn add_release(&self, repo: &str, tag_name: &str, commit_sha: &str, author: &str) -> u64 {
+        let mut state = self.state();
+        let now = state.tick();
+        let id = state.next_release_id;
+        state.next_release_id += 1;
+
+        let repo = state.repos.entry(repo.to_string()).or_default();
+        repo.tags
+            .entry(tag_name.to_string())
+            .or_insert_with(|| commit_sha.to_string());
+        repo.releases.push(FakeRelease {
+            tag_name: tag_name.to_string(),
+            name: Some(tag_name.to_string()),
+            body: None,
+            author: author.to_string(),
+            draft: false,
+            prerelease: false,
+            id,
+            created_at: now,
+            published_at: Some(now),
+        });
+        id
+    }
+
+    /// Change a release. Drafts that are no longer drafts are published.
+    ///
+    /// # Panics
+    /// If it doesn't exist.
+    pub fn edit_release(&self, repo: &str, id: u64, f: impl FnOnce(&mut FakeRelease)) {
+        let mut state = self.state();
+        let now = state.tick();
+        let release = state
+            .repos
+            .get_mut(repo)
+            .and_then(|r| r.releases.iter_mut().find(|r| r.id == id))
+            .unwrap_or_else(|| panic!("no release {id} in {repo}"));
+
+        f(release);
+        if release.draft {
+            release.published_at = None;
+        } else {
+            release.published_at.get_or_insert(now);
+        }
+    }
+
+    /// Change what is reported about a repository.
+    pub fn edit_repo(&self, repo: &str, f: impl FnOnce(&mut FakeRepoMetadata)) {
+        let mut state = self.state();
+        state.tick();
+        f(&mut state.repos.entry(repo.to_string()).or_default().metadata);
+    }
+
+    /// Give a user access to a repository with a permission like `triage` or `write`,
+    /// or take it away with None.
+    pub fn set_collaborator(&self, repo: &str, login: &str, permission: Option<&str>) {
+        let mut state = self.state();
+        state.tick();
+        let collaborators = &mut state
+            .repos
+            .entry(repo.to_string())
+            .or_default()
+            .collaborators;
+        match permission {
+            Some(permission) => collaborators.insert(login.to_string(), permission.to_string()),
+            None => collaborators.remove(login),
+        };
+    }
+
+    /// Create a team of `organization`, or replace its members if it exists.
+    pub fn set_team(&self, organization: &str, slug: &str, name: &str, members: &[&str]) {
+        let mut state = self.state();
+        state.tick();
+        let members = members.iter().map(ToString::to_string).collect();
+        state.teams.insert(
+            format!("{organization}/{slug}"),
+            (name.to_string(), members),
+        );
+    }
+
+    /// Give a team access to a repository of its organization, or take it away with None.
+    ///
+    /// # Panics
+    /// If the team doesn't exist.
+    pub fn set_team_permission(&self, repo: &str, slug: &str, permission: Option<&str>) {
+        let mut state = self.state();
+        state.tick();
+        let (organization, _) = repo.split_once('/').expect("repo should be owner/name");
+        assert!(
+            state.teams.contains_key(&format!("{organization}/{slug}")),
+            "no team {organization}/{slug}"
+        );
+        let teams = &mut state.repos.entry(repo.to_string()).or_default().teams;
+        match permission {
+            Some(permission) => teams.insert(slug.to_string(), permission.to_string()),
+            None => teams.remove(slug),
+        };
+    }
+
+    /// Set the profile of a user, users without one have an empty profile.
+    pub fn set_profile(&self, login: &str, profile: FakeProfile) {
+        let mut state = self.state();
+        state.user_id(login);
+        state.profiles.insert(login.to_string(), profile);
+    }
+
+    /// Set how a user is related to every repository, like `MEMBER`.
+    /// Defaults to `CONTRIBUTOR`.
+    pub fn set_association(&self, login: &str, association: &str) {
+        let mut state = self.state();
+        state
+            .associations
+            .insert(login.to_string(), association.to_string());
+    }
+
+    /// Delete the account of a user. Like on GitHub, their issues and comments are then
+    /// authored by the ghost user, and their pull requests by no one.
+    pub fn delete_user(&self, login: &str) {
+        self.state().deleted.insert(login.to_string());
+    }
+
+    /// Make the next `n` requests fail.
+    pub fn fail_next(&self, n: usize) {
+        self.state().failures += n;
+    }
+
+    /// All requests made so far, like `"pulls rust-lang/rust old page 2"`.
+    pub fn calls(&self) -> Vec<String> {
+        self.state().calls.clone()
+    }
+
+    /// Record a call, and fail it if that was requested with [`FakeGithub::fail_next`].
+    fn call(&self, description: String) -> Result<MutexGuard<'_, State>, ForgeError> {
+        let mut state = self.state();
+        state.calls.push(description.clone());
+        if state.failures > 0 {
+            state.failures -= 1;
+            return Err(ForgeError::Other(format!(
+                "injected failure for {description}"
+            )));
+        }
+        Ok(state)
+    }
+}
+
+impl State {
+    fn tick(&mut self) -> i64 {
+        self.clock += 60;
+        EPOCH + self.clock
+    }
+
+    fn user_id(&mut self, login: &str) -> u64 {
+        let next = self.users.len() as u64 + 1;
+        *self.users.entry(login.to_string()).or_insert(next)
+    }
+
+    fn discussion(&mut self, repo: &str, number: u64) -> &mut FakeDiscussion {
+        self.repos
+            .get_mut(repo)
+            .and_then(|r| r.discussions.get_mut(&number))
+            .unwrap_or_else(|| panic!("no discussion {repo}#{number}"))
+    }
+
+    fn author(&mut self, login: &str) -> Value {
+        if self.deleted.contains(login) {
+            return json::ghost();
+        }
+        let id = self.user_id(login);
+        let mut author = json::user(login, id, None);
+        if self.profiles.get(login).is_some_and(|p| p.bot) {
+            author["type"] = "Bot".into();
+        }
+        author
+    }
+
+    fn association(&self, login: &str) -> &str {
+        self.associations
+            .get(login)
+            .map_or("CONTRIBUTOR", String::as_str)
+    }
+
+    /// Like [`State::author`], as a GraphQL actor. Deleted accounts are null.
+    fn actor(&mut self, login: &str) -> Value {
+        if self.deleted.contains(login) {
+            return Value::Null;
+        }
+        let author = self.author(login);
+        json!({
+            "login": login,
+            "databaseId": author["id"],
+            "__typename": author["type"],
+        })
+    }
+
+    fn closed_by(&mut self, item: &FakeItem) -> Value {
+        match &item.closed_by {
+            Some(login) if item.closed => self.author(login),
+            _ => Value::Null,
+        }
+    }
+
+    fn labels(repo: &str, labels: &[String]) -> Value {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(id, name)| json::label(repo, id + 1, name, None, "ededed"))
+            .collect()
+    }
+
+    fn issue_json(&mut self, repo: &str, number: u64, item: &FakeItem) -> Value {
+        let url = format!("https://api.github.com/repos/{repo}/issues/{number}");
+        let num_comments = self.repos[repo].comments.get(&number).map_or(0, Vec::len);
+        let assignees: Vec<_> = item.assignees.iter().map(|a| self.author(a)).collect();
+        let closed_by = self.closed_by(item);
+
+        let mut issue = json!({
+            "id": number,
+            "node_id": format!("I_{number}"),
+            "url": url,
+            "repository_url": format!("https://api.github.com/repos/{repo}"),
+            "labels_url": format!("{url}/labels"),
+            "comments_url": format!("{url}/comments"),
+            "events_url": format!("{url}/events"),
+            "html_url": format!("https://github.com/{repo}/issues/{number}"),
+            "number": number,
+            "state": if item.closed { "closed" } else { "open" },
+            "state_reason": if item.closed { Value::from("completed") } else { Value::Null },
+            "title": item.title,
+            "body": item.body,
+            "user": self.author(&item.author),
+            "labels": Self::labels(repo, &item.labels),
+            "assignees": assignees,
+            "author_association": self.association(&item.author),
+            "locked": false,
+            "comments": num_comments,
+            "closed_at": item.closed_at.map(timestamp),
+            "closed_by": closed_by,
+            "created_at": timestamp(item.created_at),
+            "updated_at": timestamp(item.updated_at),
+        });
+        if item.is_pr {
+            issue["pull_request"] = json!({
+                "url": format!("https://api.github.com/repos/{repo}/pulls/{number}"),
+                "html_url": format!("https://github.com/{repo}/pull/{number}"),
+                "diff_url": format!("https://github.com/{repo}/pull/{number}.diff"),
+                "patch_url": format!("https://github.com/{repo}/pull/{number}.patch"),
+            });
+        }
+        issue
+    }
+
+    /// The comments of a discussion, or the replies to the comment with id `parent`,
+    /// with the first page of the replies to each comment.
+    fn discussion_comments_json(
+        &mut self,
+        repo: &str,
+        number: u64,
+        discussion: &FakeDiscussion,
+        parent: Option<u64>,
+    ) -> Vec<Value> {
+        let url = format!("https://github.com/{repo}/discussions/{number}");
+        let answer = discussion.answer.as_ref();
+        let mut comments = Vec::new();
+        for c in discussion.comments.iter().filter(|c| c.parent == parent) {
+            let mut comment = json!({
+                "id": format!("DC_{}", c.id),
+                "body": c.body,
+                "url": format!("{url}#discussioncomment-{}", c.id),
+                "author": self.actor(&c.author),
+                "authorAssociation": self.association(&c.author),
+                "createdAt": timestamp(c.created_at),
+                "updatedAt": timestamp(c.updated_at),
+                "isAnswer": answer.is_some_and(|(id, ..)| *id == c.id),
+            });
+            if parent.is_none() {
+                let replies = self.discussion_comments_json(repo, number, discussion, Some(c.id));
+                comment["replies"] = self.connection(replies, &format!("DC_{}", c.id));
+            }
+            comments.push(comment);
+        }
+        comments
+    }
+
+    /// The first page of a GraphQL connection of the node `node_id`.
+    fn connection(&self, nodes: Vec<Value>, node_id: &str) -> Value {
+        let has_next_page = nodes.len() > self.page_size;
+        let nodes: Vec<_> = nodes.into_iter().take(self.page_size).collect();
+        json!({
+            "nodes": nodes,
+            "pageInfo": {
+                "hasNextPage": has_next_page,
+                "endCursor": format!("{node_id}?page=2"),
+            },
+        })
+    }
+
+    fn discussion_json(&mut self, repo: &str, number: u64, discussion: &FakeDiscussion) -> Value {
+        let url = format!("https://github.com/{repo}/discussions/{number}");
+        let answer = discussion.answer.as_ref();
+        let node_id = format!("D_{repo}#{number}");
+        let comments = self.discussion_comments_json(repo, number, discussion, None);
+        let comments = self.connection(comments, &node_id);
+
+        let slug: String = discussion
+            .category
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        json!({
+            "id": node_id,
+            "number": number,
+            "title": discussion.title,
+            "body": discussion.body,
+            "url": url,
+            "author": self.actor(&discussion.author),
+            "authorAssociation": self.association(&discussion.author),
+            "category": {
+                "id": format!("DIC_{slug}"),
+                "name": discussion.category,
+                "slug": slug,
+                "emoji": ":speech_balloon:",
+                "isAnswerable": discussion.category == "Q&A",
+            },
+            "createdAt": timestamp(discussion.created_at),
+            "updatedAt": timestamp(discussion.updated_at),
+            "closed": discussion.closed,
+            "closedAt": discussion.closed_at.map(timestamp),
+            "locked": discussion.locked,
+            "answerChosenAt": answer.map(|(.., at)| timestamp(*at)),
+            "answerChosenBy": match answer {
+                Some((_, by, _)) => self.actor(by),
+                None => Value::Null,
+            },
+            "comments": comments,
+        })
+    }
+
+    fn pr_json(&mut self, repo: &str, number: u64, item: &FakeItem) -> Value {
+        let assignees: Vec<_> = item.assignees.iter().map(|a| self.author(a)).collect();
+        let reviewers: Vec<_> = item
+            .requested_reviewers
+            .iter()
+            .map(|a| self.author(a))
+            .collect();
+
+        json!({
+            "url": format!("https://api.github.com/repos/{repo}/pulls/{number}"),
+            "id": number,
+            "number": number,
+            "state": if item.closed { "closed" } else { "open" },
+            "locked": false,
+            "maintainer_can_modify": false,
+            "title": item.title,
+            "user": if self.deleted.contains(&item.author) {
+                Value::Null
+            } else {
+                self.author(&item.author)
+            },
+            "body": item.body,
+            "labels": Self::labels(repo, &item.labels),
+            "created_at": timestamp(item.created_at),
+            "updated_at": timestamp(item.updated_at),
+            "closed_at": item.closed_at.map(timestamp),
+            "merged_at": item.merged.then(|| item.closed_at.map(timestamp)).flatten(),
+            "merge_commit_sha": item.merged.then(|| format!("{number:040x}")),
+            "merged_by": if item.merged { self.closed_by(item) } else { Value::Null },
+            "assignees": assignees,
+            "requested_reviewers": reviewers,
+            "head": { "ref": format!("pr-{number}"), "sha": format!("{:040x}", number + 1_000_000) },
+            "base": { "ref": "main", "sha": format!("{:040x}", 0) },
+            "author_association": self.association(&item.author),
+            "draft": false,
+        })
+    }
+}
+
+fn timestamp(secs: i64) -> String {
+    DateTime::<Utc>::from_timestamp_secs(secs)
+        .unwrap()
+        .to_rfc3339()
+}
+
+/// The 1-based page that is requested. The page in the url takes precedence.
+fn requested_page(page: usize, url: Option<&str>) -> usize {
+    url.and_then(|url| url.split_once("page=")?.1.split('&').next()?.parse().ok())
+        .unwrap_or(page)
+        .max(1)
+}
+
+/// Take page `page` of `items` and deserialize it.
+fn paginate<T: DeserializeOwned>(
+    items: Vec<Value>,
+    page_size: usize,
+    page: usize,
+    base_url: String,
+) -> Result<ForgePage<T>, ForgeError> {
+    let num_pages = items.len().div_ceil(page_size);
+    let items = items
+        .into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .map(|i| serde_json::from_value(i).map_err(|e| ForgeError::Other(e.to_string())))
+        .collect::<Result<_, _>>()?;
+
+    Ok(ForgePage {
+        items,
+        next: (page < num_pages).then(|| format!("{base_url}page={}", page + 1)),
+        last_page: (page < num_pages).then_some(num_pages),
+    })
+}
+
+impl FakeGithub {
+    fn list_items(
+        &self,
+        kind: &str,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        only_prs: bool,
+        to_json: impl Fn(&mut State, &str, u64, &FakeItem) -> Value,
+    ) -> Result<(Vec<Value>, usize, String), ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let mut state = self.call(format!("{kind} {name} {list_type} page {page}"))?;
+
+        let mut items: Vec<_> = state
+            .repos
+            .get(&name)
+            .map(|r| {
+                r.items
+                    .iter()
+                    .filter(|(_, i)| !only_prs || i.is_pr)
+                    .map(|(n, i)| (*n, i.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        items.sort_by_key(|(number, item)| (item.updated_at, *number));
+        if let ListType::New = list_type {
+            items.reverse();
+        }
+
+        let items = items
+            .iter()
+            .map(|(number, item)| to_json(&mut state, &name, *number, item))
+            .collect();
+        let direction = match list_type {
+            ListType::New => "desc",
+            ListType::Old => "asc",
+        };
+
+        Ok((
+            items,
+            state.page_size,
+            format!("https://api.github.com/repos/{name}/{kind}?direction={direction}&"),
+        ))
+    }
+}
+
+#[async_trait]
+impl Forge for FakeGithub {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        let page = requested_page(page, url);
+        let (items, page_size, base) =
+            self.list_items("pulls", repo, list_type, page, true, State::pr_json)?;
+        paginate(items, page_size, page, base)
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        let page = requested_page(page, url);
+        let (items, page_size, base) =
+            self.list_items("issues", repo, list_type, page, false, State::issue_json)?;
+        paginate(items, page_size, page, base)
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("comments {name}#{issue_number} page {page}"))?;
+
+        let comments: Vec<_> = state
+            .repos
+            .get(&name)
+            .and_then(|r| r.comments.get(&issue_number))
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| since.is_none_or(|since| c.updated_at >= since.timestamp()))
+            .collect();
+
+        let items = comments
+            .iter()
+            .map(|c| {
+                let url = format!("https://api.github.com/repos/{name}/issues/comments/{}", c.id);
+                json!({
+                    "id": c.id,
+                    "node_id": format!("IC_{}", c.id),
+                    "url": url,
+                    "html_url": format!("https://github.com/{name}/issues/{issue_number}#issuecomment-{}", c.id),
+                    "issue_url": format!("https://api.github.com/repos/{name}/issues/{issue_number}"),
+                    "body": c.body,
+                    "author_association": state.association(&c.author),
+                    "user": state.author(&c.author),
+                    "created_at": timestamp(c.created_at),
+                    "updated_at": timestamp(c.updated_at),
+                })
+            })
+            .collect();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/repos/{name}/issues/{issue_number}/comments?"),
+        )
+    }
+
+    async fn list_discussions(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Discussion>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("discussions {name} {list_type} page {page}"))?;
+
+        let mut discussions: Vec<_> = state
+            .repos
+            .get(&name)
+            .map(|r| r.discussions.clone().into_iter().collect())
+            .unwrap_or_default();
+        discussions.sort_by_key(|(number, discussion)| (discussion.updated_at, *number));
+        if let ListType::New = list_type {
+            discussions.reverse();
+        }
+
+        let items = discussions
+            .iter()
+            .map(|(number, discussion)| state.discussion_json(&name, *number, discussion))
+            .collect();
+        let page_size = state.page_size;
+        drop(state);
+        // the cursor of the next page
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("{name}/discussions/{list_type}?"),
+        )
+    }
+
+    async fn list_discussion_comments(
+        &self,
+        node_id: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<DiscussionComment>, ForgeError> {
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("discussion comments {node_id} page {page}"))?;
+
+        // the discussion, and the comment whose replies are listed
+        let listed = |(name, repo): (&String, &FakeRepo)| {
+            repo.discussions.iter().find_map(|(number, discussion)| {
+                let parent = match node_id.strip_prefix("DC_") {
+                    Some(id) => Some(id.parse().ok()?),
+                    None if node_id == format!("D_{name}#{number}") => None,
+                    None => return None,
+                };
+                let exists = parent.is_none_or(|id| discussion.comments.iter().any(|c| c.id == id));
+                exists.then(|| (name.clone(), *number, discussion.clone(), parent))
+            })
+        };
+        let (name, number, discussion, parent) = state
+            .repos
+            .iter()
+            .find_map(listed)
+            .ok_or_else(|| ForgeError::Other(format!("no node {node_id}")))?;
+
+        let items = state.discussion_comments_json(&name, number, &discussion, parent);
+        let page_size = state.page_size;
+        drop(state);
+        paginate(items, page_size, page, format!("{node_id}?"))
+    }
+
+    async fn list_releases(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Release>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("releases {name} page {page}"))?;
+
+        let releases = state
+            .repos
+            .get(&name)
+            .map(|r| r.releases.clone())
+            .unwrap_or_default();
+        let items = releases
+            .iter()
+            .rev()
+            .map(|r| {
+                let url = format!("https://api.github.com/repos/{name}/releases/{}", r.id);
+                json!({
+                    "url": url,
+                    "html_url": format!("https://github.com/{name}/releases/tag/{}", r.tag_name),
+                    "assets_url": format!("{url}/assets"),
+                    "upload_url": format!("https://uploads.github.com/repos/{name}/releases/{}/assets{{?name,label}}", r.id),
+                    "tarball_url": format!("https://api.github.com/repos/{name}/tarball/{}", r.tag_name),
+                    "zipball_url": format!("https://api.github.com/repos/{name}/zipball/{}", r.tag_name),
+                    "id": r.id,
+                    "node_id": format!("RE_{}", r.id),
+                    "tag_name": r.tag_name,
+                    "target_commitish": "main",
+                    "name": r.name,
+                    "body": r.body,
+                    "draft": r.draft,
+                    "prerelease": r.prerelease,
+                    "created_at": timestamp(r.created_at),
+                    "published_at": r.published_at.map(timestamp),
+                    "author": state.author(&r.author),
+                    "assets": [],
+                })
+            })
+            .collect();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/repos/{name}/releases?"),
+        )
+    }
+
+    async fn list_tags(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Tag>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let state = self.call(format!("tags {name} page {page}"))?;
+
+        let items = state
+            .repos
+            .get(&name)
+            .map(|r| {
+                r.tags
+                    .iter()
+                    .rev()
+                    .map(|(tag, sha)| {
+                        json!({
+                            "name": tag,
+                            "commit": {
+                                "sha": sha,
+                                "url": format!("https://api.github.com/repos/{name}/commits/{sha}"),
+                            },
+                            "zipball_url": format!("https://api.github.com/repos/{name}/zipball/refs/tags/{tag}"),
+                            "tarball_url": format!("https://api.github.com/repos/{name}/tarball/refs/tags/{tag}"),
+                            "node_id": format!("REF_{tag}"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/repos/{name}/tags?"),
+        )
+    }
+
+    async fn list_collaborators(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Collaborator>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("collaborators {name} page {page}"))?;
+
+        let collaborators = state
+            .repos
+            .get(&name)
+            .map(|r| r.collaborators.clone())
+            .unwrap_or_default();
+        let items = collaborators
+            .iter()
+            .map(|(login, permission)| {
+                // every permission includes the ones below it
+                let levels = ["read", "triage", "write", "maintain", "admin"];
+                let level = levels.iter().position(|l| l == permission).unwrap_or(0);
+                let mut collaborator = state.author(login);
+                collaborator["permissions"] = json!({
+                    "pull": true,
+                    "triage": level >= 1,
+                    "push": level >= 2,
+                    "maintain": level >= 3,
+                    "admin": level >= 4,
+                });
+                collaborator["role_name"] = permission.clone().into();
+                collaborator
+            })
+            .collect();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/repos/{name}/collaborators?"),
+        )
+    }
+
+    async fn list_repo_teams(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Team>, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let page = requested_page(page, url);
+        let state = self.call(format!("teams {name} page {page}"))?;
+
+        let org = &repo.organization;
+        let items = state
+            .repos
+            .get(&name)
+            .map(|r| {
+                r.teams
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (slug, permission))| {
+                        let (team_name, _) = &state.teams[&format!("{org}/{slug}")];
+                        let url = format!("https://api.github.com/orgs/{org}/teams/{slug}");
+                        // teams have the api's older names for read and write
+                        let permission = match permission.as_str() {
+                            "read" => "pull",
+                            "write" => "push",
+                            other => other,
+                        };
+                        json!({
+                            "id": i + 1,
+                            "node_id": format!("T_{org}_{slug}"),
+                            "url": url,
+                            "html_url": format!("https://github.com/orgs/{org}/teams/{slug}"),
+                            "name": team_name,
+                            "slug": slug,
+                            "description": null,
+                            "privacy": "closed",
+                            "permission": permission,
+                            "members_url": format!("{url}/members{{/member}}"),
+                            "repositories_url": format!("{url}/repos"),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/repos/{name}/teams?"),
+        )
+    }
+
+    async fn list_team_members(
+        &self,
+        organization: &str,
+        slug: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Author>, ForgeError> {
+        let page = requested_page(page, url);
+        let mut state = self.call(format!("team members {organization}/{slug} page {page}"))?;
+
+        let members = state
+            .teams
+            .get(&format!("{organization}/{slug}"))
+            .map(|(_, members)| members.clone())
+            .unwrap_or_default();
+        let items = members.iter().map(|login| state.author(login)).collect();
+
+        let page_size = state.page_size;
+        drop(state);
+        paginate(
+            items,
+            page_size,
+            page,
+            format!("https://api.github.com/orgs/{organization}/teams/{slug}/members?"),
+        )
+    }
+
+    async fn repository(&self, repo: &Repo) -> Result<Repository, ForgeError> {
+        let name = format!("{}/{}", repo.organization, repo.name);
+        let state = self.call(format!("repo {name}"))?;
+
+        // like listings, repositories that weren't scripted exist but are empty
+        let id = state.repos.keys().position(|r| *r == name).unwrap_or(0) + 1;
+        let fake = state.repos.get(&name);
+        let metadata = fake.map(|r| r.metadata.clone()).unwrap_or_default();
+        let open_issues = fake.map_or(0, |r| r.items.values().filter(|i| !i.closed).count());
+
+        let url = format!("https://api.github.com/repos/{name}");
+        let repository = json!({
+            "id": id,
+            "node_id": format!("R_{id}"),
+            "name": repo.name,
+            "full_name": name,
+            "private": metadata.visibility != "public",
+            "html_url": format!("https://github.com/{name}"),
+            "description": metadata.description,
+            "fork": false,
+            "url": url,
+            "default_branch": metadata.default_branch,
+            "topics": metadata.topics,
+            "visibility": metadata.visibility,
+            "archived": metadata.archived,
+            "stargazers_count": metadata.stars,
+            // the api's watchers are its stars
+            "watchers_count": metadata.stars,
+            "subscribers_count": metadata.watchers,
+            "forks_count": metadata.forks,
+            "open_issues_count": open_issues,
+        });
+        serde_json::from_value(repository).map_err(|e| ForgeError::Other(e.to_string()))
+    }
+
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        let mut state = self.call(format!("user {user_id}"))?;
+        let Some(login) = state
+            .users
+            .iter()
+            .find(|(login, id)| **id == user_id && !state.deleted.contains(*login))
+            .map(|(login, _)| login.clone())
+        else {
+            return Err(ForgeError::Other(format!("no user {user_id}")));
+        };
+
+        let profile = state.profiles.get(&login).cloned().unwrap_or_default();
+        let mut user = state.author(&login);
+        user["name"] = profile.name.into();
+        let extra = json!({
+            "company": profile.company,
+            "blog": "",
+            "location": profile.location,
+            "hireable": null,
+            "bio": null,
+            "twitter_username": null,
+            "public_repos": 0,
+            "public_gists": 0,
+            "followers": 0,
+            "following": 0,
+            // accounts are made a day apart, a year before the first change
+            "created_at": timestamp(EPOCH - 365 * 86_400 + user_id as i64 * 86_400),
+            "updated_at": timestamp(EPOCH),
+        });
+        user.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(user).map_err(|e| ForgeError::Other(e.to_string()))
+    }
+}
@@ -0,0 +1,498 @@
+use std::{collections::VecDeque, str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use http::Uri;
+use octocrab::{
+    Octocrab, Page,
+    models::{
+        Author, Collaborator, Repository, UserProfile,
+        issues::{Comment, Issue},
+        pulls::PullRequest,
+        repos::{Release, Tag},
+        teams::Team,
+    },
+    params::Direction,
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use crate::{
+    GithubCredentials, Repo,
+    forge::{
+        Forge, ForgeError, ForgePage, ListType, TokenBudget, async_trait,
+        discussion::{
+            COMMENTS_PER_DISCUSSION, Connection, DISCUSSIONS_PER_PAGE, Discussion,
+            DiscussionComment, PageInfo, REPLIES_PER_COMMENT,
+        },
+    },
+};
+
+/// Lists discussions with their comments and the replies to those.
+/// The fragments and page sizes are filled in by [`discussions_query`].
+const DISCUSSIONS_QUERY: &str = r#"
+query($owner: String!, $name: String!, $direction: OrderDirection!, $after: String) {
+  repository(owner: $owner, name: $name) {
+    discussions(first: DISCUSSIONS, after: $after, orderBy: {field: UPDATED_AT, direction: $direction}) {
+      totalCount
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        id number title body url authorAssociation
+        createdAt updatedAt closed closedAt locked answerChosenAt
+        author { ...actor }
+        answerChosenBy { ...actor }
+        category { id name slug emoji isAnswerable }
+        comments(first: COMMENTS) { ...comments }
+      }
+    }
+  }
+}
+"#;
+
+/// Lists the comments of a discussion, or the replies to a comment, after `$after`.
+const DISCUSSION_COMMENTS_QUERY: &str = r#"
+query($id: ID!, $after: String) {
+  node(id: $id) {
+    ... on Discussion {
+      comments(first: COMMENTS, after: $after) { ...comments }
+    }
+    ... on DiscussionComment {
+      replies(first: REPLIES, after: $after) { ...replies }
+    }
+  }
+}
+"#;
+
+const DISCUSSION_FRAGMENTS: &str = r#"
+fragment comments on DiscussionCommentConnection {
+  pageInfo { hasNextPage endCursor }
+  nodes {
+    ...comment
+    replies(first: REPLIES) { ...replies }
+  }
+}
+
+fragment replies on DiscussionCommentConnection {
+  pageInfo { hasNextPage endCursor }
+  nodes { ...comment }
+}
+
+fragment actor on Actor {
+  __typename login
+  ... on User { databaseId }
+  ... on Bot { databaseId }
+  ... on Organization { databaseId }
+  ... on Mannequin { databaseId }
+}
+
+fragment comment on DiscussionComment {
+  id body url authorAssociation createdAt updatedAt isAnswer
+  author { ...actor }
+}
+"#;
+
+/// `query` with the fragments it uses and the page sizes filled in.
+fn discussions_query(query: &str) -> String {
+    format!("{query}{DISCUSSION_FRAGMENTS}")
+        .replace("DISCUSSIONS", &DISCUSSIONS_PER_PAGE.to_string())
+        .replace("COMMENTS", &COMMENTS_PER_DISCUSSION.to_string())
+        .replace("REPLIES", &REPLIES_PER_COMMENT.to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscussionConnection {
+    total_count: usize,
+    page_info: PageInfo,
+    nodes: Vec<Discussion>,
+}
+
+/// Talks to the real GitHub api, rotating through a set of api tokens.
+///
+/// GraphQL doesn't accept an app's id and secret, so discussions are listed with the
+/// [`GithubCredentials::graphql_token`]s. Without any, no discussions are listed.
+pub struct OctocrabForge {
+    octocrabs: Mutex<VecDeque<Arc<Octocrab>>>,
+    graphql_octocrabs: Mutex<VecDeque<Arc<Octocrab>>>,
+}
+
+impl OctocrabForge {
+    pub fn new(credentials: &[GithubCredentials]) -> Self {
+        let octocrabs = credentials
+            .iter()
+            .map(|credentials| {
+                octocrab::Octocrab::builder()
+                    .basic_auth(credentials.app_id.clone(), credentials.app_secret.clone())
+                    .build()
+                    .unwrap()
+            })
+            .map(Arc::new)
+            .collect();
+        let graphql_octocrabs = credentials
+            .iter()
+            .filter_map(|credentials| credentials.graphql_token.clone())
+            .map(|token| {
+                octocrab::Octocrab::builder()
+                    .personal_token(token)
+                    .build()
+                    .unwrap()
+            })
+            .map(Arc::new)
+            .collect();
+
+        Self {
+            octocrabs: Mutex::new(octocrabs),
+            graphql_octocrabs: Mutex::new(graphql_octocrabs),
+        }
+    }
+
+    async fn octocrab(&self) -> Arc<Octocrab> {
+        let mut octocrabs = self.octocrabs.lock().await;
+        octocrabs.rotate_left(1);
+        octocrabs.front().unwrap().clone()
+    }
+
+    /// The next client for GraphQL, None if there's no token for it.
+    async fn graphql_octocrab(&self) -> Option<Arc<Octocrab>> {
+        let mut octocrabs = self.graphql_octocrabs.lock().await;
+        octocrabs.rotate_left(1);
+        octocrabs.front().cloned()
+    }
+
+    /// Continue at `url` if it's given and valid, otherwise do the request built by `first`.
+    async fn page<T: DeserializeOwned>(
+        &self,
+        url: Option<&str>,
+        first: impl AsyncFnOnce(Arc<Octocrab>) -> octocrab::Result<Page<T>>,
+    ) -> Result<ForgePage<T>, ForgeError> {
+        let octocrab = self.octocrab().await;
+
+        let page = if let Some(url) = url
+            && let Ok(uri) = Uri::from_str(url)
+        {
+            octocrab.get_page(&Some(uri)).await?
+        } else {
+            Some(first(octocrab).await?)
+        };
+
+        Ok(match page {
+            Some(mut page) => ForgePage {
+                items: page.take_items(),
+                next: page.next.map(|i| i.to_string()),
+                last_page: page.last.as_ref().and_then(page_number),
+            },
+            None => ForgePage {
+                items: Vec::new(),
+                next: None,
+                last_page: None,
+            },
+        })
+    }
+
+    /// Send one of the discussion queries, failing on any GraphQL errors.
+    /// None without a token for GraphQL.
+    async fn graphql(&self, query: &str, variables: Value) -> Result<Option<Value>, ForgeError> {
+        let Some(octocrab) = self.graphql_octocrab().await else {
+            return Ok(None);
+        };
+        let response: Value = octocrab
+            .graphql(&json!({
+                "query": discussions_query(query),
+                "variables": variables,
+            }))
+            .await?;
+
+        if let Some(errors) = response.get("errors") {
+            return Err(ForgeError::Other(errors.to_string()));
+        }
+        Ok(Some(response))
+    }
+}
+
+/// The `page` query parameter of a pagination link.
+fn page_number(uri: &Uri) -> Option<usize> {
+    uri.query()?
+        .split('&')
+        .find_map(|param| param.strip_prefix("page="))?
+        .parse()
+        .ok()
+}
+
+fn direction(list_type: ListType) -> Direction {
+    match list_type {
+        ListType::New => Direction::Descending,
+        ListType::Old => Direction::Ascending,
+    }
+}
+
+#[async_trait]
+impl Forge for OctocrabForge {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .pulls(&repo.organization, &repo.name)
+                .list()
+                .sort(octocrab::params::pulls::Sort::Updated)
+                .direction(direction(list_type))
+                .state(octocrab::params::State::All)
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .issues(&repo.organization, &repo.name)
+                .list()
+                .sort(octocrab::params::issues::Sort::Updated)
+                .direction(direction(list_type))
+                .state(octocrab::params::State::All)
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        self.page(url, async |octocrab| {
+            let comments = octocrab.issues(&repo.organization, &repo.name);
+            let mut comments = comments.list_comments(issue_number);
+
+            if let Some(since) = since {
+                comments = comments.since(since);
+            }
+
+            comments.page(page as u32).per_page(100).send().await
+        })
+        .await
+    }
+
+    async fn list_discussions(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        _page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Discussion>, ForgeError> {
+        let direction = match list_type {
+            ListType::New => "DESC",
+            ListType::Old => "ASC",
+        };
+        let Some(response) = self
+            .graphql(
+                DISCUSSIONS_QUERY,
+                json!({
+                    "owner": repo.organization,
+                    "name": repo.name,
+                    "direction": direction,
+                    "after": url,
+                }),
+            )
+            .await?
+        else {
+            return Ok(ForgePage {
+                items: Vec::new(),
+                next: None,
+                last_page: None,
+            });
+        };
+
+        let connection = response
+            .pointer("/data/repository/discussions")
+            .cloned()
+            .ok_or_else(|| ForgeError::Other(format!("no discussions for {repo:?}")))?;
+        let DiscussionConnection {
+            total_count,
+            page_info,
+            nodes,
+        } = serde_json::from_value(connection).map_err(|e| ForgeError::Other(e.to_string()))?;
+
+        let has_next = page_info.has_next_page;
+        Ok(ForgePage {
+            items: nodes,
+            next: page_info.next().map(ToString::to_string),
+            last_page: has_next.then(|| total_count.div_ceil(DISCUSSIONS_PER_PAGE)),
+        })
+    }
+
+    async fn list_discussion_comments(
+        &self,
+        node_id: &str,
+        _page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<DiscussionComment>, ForgeError> {
+        let Some(response) = self
+            .graphql(
+                DISCUSSION_COMMENTS_QUERY,
+                json!({ "id": node_id, "after": url }),
+            )
+            .await?
+        else {
+            return Ok(ForgePage {
+                items: Vec::new(),
+                next: None,
+                last_page: None,
+            });
+        };
+
+        let connection = ["/data/node/comments", "/data/node/replies"]
+            .into_iter()
+            .find_map(|pointer| response.pointer(pointer))
+            .cloned()
+            .ok_or_else(|| ForgeError::Other(format!("no comments for {node_id}")))?;
+        let Connection { nodes, page_info } =
+            serde_json::from_value(connection).map_err(|e| ForgeError::Other(e.to_string()))?;
+
+        Ok(ForgePage {
+            items: nodes,
+            next: page_info.next().map(ToString::to_string),
+            last_page: None,
+        })
+    }
+
+    async fn list_releases(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Release>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .repos(&repo.organization, &repo.name)
+                .releases()
+                .list()
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_tags(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Tag>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .repos(&repo.organization, &repo.name)
+                .list_tags()
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_collaborators(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Collaborator>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .repos(&repo.organization, &repo.name)
+                .list_collaborators()
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_repo_teams(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Team>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .repos(&repo.organization, &repo.name)
+                .list_teams()
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn list_team_members(
+        &self,
+        organization: &str,
+        slug: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Author>, ForgeError> {
+        self.page(url, async |octocrab| {
+            octocrab
+                .teams(organization)
+                .members(slug)
+                .page(page as u32)
+                .per_page(100)
+                .send()
+                .await
+        })
+        .await
+    }
+
+    async fn repository(&self, repo: &Repo) -> Result<Repository, ForgeError> {
+        Ok(self
+            .octocrab()
+            .await
+            .repos(&repo.organization, &repo.name)
+            .get()
+            .await?)
+    }
+
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        Ok(self.octocrab().await.users_by_id(user_id).profile().await?)
+    }
+
+    async fn rate_limits(&self) -> Result<Vec<TokenBudget>, ForgeError> {
+        let octocrabs: Vec<_> = self.octocrabs.lock().await.iter().cloned().collect();
+
+        let mut budgets = Vec::new();
+        for octocrab in octocrabs {
+            let core = octocrab.ratelimit().get().await?.resources.core;
+            budgets.push(TokenBudget {
+                limit: core.limit as u64,
+                used: core.used as u64,
+                remaining: core.remaining as u64,
+                reset: DateTime::from_timestamp_secs(core.reset as i64).unwrap_or_default(),
+            });
+        }
+        Ok(budgets)
+    }
+}
@@ -0,0 +1,58 @@
+//! GitHub api objects as json, for building octocrab models from data
+//! that didn't come from the api, like in [`fake`](super::fake) and imports.
+
+use serde_json::{Value, json};
+
+use crate::{GHOST_ID, GHOST_LOGIN};
+
+pub fn user(login: &str, id: u64, name: Option<&str>) -> Value {
+    let url = format!("https://api.github.com/users/{login}");
+    json!({
+        "login": login,
+        "id": id,
+        "node_id": format!("U_{id}"),
+        "avatar_url": format!("https://avatars.githubusercontent.com/u/{id}"),
+        "gravatar_id": "",
+        "url": url,
+        "html_url": format!("https://github.com/{login}"),
+        "followers_url": format!("{url}/followers"),
+        "following_url": format!("{url}/following"),
+        "gists_url": format!("{url}/gists"),
+        "starred_url": format!("{url}/starred"),
+        "subscriptions_url": format!("{url}/subscriptions"),
+        "organizations_url": format!("{url}/orgs"),
+        "repos_url": format!("{url}/repos"),
+        "events_url": format!("{url}/events"),
+        "received_events_url": format!("{url}/received_events"),
+        "type": "User",
+        "site_admin": false,
+        "name": name,
+        "patch_url": null,
+    })
+}
+
+/// The user that stands in for deleted accounts.
+pub fn ghost() -> Value {
+    user(GHOST_LOGIN, GHOST_ID, None)
+}
+
+/// Make `object` authored by the [`ghost`] if its `user` is null or missing.
+pub fn fill_ghost(object: &mut Value) {
+    if let Some(object) = object.as_object_mut()
+        && object.get("user").is_none_or(Value::is_null)
+    {
+        object.insert("user".to_string(), ghost());
+    }
+}
+
+pub fn label(repo: &str, id: usize, name: &str, description: Option<&str>, color: &str) -> Value {
+    json!({
+        "id": id,
+        "node_id": format!("L_{name}"),
+        "url": format!("https://api.github.com/repos/{repo}/labels/{id}"),
+        "name": name,
+        "description": description,
+        "color": color,
+        "default": false,
+    })
+}
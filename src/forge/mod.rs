@@ -0,0 +1,251 @@
+//! Everything that talks to GitHub goes through the [`Forge`] trait.
+//!
+//! [`OctocrabForge`] is the real thing, [`fake::FakeGithub`] serves scripted
+//! repositories from memory so the scheduling and pagination logic can be tested offline.
+
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use octocrab::models::{
+    Author, Collaborator, Repository, UserProfile,
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+    repos::{Release, Tag},
+    teams::Team,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Repo,
+    forge::discussion::{Discussion, DiscussionComment},
+};
+
+pub mod cassette;
+pub mod discussion;
+pub mod fake;
+mod github;
+pub(crate) mod json;
+
+pub use crate::requests::ListType;
+pub use async_trait::async_trait;
+pub use github::OctocrabForge;
+
+/// One page of a paginated listing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgePage<T> {
+    pub items: Vec<T>,
+    /// Url of the next page, `None` if this is the last page.
+    pub next: Option<String>,
+    /// Number of the last page (counting from 1), when the forge reported it.
+    /// Like on GitHub, the last page itself doesn't report it.
+    #[serde(default)]
+    pub last_page: Option<usize>,
+}
+
+/// The api budget of one token, as reported by the forge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenBudget {
+    pub limit: u64,
+    pub used: u64,
+    pub remaining: u64,
+    /// When `remaining` is reset to `limit` again
+    pub reset: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum ForgeError {
+    Octocrab(octocrab::Error),
+    /// Any error that doesn't come from octocrab, for example from a [`fake::FakeGithub`].
+    Other(String),
+}
+
+impl Display for ForgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForgeError::Octocrab(e) => write!(f, "{e}"),
+            ForgeError::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ForgeError {}
+
+impl From<octocrab::Error> for ForgeError {
+    fn from(value: octocrab::Error) -> Self {
+        Self::Octocrab(value)
+    }
+}
+
+/// A source of repository metadata.
+///
+/// All listings are paginated. A listing is started with `page` 0 and `url` `None`,
+/// after which [`ForgePage::next`] is passed back as `url` (together with the next page number)
+/// to continue where the previous page left off.
+#[async_trait]
+pub trait Forge: Send + Sync {
+    /// List pull requests ordered by the time they were last updated.
+    /// [`ListType::New`] lists the most recently updated ones first.
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError>;
+
+    /// List issues ordered by the time they were last updated.
+    /// Like on GitHub, this listing includes pull requests.
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError>;
+
+    /// List the comments on an issue or pull request,
+    /// optionally only those updated after `since`.
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError>;
+
+    /// List discussions ordered by the time they were last updated, with their comments.
+    ///
+    /// Discussions are paginated with GraphQL cursors, `url` is the cursor of the
+    /// previous page. Forges without discussions list none.
+    async fn list_discussions(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Discussion>, ForgeError> {
+        let _ = (repo, list_type, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List the comments of a discussion, or the replies to a comment on one, past those
+    /// that were listed with it. `node_id` is the GraphQL node id of the discussion or
+    /// comment, and like for [`Forge::list_discussions`], `url` is a cursor.
+    async fn list_discussion_comments(
+        &self,
+        node_id: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<DiscussionComment>, ForgeError> {
+        let _ = (node_id, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List releases, newest first. Forges without releases list none.
+    async fn list_releases(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Release>, ForgeError> {
+        let _ = (repo, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List tags with the commit they point at. Forges without tags list none.
+    async fn list_tags(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Tag>, ForgeError> {
+        let _ = (repo, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List the users with access to a repository, with their highest permission.
+    /// This includes access through teams and the organization.
+    /// Forges without collaborators list none.
+    async fn list_collaborators(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Collaborator>, ForgeError> {
+        let _ = (repo, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List the teams with access to a repository, with their permission on it.
+    /// Forges without teams list none.
+    async fn list_repo_teams(
+        &self,
+        repo: &Repo,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Team>, ForgeError> {
+        let _ = (repo, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// List the members of a team of `organization`, including those of its child teams.
+    async fn list_team_members(
+        &self,
+        organization: &str,
+        slug: &str,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Author>, ForgeError> {
+        let _ = (organization, slug, page, url);
+        Ok(ForgePage {
+            items: Vec::new(),
+            next: None,
+            last_page: None,
+        })
+    }
+
+    /// The metadata of a repository, like its description, topics and star count.
+    ///
+    /// Forges that can't look repositories up fail, and no metadata is stored.
+    async fn repository(&self, repo: &Repo) -> Result<Repository, ForgeError> {
+        Err(ForgeError::Other(format!("can't look up {repo:?}")))
+    }
+
+    /// The full profile of a user, by id since logins can change.
+    ///
+    /// Forges that can't look users up fail, and profiles are left empty.
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        Err(ForgeError::Other(format!("can't look up user {user_id}")))
+    }
+
+    /// The remaining api budget of every token this forge uses.
+    /// Checking this should not use up any budget.
+    async fn rate_limits(&self) -> Result<Vec<TokenBudget>, ForgeError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,208 @@
+//! Importing [GH Archive](https://www.gharchive.org) event files.
+//!
+//! Only `IssuesEvent`, `PullRequestEvent` and `IssueCommentEvent` are imported,
+//! other events are ignored.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use flate2::read::MultiGzDecoder;
+use octocrab::models::{
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    GithubDb, Repo,
+    database::updates::ProcessStatus,
+    forge::json,
+    import::{ImportError, ImportSummary},
+};
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    repo: EventRepo,
+    payload: Value,
+}
+
+#[derive(Deserialize)]
+struct EventRepo {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct IssuesPayload {
+    issue: Issue,
+}
+
+#[derive(Deserialize)]
+struct PullRequestPayload {
+    pull_request: PullRequest,
+}
+
+#[derive(Deserialize)]
+struct IssueCommentPayload {
+    action: String,
+    issue: Issue,
+    comment: Comment,
+}
+
+// only lives until it's matched on
+#[allow(clippy::large_enum_variant)]
+enum Payload {
+    Issue(IssuesPayload),
+    PullRequest(PullRequestPayload),
+    Comment(IssueCommentPayload),
+}
+
+impl GithubDb {
+    /// Import a GH Archive file with one event per line, gzipped if it ends in `.gz`,
+    /// see the [module documentation](crate::import).
+    ///
+    /// Only events of `repos` are imported, or of every repository if it's empty.
+    /// Comments of issues and pull requests that weren't in the database yet are queued
+    /// to be fetched, since the archive only has those made while it was recorded.
+    pub async fn import_archive(
+        &self,
+        path: impl AsRef<Path>,
+        repos: &[Repo],
+    ) -> Result<ImportSummary, ImportError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(ImportError::io(path))?;
+        let reader: Box<dyn Read + Send> = if path.extension().is_some_and(|e| e == "gz") {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        let mut summary = ImportSummary::default();
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(ImportError::io(path))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Ok(mut event) = serde_json::from_str::<Event>(&line) else {
+                tracing::debug!("{}:{}: not an event", path.display(), i + 1);
+                summary.invalid += 1;
+                continue;
+            };
+            let Ok(repo) = event.repo.name.parse::<Repo>() else {
+                summary.invalid += 1;
+                continue;
+            };
+            if !repos.is_empty() && !repos.contains(&repo) {
+                continue;
+            }
+
+            // octocrab requires the author of issues and comments, deleted accounts don't have one
+            for key in ["issue", "pull_request", "comment"] {
+                if let Some(object) = event.payload.get_mut(key) {
+                    json::fill_ghost(object);
+                }
+            }
+            let payload = match event.kind.as_str() {
+                "IssuesEvent" => serde_json::from_value(event.payload).map(Payload::Issue),
+                "PullRequestEvent" => {
+                    serde_json::from_value(event.payload).map(Payload::PullRequest)
+                }
+                "IssueCommentEvent" => serde_json::from_value(event.payload).map(Payload::Comment),
+                _ => continue,
+            };
+            match payload {
+                Ok(Payload::Issue(IssuesPayload { issue })) => {
+                    self.import_issue(repo, issue, &mut summary).await
+                }
+                Ok(Payload::PullRequest(PullRequestPayload { pull_request })) => {
+                    self.import_pr(repo, pull_request, &mut summary).await
+                }
+                Ok(Payload::Comment(payload)) => {
+                    self.import_comment(repo, payload, &mut summary).await
+                }
+                Err(e) => {
+                    tracing::debug!("{}:{}: {e}", path.display(), i + 1);
+                    summary.invalid += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    async fn import_issue(&self, repo: Repo, issue: Issue, summary: &mut ImportSummary) {
+        let number = issue.number;
+        if self
+            .has_item_since(number, issue.updated_at.timestamp())
+            .await
+        {
+            summary.stale += 1;
+            return;
+        }
+
+        if issue.pull_request.is_some() {
+            summary.pull_requests += 1;
+        } else {
+            summary.issues += 1;
+        }
+        let status = self.store_issue(repo.clone(), issue).await;
+        if status == ProcessStatus::New {
+            self.add_comments_updated_req(status, repo, None, number)
+                .await;
+        }
+    }
+
+    async fn import_pr(&self, repo: Repo, pr: PullRequest, summary: &mut ImportSummary) {
+        let number = pr.number;
+        let updated_at = pr.updated_at.or(pr.created_at).map(|t| t.timestamp());
+        // without a timestamp it can't be fresher than what's stored
+        if self
+            .has_item_since(number, updated_at.unwrap_or(i64::MIN))
+            .await
+        {
+            summary.stale += 1;
+            return;
+        }
+
+        summary.pull_requests += 1;
+        let status = self.process_pr(repo.clone(), pr).await;
+        if status == ProcessStatus::New {
+            self.add_comments_updated_req(status, repo, None, number)
+                .await;
+        }
+    }
+
+    async fn import_comment(
+        &self,
+        repo: Repo,
+        IssueCommentPayload {
+            action,
+            issue,
+            comment,
+        }: IssueCommentPayload,
+        summary: &mut ImportSummary,
+    ) {
+        let number = issue.number;
+        self.import_issue(repo.clone(), issue, summary).await;
+        // there's no way to remove comments from the database
+        if action == "deleted" {
+            return;
+        }
+
+        let updated_at = comment.updated_at.unwrap_or(comment.created_at);
+        if self
+            .has_comment_since(*comment.id, updated_at.timestamp())
+            .await
+        {
+            summary.stale += 1;
+            return;
+        }
+        summary.comments += 1;
+        self.process_comment(repo, comment, number).await;
+    }
+}
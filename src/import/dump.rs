@@ -0,0 +1,416 @@
+//! Importing a jsonl [export](crate::export).
+//!
+//! Rows are turned back into GitHub api objects so they can go through `process_*`.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use octocrab::models::{
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::{
+    GithubDb, Repo,
+    database::legacy,
+    enums::AuthorAssociation,
+    export::{
+        CommentRecord, IssueRecord, LabelLinkRecord, LabelRecord, PullRequestRecord,
+        UserLinkRecord, UserRecord,
+    },
+    forge::json,
+    import::{ImportError, ImportSummary},
+};
+
+/// `(repo, number)`
+type Key = (String, i64);
+
+/// Everything in a dump except issues and comments, which are streamed.
+#[derive(Default)]
+struct Dump {
+    users: HashMap<String, UserRecord>,
+    labels: HashMap<String, LabelRecord>,
+    label_links: HashMap<Key, Vec<String>>,
+    assignments: HashMap<Key, Vec<String>>,
+    review_requests: HashMap<Key, Vec<String>>,
+    pull_requests: HashMap<Key, PullRequestRecord>,
+}
+
+impl GithubDb {
+    /// Import a directory written by [`GithubDb::export`] with
+    /// [`ExportFormat::Jsonl`](crate::export::ExportFormat::Jsonl),
+    /// see the [module documentation](crate::import).
+    ///
+    /// `issues.jsonl` is required, the other files are optional.
+    /// Links between issues and pull requests aren't imported.
+    pub async fn import_dump(&self, dir: impl AsRef<Path>) -> Result<ImportSummary, ImportError> {
+        let dir = dir.as_ref();
+        let mut summary = ImportSummary::default();
+        let dump = Dump::load(dir)?;
+
+        let mut imported = Vec::new();
+        if !path(dir, "issues").exists() {
+            let error = io::ErrorKind::NotFound.into();
+            return Err(ImportError::Io(path(dir, "issues"), error));
+        }
+        for record in records::<IssueRecord>(dir, "issues")? {
+            let (line, issue) = record?;
+            let (Ok(repo), Ok(updated_at)) = (
+                issue.repo.parse::<Repo>(),
+                DateTime::parse_from_rfc3339(&issue.updated_at),
+            ) else {
+                summary.invalid += 1;
+                continue;
+            };
+            let number = issue.number as u64;
+            if self.has_item_since(number, updated_at.timestamp()).await {
+                summary.stale += 1;
+                continue;
+            }
+
+            let key = (issue.repo.clone(), issue.number);
+            let error = |error| ImportError::Json {
+                path: path(dir, "issues"),
+                line,
+                error,
+            };
+            if issue.is_pull_request {
+                let Some(pr) = dump.pull_requests.get(&key) else {
+                    summary.invalid += 1;
+                    continue;
+                };
+                let pr: PullRequest =
+                    serde_json::from_value(dump.pull_request(&issue, pr)).map_err(error)?;
+                self.process_pr(repo.clone(), pr).await;
+                summary.pull_requests += 1;
+            } else {
+                summary.issues += 1;
+            }
+            let issue: Issue = serde_json::from_value(dump.issue(&issue)).map_err(error)?;
+            self.store_issue(repo, issue).await;
+            imported.push(number);
+        }
+
+        for record in records::<CommentRecord>(dir, "comments")? {
+            let (line, comment) = record?;
+            let (Ok(repo), Ok(updated_at)) = (
+                comment.repo.parse::<Repo>(),
+                DateTime::parse_from_rfc3339(&comment.updated_at),
+            ) else {
+                summary.invalid += 1;
+                continue;
+            };
+            let number = comment.number as u64;
+            if !self.has_item(number).await {
+                summary.invalid += 1;
+                continue;
+            }
+            if self
+                .has_comment_since(comment.comment_id as u64, updated_at.timestamp())
+                .await
+            {
+                summary.stale += 1;
+                continue;
+            }
+
+            let comment: Comment =
+                serde_json::from_value(dump.comment(&comment)).map_err(|error| {
+                    ImportError::Json {
+                        path: path(dir, "comments"),
+                        line,
+                        error,
+                    }
+                })?;
+            self.process_comment(repo, comment, number).await;
+            summary.comments += 1;
+        }
+
+        // the dump has all their comments up to when it was made,
+        // newer ones are fetched when syncing sees the item was updated
+        for number in imported {
+            self.comments_synced(number).await;
+        }
+
+        // so they aren't fetched again, users without `created_at` never had theirs fetched
+        let profiles = dump.users.into_values();
+        self.store_dumped_profiles(profiles.filter(|user| user.created_at.is_some()).collect())
+            .await;
+
+        Ok(summary)
+    }
+
+    /// The profiles of users that are already in the database but have none.
+    async fn store_dumped_profiles(&self, users: Vec<UserRecord>) {
+        let now = Utc::now().timestamp();
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use crate::schema::*;
+
+                for record in users {
+                    let Some(row) = txn.query_one(User.github_id(record.github_id)) else {
+                        continue;
+                    };
+                    let mut user = txn.mutable(row);
+                    if user.profile_synced_timestamp.is_some() {
+                        continue;
+                    }
+                    user.display_name = record.display_name;
+                    user.kind = record.kind;
+                    user.company = record.company;
+                    user.location = record.location;
+                    user.account_created_timestamp = record
+                        .created_at
+                        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+                        .map(|at| at.timestamp());
+                    user.profile_synced_timestamp = Some(now);
+                }
+            })
+            .await
+    }
+}
+
+fn path(dir: &Path, table: &str) -> PathBuf {
+    dir.join(table).with_extension("jsonl")
+}
+
+/// The non-empty lines of `{table}.jsonl` with their line number,
+/// nothing if the file doesn't exist.
+fn records<R: DeserializeOwned>(
+    dir: &Path,
+    table: &str,
+) -> Result<impl Iterator<Item = Result<(usize, R), ImportError>>, ImportError> {
+    let path = path(dir, table);
+    let file = match File::open(&path) {
+        Ok(file) => Some(file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(ImportError::Io(path, e)),
+    };
+
+    let records = file
+        .into_iter()
+        .flat_map(|file| BufReader::new(file).lines())
+        .enumerate()
+        .filter_map(move |(i, line)| {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => return None,
+                Ok(line) => line,
+                Err(e) => return Some(Err(ImportError::Io(path.clone(), e))),
+            };
+            Some(
+                serde_json::from_str(&line)
+                    .map(|record| (i + 1, record))
+                    .map_err(|error| ImportError::Json {
+                        path: path.clone(),
+                        line: i + 1,
+                        error,
+                    }),
+            )
+        });
+    Ok(records)
+}
+
+fn table<R: DeserializeOwned>(dir: &Path, table: &str) -> Result<Vec<R>, ImportError> {
+    records(dir, table)?
+        .map(|record| record.map(|(_, record)| record))
+        .collect()
+}
+
+fn links(records: Vec<UserLinkRecord>) -> HashMap<Key, Vec<String>> {
+    let mut links: HashMap<Key, Vec<String>> = HashMap::new();
+    for link in records {
+        links
+            .entry((link.repo, link.number))
+            .or_default()
+            .push(link.user);
+    }
+    links
+}
+
+/// Exports before the association was stored like the api have `FirstTimeContributor`.
+fn api_association(association: &str) -> Option<&'static str> {
+    match legacy::author_association(association) {
+        AuthorAssociation::Unknown => None,
+        association => Some(association.as_str()),
+    }
+}
+
+impl Dump {
+    fn load(dir: &Path) -> Result<Self, ImportError> {
+        let mut dump = Dump {
+            assignments: links(table(dir, "assignments")?),
+            review_requests: links(table(dir, "review_requests")?),
+            ..Default::default()
+        };
+        for user in table::<UserRecord>(dir, "users")? {
+            dump.users.insert(user.login.clone(), user);
+        }
+        for label in table::<LabelRecord>(dir, "labels")? {
+            dump.labels.insert(label.name.clone(), label);
+        }
+        for link in table::<LabelLinkRecord>(dir, "label_links")? {
+            dump.label_links
+                .entry((link.repo, link.number))
+                .or_default()
+                .push(link.label);
+        }
+        for pr in table::<PullRequestRecord>(dir, "pull_requests")? {
+            dump.pull_requests.insert((pr.repo.clone(), pr.number), pr);
+        }
+        Ok(dump)
+    }
+
+    fn author(&self, login: &str, id: i64) -> Value {
+        let user = self.users.get(login);
+        let mut author = json::user(login, id as u64, user.map(|u| u.display_name.as_str()));
+        if let Some(kind) = user.and_then(|user| user.kind.as_deref()) {
+            author["type"] = kind.into();
+        }
+        author
+    }
+
+    /// Users that are only referenced by login, `None` if they aren't in the dump.
+    fn user(&self, login: &str) -> Option<Value> {
+        let user = self.users.get(login)?;
+        Some(json::user(
+            login,
+            user.github_id as u64,
+            Some(&user.display_name),
+        ))
+    }
+
+    fn users(&self, links: &HashMap<Key, Vec<String>>, key: &Key) -> Vec<Value> {
+        links
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter_map(|login| self.user(login))
+            .collect()
+    }
+
+    fn labels(&self, key: &Key) -> Vec<Value> {
+        let repo = &key.0;
+        self.label_links
+            .get(key)
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, name)| match self.labels.get(name) {
+                Some(label) => json::label(
+                    repo,
+                    id + 1,
+                    name,
+                    Some(label.description.as_str()).filter(|d| !d.is_empty()),
+                    &label.color,
+                ),
+                None => json::label(repo, id + 1, name, None, "ededed"),
+            })
+            .collect()
+    }
+
+    fn issue(&self, issue: &IssueRecord) -> Value {
+        let (repo, number) = (&issue.repo, issue.number);
+        let key = (repo.clone(), number);
+        let url = format!("https://api.github.com/repos/{repo}/issues/{number}");
+
+        let mut value = json!({
+            "id": number,
+            "node_id": format!("I_{number}"),
+            "url": url,
+            "repository_url": format!("https://api.github.com/repos/{repo}"),
+            "labels_url": format!("{url}/labels"),
+            "comments_url": format!("{url}/comments"),
+            "events_url": format!("{url}/events"),
+            "html_url": format!("https://github.com/{repo}/issues/{number}"),
+            "number": number,
+            "state": issue.state,
+            "state_reason": issue.state_reason,
+            "title": issue.title,
+            "body": issue.body,
+            "user": self.author(&issue.author, issue.author_id),
+            "labels": self.labels(&key),
+            "assignees": self.users(&self.assignments, &key),
+            "author_association": api_association(&issue.author_association),
+            "locked": issue.locked,
+            "active_lock_reason": issue.lock_reason,
+            "comments": 0,
+            "closed_at": issue.closed_at,
+            "closed_by": issue.closed_by.as_deref().and_then(|login| self.user(login)),
+            "created_at": issue.created_at,
+            "updated_at": issue.updated_at,
+        });
+        if issue.is_pull_request {
+            value["pull_request"] = json!({
+                "url": format!("https://api.github.com/repos/{repo}/pulls/{number}"),
+                "html_url": format!("https://github.com/{repo}/pull/{number}"),
+                "diff_url": format!("https://github.com/{repo}/pull/{number}.diff"),
+                "patch_url": format!("https://github.com/{repo}/pull/{number}.patch"),
+            });
+        }
+        value
+    }
+
+    fn pull_request(&self, issue: &IssueRecord, pr: &PullRequestRecord) -> Value {
+        let (repo, number) = (&issue.repo, issue.number);
+        let key = (repo.clone(), number);
+
+        json!({
+            "url": format!("https://api.github.com/repos/{repo}/pulls/{number}"),
+            "id": number,
+            "number": number,
+            "state": issue.state,
+            "locked": issue.locked,
+            "active_lock_reason": issue.lock_reason,
+            "maintainer_can_modify": pr.maintainer_can_modify,
+            "title": issue.title,
+            "user": self.author(&issue.author, issue.author_id),
+            "body": issue.body,
+            "labels": self.labels(&key),
+            "created_at": issue.created_at,
+            "updated_at": issue.updated_at,
+            "closed_at": issue.closed_at,
+            "merged_at": pr.merged_at,
+            "merged_by": pr.merged_by.as_deref().and_then(|login| self.user(login)),
+            "merge_commit_sha": pr.merge_commit_sha,
+            "assignees": self.users(&self.assignments, &key),
+            "requested_reviewers": self.users(&self.review_requests, &key),
+            "head": { "ref": "", "sha": pr.head_sha.clone().unwrap_or_default() },
+            "base": { "ref": "", "sha": pr.base_sha.clone().unwrap_or_default() },
+            "author_association": api_association(&issue.author_association),
+            "draft": pr.draft,
+            "mergeable": pr.mergeable,
+            "rebaseable": pr.rebaseable,
+            "mergeable_state": pr.mergeable_state,
+            "additions": pr.additions,
+            "deletions": pr.deletions,
+            "changed_files": pr.changed_files,
+            "commits": pr.commits,
+        })
+    }
+
+    fn comment(&self, comment: &CommentRecord) -> Value {
+        let (repo, number, id) = (&comment.repo, comment.number, comment.comment_id);
+        let node_id = comment.node_id.clone().unwrap_or(format!("IC_{id}"));
+        let html_url = comment.html_url.clone().unwrap_or(format!(
+            "https://github.com/{repo}/issues/{number}#issuecomment-{id}"
+        ));
+        json!({
+            "id": id,
+            "node_id": node_id,
+            "url": format!("https://api.github.com/repos/{repo}/issues/comments/{id}"),
+            "html_url": html_url,
+            "issue_url": format!("https://api.github.com/repos/{repo}/issues/{number}"),
+            "body": comment.body,
+            "author_association": api_association(&comment.author_association),
+            "user": self.author(&comment.author, comment.author_id),
+            "created_at": comment.created_at,
+            "updated_at": comment.updated_at,
+        })
+    }
+}
@@ -0,0 +1,88 @@
+//! Bootstrapping a database from offline dumps instead of the api, either
+//! a jsonl [export](crate::export) or [GH Archive](https://www.gharchive.org) event files.
+//!
+//! Everything goes through the same `process_*` functions that syncing uses.
+//! Issues, pull requests and comments that the database already has with the same
+//! or a newer `updated_at` are skipped, so an import never overwrites fresher data
+//! and can be repeated. Syncing afterwards only has to fill the gaps.
+
+use std::{
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{GithubDb, database::schema};
+
+mod archive;
+mod dump;
+
+/// Returned by [`GithubDb::import_dump`] and [`GithubDb::import_archive`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Issues that were processed, not counting pull requests
+    pub issues: usize,
+    pub pull_requests: usize,
+    pub comments: usize,
+    /// Skipped because the database already had the same or newer data
+    pub stale: usize,
+    /// Skipped because they can't be imported, like archive lines that aren't valid events
+    /// or comments on issues that are in neither the dump nor the database
+    pub invalid: usize,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(PathBuf, io::Error),
+    Json {
+        path: PathBuf,
+        line: usize,
+        error: serde_json::Error,
+    },
+}
+
+impl ImportError {
+    fn io(path: &Path) -> impl FnOnce(io::Error) -> Self {
+        let path = path.to_path_buf();
+        move |e| Self::Io(path, e)
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            ImportError::Json { path, line, error } => {
+                write!(f, "{}:{line}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl GithubDb {
+    /// Whether the database has the issue or pull request updated at or after `updated_at`.
+    async fn has_item_since(&self, number: u64, updated_at: i64) -> bool {
+        self.db
+            .transaction(move |txn| {
+                txn.query_one(schema::IssuePullRequestShared.number(number as i64))
+                    .is_some_and(|shared| txn.lazy(shared).updated_timestamp >= updated_at)
+            })
+            .await
+    }
+
+    async fn has_item(&self, number: u64) -> bool {
+        self.has_item_since(number, i64::MIN).await
+    }
+
+    /// Whether the database has the comment updated at or after `updated_at`.
+    async fn has_comment_since(&self, comment_id: u64, updated_at: i64) -> bool {
+        self.db
+            .transaction(move |txn| {
+                txn.query_one(schema::Comment.comment_id(comment_id as i64))
+                    .is_some_and(|comment| txn.lazy(comment).updated_timestamp >= updated_at)
+            })
+            .await
+    }
+}
@@ -1,31 +1,65 @@
 use std::{
-    collections::VecDeque,
     fmt::Debug,
     future::poll_fn,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::{Arc, atomic::AtomicI64},
+    sync::{
+        Arc,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
     task::Poll,
     time::Duration,
 };
 
-use octocrab::Octocrab;
-use rust_query::{DatabaseAsync, Transaction, aggregate};
+use rust_query::{Database, DatabaseAsync, Transaction, aggregate};
 use serde::{Deserialize, Serialize};
-use tokio::{sync::Mutex, task, time::interval};
+use tokio::{
+    sync::{Mutex, Semaphore, broadcast},
+    task::{self, JoinSet},
+    time::interval,
+};
 
 use crate::{
+    backup::{RunningGuard, RunningRequests, Snapshots},
     database::{schema::Schema, updates::ProcessStatus},
-    requests::{Priority, Request, limits::RequestLimits},
+    forge::{Forge, OctocrabForge},
+    metrics::Metrics,
+    requests::{
+        Request,
+        limits::{Grant, RequestLimits},
+    },
 };
 
+mod backup;
 mod database;
+pub mod enums;
+mod events;
+pub mod export;
+pub mod filter;
+pub mod forge;
+pub mod import;
+mod metrics;
+mod progress;
+pub mod queries;
 mod requests;
-
-pub use crate::database::schema;
+mod run;
+mod search;
+mod stats;
+mod users;
+
+pub use crate::backup::BackupError;
+pub use crate::database::schema::{self, OpenError};
+pub use crate::events::{Event, EventStream};
+pub use crate::filter::{Filter, FilterError, ItemSummary};
+pub use crate::progress::{Listing, ListingProgress, Progress};
+pub use crate::requests::{Priority, queue::QueuedRequest};
+pub use crate::run::RunSummary;
+pub use crate::search::{ItemKind, SearchError, SearchFilters, SearchHit};
+pub use crate::stats::Stats;
+pub use crate::users::{GHOST_ID, GHOST_LOGIN};
 pub use rust_query;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Repo {
     pub organization: String,
     pub name: String,
@@ -53,16 +87,43 @@ impl FromStr for Repo {
 pub struct GithubCredentials {
     pub app_id: String,
     pub app_secret: String,
+    /// A token for the GraphQL api, which doesn't accept the app's id and secret.
+    /// Discussions are only synced with at least one.
+    pub graphql_token: Option<String>,
 }
 
 pub struct GithubDb {
     db: DatabaseAsync<Schema>,
-    octocrabs: Mutex<VecDeque<Arc<Octocrab>>>,
+    /// The same database, for what rust-query can't do, like full-text search.
+    raw_db: Arc<Database<Schema>>,
+    forge: Arc<dyn Forge>,
 
     limits: Mutex<RequestLimits>,
     request_sequence_number: AtomicI64,
+    /// Requests taken from the queue that are being handled, for backups.
+    running: Arc<std::sync::Mutex<RunningRequests>>,
 
     refresh: Mutex<tokio::time::Interval>,
+    release_refresh: Mutex<tokio::time::Interval>,
+    repo_refresh: Mutex<tokio::time::Interval>,
+    access_refresh: Mutex<tokio::time::Interval>,
+
+    /// Request handlers that were spawned by [`GithubDb::update`] and haven't been reaped yet.
+    tasks: Mutex<JoinSet<()>>,
+    tasks_finished: AtomicU64,
+    tasks_panicked: AtomicU64,
+    shutdown_timeout: Duration,
+    /// Limits the number of request handlers that run at the same time.
+    in_flight: Arc<Semaphore>,
+
+    events: broadcast::Sender<Event>,
+
+    metrics: Metrics,
+    stats_cache: Mutex<Option<Stats>>,
+    stats_max_age: Duration,
+    snapshots: Option<Snapshots>,
+    /// Logins of users that are stored as bots, see [`GithubDb::with_bots`].
+    bots: Vec<String>,
 
     repos: Vec<Repo>,
 }
@@ -74,17 +135,47 @@ impl GithubDb {
         requests_per_hour: usize,
         repos: &[&str],
     ) -> Self {
-        let octocrabs = credentials
-            .iter()
-            .map(|GithubCredentials { app_id, app_secret }| {
-                octocrab::Octocrab::builder()
-                    .basic_auth(app_id.clone(), app_secret.clone())
-                    .build()
-                    .unwrap()
-            })
-            .map(Arc::new)
-            .collect();
-        let db = schema::migrate(db_path);
+        Self::new_with_forge(
+            db_path,
+            Arc::new(OctocrabForge::new(credentials)),
+            requests_per_hour,
+            repos,
+        )
+        .await
+    }
+
+    /// Like [`GithubDb::new`], but gets its data from any [`Forge`],
+    /// for example a [`forge::fake::FakeGithub`] in tests.
+    pub async fn new_with_forge(
+        db_path: impl AsRef<Path>,
+        forge: Arc<dyn Forge>,
+        requests_per_hour: usize,
+        repos: &[&str],
+    ) -> Self {
+        let raw_db = schema::migrate(db_path);
+        // boxed, the future is large enough to overflow a test thread's stack otherwise
+        Box::pin(Self::with_database(raw_db, forge, requests_per_hour, repos)).await
+    }
+
+    /// Like [`GithubDb::new_with_forge`], but only opens a database that already exists
+    /// and is at the current schema version, to look at what's in it without migrating.
+    pub async fn open_with_forge(
+        db_path: impl AsRef<Path>,
+        forge: Arc<dyn Forge>,
+        requests_per_hour: usize,
+        repos: &[&str],
+    ) -> Result<Self, OpenError> {
+        let raw_db = schema::open(db_path)?;
+        Ok(Box::pin(Self::with_database(raw_db, forge, requests_per_hour, repos)).await)
+    }
+
+    async fn with_database(
+        raw_db: Arc<Database<Schema>>,
+        forge: Arc<dyn Forge>,
+        requests_per_hour: usize,
+        repos: &[&str],
+    ) -> Self {
+        let db = DatabaseAsync::new(raw_db.clone());
 
         let max_seq_number = db
             .transaction_mut_ok(|txn| {
@@ -100,7 +191,8 @@ impl GithubDb {
 
         let res = Self {
             db,
-            octocrabs: Mutex::new(octocrabs),
+            raw_db,
+            forge,
             repos: repos
                 .iter()
                 .map(|f| {
@@ -111,7 +203,22 @@ impl GithubDb {
                 .collect(),
             limits: Mutex::new(RequestLimits::new(requests_per_hour)),
             request_sequence_number: AtomicI64::new(max_seq_number),
+            running: Default::default(),
             refresh: Mutex::new(interval(Duration::from_secs(60))),
+            release_refresh: Mutex::new(interval(Duration::from_secs(6 * 60 * 60))),
+            repo_refresh: Mutex::new(interval(Duration::from_secs(60 * 60))),
+            access_refresh: Mutex::new(interval(Duration::from_secs(6 * 60 * 60))),
+            tasks: Mutex::new(JoinSet::new()),
+            tasks_finished: AtomicU64::new(0),
+            tasks_panicked: AtomicU64::new(0),
+            shutdown_timeout: Duration::from_secs(30),
+            in_flight: Arc::new(Semaphore::new(16)),
+            events: broadcast::channel(1024).0,
+            metrics: Metrics::default(),
+            stats_cache: Mutex::new(None),
+            stats_max_age: Duration::from_secs(60),
+            snapshots: None,
+            bots: Vec::new(),
         };
 
         res.startup_requests().await;
@@ -119,10 +226,91 @@ impl GithubDb {
         res
     }
 
-    async fn octocrab(&self) -> Arc<Octocrab> {
-        let mut octocrabs = self.octocrabs.lock().await;
-        octocrabs.rotate_left(1);
-        octocrabs.front().unwrap().clone()
+    /// How long [`GithubDb::run`] waits for in-flight requests to finish
+    /// after being asked to shut down. Requests still running after that are aborted.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// How often the newest issues, pull requests and discussions are checked for changes.
+    ///
+    /// Defaults to a minute.
+    pub fn with_refresh_interval(mut self, period: Duration) -> Self {
+        self.refresh = Mutex::new(interval(period));
+        self
+    }
+
+    /// How often releases and tags are listed. They change rarely,
+    /// and are listed at `Index` priority.
+    ///
+    /// Defaults to six hours.
+    pub fn with_release_refresh_interval(mut self, period: Duration) -> Self {
+        self.release_refresh = Mutex::new(interval(period));
+        self
+    }
+
+    /// How often the metadata of repositories is fetched, like their description and
+    /// topics. Every fetch also stores a snapshot of their star, fork, watcher and open
+    /// issue counts, so this is the resolution of [`GithubDb::repo_history`].
+    ///
+    /// Defaults to an hour.
+    pub fn with_repo_refresh_interval(mut self, period: Duration) -> Self {
+        self.repo_refresh = Mutex::new(interval(period));
+        self
+    }
+
+    /// How often the collaborators of repositories are listed with their permissions,
+    /// together with the teams that have access and their members. Users and teams
+    /// that lost access are removed once they're all listed.
+    ///
+    /// Defaults to six hours.
+    pub fn with_access_refresh_interval(mut self, period: Duration) -> Self {
+        self.access_refresh = Mutex::new(interval(period));
+        self
+    }
+
+    /// The maximum number of requests that are handled at the same time.
+    /// While that many are in flight, no new requests are started and the
+    /// request budget is kept for later instead.
+    ///
+    /// Defaults to 16.
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.in_flight = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// How long [`GithubDb::stats`] are cached before they're computed again.
+    ///
+    /// Defaults to a minute.
+    pub fn with_stats_max_age(mut self, max_age: Duration) -> Self {
+        self.stats_max_age = max_age;
+        self
+    }
+
+    /// Write a [backup](GithubDb::backup_to) to `dir` every `every` while [`GithubDb::run`]
+    /// is running, and remove the oldest so that only `keep` of them are left.
+    /// Snapshots are named after when they were made, like `snapshot-20250101T120000.000Z.sqlite`.
+    ///
+    /// Off by default.
+    pub fn with_snapshots(mut self, dir: impl Into<PathBuf>, every: Duration, keep: usize) -> Self {
+        self.snapshots = Some(Snapshots {
+            dir: dir.into(),
+            every,
+            keep: keep.max(1),
+        });
+        self
+    }
+
+    /// Users that are stored as [bots](enums::UserKind::Bot) even though GitHub says
+    /// they're users, like `bors`. Applied when their profile is fetched.
+    ///
+    /// Defaults to none, only accounts that GitHub says are bots are stored as bots.
+    pub fn with_bots(mut self, logins: &[&str]) -> Self {
+        self.bots = logins.iter().map(ToString::to_string).collect();
+        self
     }
 
     pub async fn transaction<R: 'static + Send>(
@@ -189,6 +377,30 @@ impl GithubDb {
                 self.add_req(Priority::Index, oldissue).await;
             }
 
+            let olddiscussion = Request::OldDiscussion {
+                repo: repo.clone(),
+                page: 0,
+                url: None,
+            };
+            let olddiscussion_name = olddiscussion.name();
+
+            let num_olddiscussion = self
+                .db
+                .transaction(move |txn| {
+                    txn.query_one(aggregate(|row| {
+                        use schema::*;
+                        let r = row.join(Request);
+                        row.filter(r.name.eq(olddiscussion_name));
+                        row.count_distinct(r)
+                    }))
+                })
+                .await;
+
+            tracing::debug!("number of old discussion requests in queue: {num_olddiscussion}");
+            if num_olddiscussion == 0 {
+                self.add_req(Priority::Index, olddiscussion).await;
+            }
+
             self.add_req(
                 Priority::Update,
                 Request::NewPr {
@@ -207,6 +419,20 @@ impl GithubDb {
                 },
             )
             .await;
+            self.add_req(
+                Priority::Update,
+                Request::NewDiscussion {
+                    repo: repo.clone(),
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+        }
+
+        // without repos, like in the cli's offline commands, nothing is synced
+        if !self.repos.is_empty() {
+            self.queue_next_profile(false).await;
         }
     }
 
@@ -230,99 +456,151 @@ impl GithubDb {
                 },
             )
             .await;
+            self.add_req(
+                Priority::Update,
+                Request::NewDiscussion {
+                    repo: repo.clone(),
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+        }
+
+        self.queue_next_profile(false).await;
+    }
+
+    async fn refresh_releases(&self) {
+        for repo in &self.repos {
+            self.add_req(
+                Priority::Index,
+                Request::Releases {
+                    repo: repo.clone(),
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+            self.add_req(
+                Priority::Index,
+                Request::Tags {
+                    repo: repo.clone(),
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+        }
+    }
+
+    async fn refresh_repos(&self) {
+        for repo in &self.repos {
+            self.add_req(Priority::Index, Request::Repository { repo: repo.clone() })
+                .await;
+        }
+    }
+
+    async fn refresh_access(&self) {
+        let started_timestamp = chrono::Utc::now().timestamp();
+        for repo in &self.repos {
+            self.add_req(
+                Priority::Index,
+                Request::Collaborators {
+                    repo: repo.clone(),
+                    started_timestamp,
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+            self.add_req(
+                Priority::Index,
+                Request::RepoTeams {
+                    repo: repo.clone(),
+                    started_timestamp,
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
         }
     }
 
-    /// Call this in your main loop
+    /// Call this in your main loop, or use [`GithubDb::run`] which does that for you.
     pub async fn update(self: Arc<Self>) {
+        self.reap_tasks().await;
+
         let mut refresh = self.refresh.lock().await;
-        if poll_fn(|cx| match refresh.poll_tick(cx) {
-            Poll::Ready(r) => Poll::Ready(Some(r)),
-            Poll::Pending => Poll::Ready(None),
-        })
-        .await
-        .is_some()
-        {
+        if ticked(&mut refresh).await {
             self.refresh().await;
+            self.log_stats().await;
+        }
+        if ticked(&mut *self.release_refresh.lock().await).await {
+            self.refresh_releases().await;
+        }
+        if ticked(&mut *self.repo_refresh.lock().await).await {
+            self.refresh_repos().await;
+        }
+        if ticked(&mut *self.access_refresh.lock().await).await {
+            self.refresh_access().await;
         }
 
         self.limits
             .lock()
             .await
-            .update(async |c| {
-                if let Some(r) = self.next_request(c).await {
+            .update(async |c, graphql| {
+                let Ok(permit) = self.in_flight.clone().try_acquire_owned() else {
+                    tracing::debug!("too many requests in flight, holding back {c:?}");
+                    return Grant::Saturated;
+                };
+
+                if let Some((r, running)) = self.next_request(c, graphql).await {
+                    let points = r.graphql_points();
                     let this = self.clone();
-                    task::spawn(async move {
+                    self.tasks.lock().await.spawn(async move {
                         this.handle_request(r).await;
+                        drop(running);
+                        drop(permit);
                     });
-                    true
+                    match points {
+                        Some(points) => Grant::StartedGraphql(points),
+                        None => Grant::Started,
+                    }
                 } else {
                     tracing::debug!("no request for category {c:?}");
-                    false
+                    Grant::Empty
                 }
             })
             .await;
-
-        self.stats().await;
     }
 
-    async fn stats(&self) {
-        let (num_prs, num_issues, num_shared, num_users, num_comments, num_labels, num_requests) =
-            self.db
-                .transaction(move |txn| {
-                    (
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(PullRequest);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(Issue);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(IssuePullRequestShared);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(User);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(Comment);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(Label);
-                            row.count_distinct(r)
-                        })),
-                        txn.query_one(aggregate(|row| {
-                            use schema::*;
-                            let r = row.join(Request);
-                            row.count_distinct(r)
-                        })),
-                    )
-                })
-                .await;
+    /// Collect the results of request handlers that finished since the last call.
+    async fn reap_tasks(&self) {
+        let mut tasks = self.tasks.lock().await;
+        while let Some(res) = tasks.try_join_next() {
+            self.record_task_result(res);
+        }
+    }
 
-        tracing::info!("{}", self.limits.lock().await);
-        tracing::info!(
-            "prs: {num_prs} issues: {num_issues} shared: {num_shared} users: {num_users} comments: {num_comments} labels: {num_labels} pending requests: {num_requests}"
-        );
-        let avg_time_btwn_req = self.limits.lock().await.average_time_between_requests();
-        let req_per_hour = (3600 * 1000) / avg_time_btwn_req.as_millis().max(1);
-        tracing::info!(
-            "average time between requests: {avg_time_btwn_req:?} i.e. {req_per_hour} req/h"
-        )
+    fn record_task_result(&self, res: Result<(), task::JoinError>) {
+        match res {
+            Ok(()) => {
+                self.tasks_finished.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) if e.is_panic() => {
+                tracing::error!("request handler panicked: {e}");
+                self.tasks_panicked.fetch_add(1, Ordering::Relaxed);
+            }
+            // aborted tasks are accounted for by whoever aborted them
+            Err(_) => {}
+        }
     }
 
-    async fn next_request(&self, c: Priority) -> Option<Request> {
+    /// The first queued request of category `c`, leaving GraphQL requests queued
+    /// unless `graphql` is set.
+    async fn next_request(&self, c: Priority, graphql: bool) -> Option<(Request, RunningGuard)> {
         loop {
+            let running = self.running.clone();
             let data = self
                 .db
                 .transaction_mut_ok(move |txn| {
@@ -331,6 +609,11 @@ impl GithubDb {
                     let req = txn.query_one(aggregate(|rows| {
                         let request = rows.join(Request);
                         rows.filter(request.category.eq(c as i64));
+                        if !graphql {
+                            for name in crate::requests::Request::GRAPHQL {
+                                rows.filter(request.name.neq(name));
+                            }
+                        }
 
                         let min_seq = rows.min(&request.sequence_number);
                         let min_seq = rows.filter_some(min_seq);
@@ -338,8 +621,19 @@ impl GithubDb {
                         rows.min(request)
                     }))?;
 
-                    let data = &txn.lazy(req).data;
-                    let request_data = serde_json::from_slice(data);
+                    let req_lazy = txn.lazy(req);
+                    let request_data = serde_json::from_slice(&req_lazy.data).map(|r| {
+                        // registered before the deletion is committed, so backups
+                        // always see the request in one of both places
+                        let running = RunningRequests::started(
+                            &running,
+                            req_lazy.sequence_number,
+                            req_lazy.category,
+                            req_lazy.name.clone(),
+                            req_lazy.data.clone(),
+                        );
+                        (r, running)
+                    });
 
                     let txn = txn.downgrade();
                     txn.delete(req).expect("already deleted");
@@ -352,8 +646,17 @@ impl GithubDb {
                 Err(e) => {
                     println!("error: {e}");
                 }
-                Ok(i) => break i,
+                Ok(i) => break Some(i),
             }
         }
     }
 }
+
+/// Whether `interval` ticked since it was last checked, without waiting for it.
+async fn ticked(interval: &mut tokio::time::Interval) -> bool {
+    poll_fn(|cx| match interval.poll_tick(cx) {
+        Poll::Ready(_) => Poll::Ready(true),
+        Poll::Pending => Poll::Ready(false),
+    })
+    .await
+}
@@ -0,0 +1,221 @@
+//! Counters about the work done by a [`GithubDb`], rendered in the
+//! [OpenMetrics](https://openmetrics.io) text format.
+//!
+//! The counters are always kept. With the `metrics` feature,
+//! `GithubDb::serve_metrics` serves them on `/metrics` for a Prometheus scraper.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{GithubDb, database::updates::ProcessStatus};
+
+#[derive(Default)]
+pub(crate) struct Metrics {
+    /// Handled requests per [`Request::name`](crate::requests::Request::name)
+    handled: Mutex<BTreeMap<&'static str, u64>>,
+    errors: AtomicU64,
+    /// Results of processing an item, per kind of item and status
+    outcomes: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+}
+
+impl Metrics {
+    pub fn request_handled(&self, name: &'static str) {
+        *self.handled.lock().unwrap().entry(name).or_default() += 1;
+    }
+
+    pub fn request_failed(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn processed(&self, kind: &'static str, status: ProcessStatus) {
+        let status = match status {
+            ProcessStatus::New => "new",
+            ProcessStatus::Updated => "updated",
+            ProcessStatus::Unchanged => "unchanged",
+        };
+        *self
+            .outcomes
+            .lock()
+            .unwrap()
+            .entry((kind, status))
+            .or_default() += 1;
+    }
+}
+
+impl GithubDb {
+    /// All metrics in the OpenMetrics text format.
+    ///
+    /// Gauges come from [`GithubDb::stats`], so they're as fresh as the cached stats.
+    pub async fn metrics(&self) -> String {
+        let stats = self.stats().await;
+        let mut out = String::new();
+
+        writeln!(out, "# TYPE github_db_requests_handled counter").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_requests_handled Requests handled, per kind of request."
+        )
+        .unwrap();
+        for (name, n) in &*self.metrics.handled.lock().unwrap() {
+            writeln!(
+                out,
+                "github_db_requests_handled_total{{request=\"{name}\"}} {n}"
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "# TYPE github_db_request_errors counter").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_request_errors Requests to the forge that failed."
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "github_db_request_errors_total {}",
+            self.metrics.errors.load(Ordering::Relaxed)
+        )
+        .unwrap();
+
+        writeln!(out, "# TYPE github_db_processed counter").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_processed Items processed, per kind and whether they were new, updated or unchanged."
+        )
+        .unwrap();
+        for ((kind, status), n) in &*self.metrics.outcomes.lock().unwrap() {
+            writeln!(
+                out,
+                "github_db_processed_total{{kind=\"{kind}\",status=\"{status}\"}} {n}"
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "# TYPE github_db_queue_depth gauge").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_queue_depth Queued requests, per category."
+        )
+        .unwrap();
+        for (category, n) in &stats.queue {
+            writeln!(
+                out,
+                "github_db_queue_depth{{category=\"{category:?}\"}} {n}"
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "# TYPE github_db_rate_limit_remaining gauge").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_rate_limit_remaining Requests left on every api token until it resets."
+        )
+        .unwrap();
+        for (i, token) in stats.tokens.iter().enumerate() {
+            writeln!(
+                out,
+                "github_db_rate_limit_remaining{{token=\"{i}\"}} {}",
+                token.remaining
+            )
+            .unwrap();
+        }
+        writeln!(out, "# TYPE github_db_rate_limit gauge").unwrap();
+        writeln!(
+            out,
+            "# HELP github_db_rate_limit Requests every api token may do per period."
+        )
+        .unwrap();
+        for (i, token) in stats.tokens.iter().enumerate() {
+            writeln!(out, "github_db_rate_limit{{token=\"{i}\"}} {}", token.limit).unwrap();
+        }
+
+        writeln!(out, "# TYPE github_db_rows gauge").unwrap();
+        writeln!(out, "# HELP github_db_rows Rows per table.").unwrap();
+        for (table, n) in [
+            ("pull_request", stats.prs),
+            ("issue", stats.issues),
+            ("issue_pull_request_shared", stats.shared),
+            ("user", stats.users),
+            ("comment", stats.comments),
+            ("label", stats.labels),
+        ] {
+            writeln!(out, "github_db_rows{{table=\"{table}\"}} {n}").unwrap();
+        }
+
+        writeln!(out, "# EOF").unwrap();
+        out
+    }
+}
+
+#[cfg(feature = "metrics")]
+mod server {
+    use std::sync::Arc;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream, ToSocketAddrs},
+    };
+
+    use crate::GithubDb;
+
+    impl GithubDb {
+        /// Serve [`GithubDb::metrics`] over http on `/metrics` until the returned future is dropped.
+        ///
+        /// ```no_run
+        /// # async fn example(gh: std::sync::Arc<github_db::GithubDb>) {
+        /// tokio::spawn(gh.clone().serve_metrics("0.0.0.0:9184"));
+        /// # }
+        /// ```
+        pub async fn serve_metrics(
+            self: Arc<Self>,
+            addr: impl ToSocketAddrs,
+        ) -> std::io::Result<()> {
+            let listener = TcpListener::bind(addr).await?;
+            tracing::info!("serving metrics on {}", listener.local_addr()?);
+
+            loop {
+                let (stream, _) = listener.accept().await?;
+                let this = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = this.respond(stream).await {
+                        tracing::debug!("metrics connection failed: {e}");
+                    }
+                });
+            }
+        }
+
+        async fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+            // we only care about the request line, so don't bother reading the rest
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).await?;
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request
+                .lines()
+                .next()
+                .and_then(|line| line.strip_prefix("GET "))
+                .and_then(|line| line.split_whitespace().next());
+
+            let (status, content_type, body) = match path {
+                Some("/metrics") => (
+                    "200 OK",
+                    "application/openmetrics-text; version=1.0.0; charset=utf-8",
+                    self.metrics().await,
+                ),
+                _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+            };
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await?;
+            stream.shutdown().await
+        }
+    }
+}
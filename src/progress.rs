@@ -0,0 +1,186 @@
+//! How far the initial indexing of every repository has gotten.
+//!
+//! The walk over all old issues and pull requests (see [`Request::OldPr`](crate::requests::Request::OldPr))
+//! can take days for big repositories. Its position is stored in the `Config` table
+//! so it survives restarts.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use rust_query::aggregate;
+use serde::{Deserialize, Serialize};
+
+use crate::{GithubDb, Repo, schema};
+
+const KEY_PREFIX: &str = "listing_progress/";
+
+/// Which of the old listings of a repository.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Listing {
+    Pulls,
+    Issues,
+    Discussions,
+}
+
+/// Position of the walk over all old issues, pull requests or discussions of one repository.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ListingProgress {
+    pub repo: String,
+    pub listing: Listing,
+    /// The last page that was fetched, counting from 1
+    pub page: usize,
+    /// None until the forge reported how many pages there are
+    pub total_pages: Option<usize>,
+    /// How often the walk reached the last page. After the first time,
+    /// everything has been indexed at least once.
+    pub passes: u64,
+}
+
+impl ListingProgress {
+    /// Pages left until the first pass is complete, None if unknown.
+    pub fn remaining_pages(&self) -> Option<usize> {
+        if self.passes > 0 {
+            return Some(0);
+        }
+        Some(self.total_pages?.saturating_sub(self.page))
+    }
+}
+
+/// Returned by [`GithubDb::progress`].
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub listings: Vec<ListingProgress>,
+    /// Issues and pull requests whose comments changed since they were last fetched,
+    /// or that never had their comments fetched at all.
+    pub missing_comment_syncs: i64,
+    /// Users whose profile wasn't fetched yet
+    pub missing_profiles: i64,
+    /// Requests needed to finish the first pass of every listing, all comment syncs
+    /// and all profiles
+    pub remaining_requests: u64,
+    /// Rough estimate based on the measured request rate,
+    /// None while there's no rate measured yet.
+    pub eta: Option<Duration>,
+}
+
+impl GithubDb {
+    /// How far indexing has gotten, and an estimate of how long it will take to finish.
+    pub async fn progress(&self) -> Progress {
+        let (listings, missing_comment_syncs, missing_profiles) = self
+            .db
+            .transaction(|txn| {
+                use schema::*;
+
+                let listings = txn.query(|rows| {
+                    let config = rows.join(Config);
+                    rows.filter(config.key.starts_with(KEY_PREFIX));
+                    rows.into_vec(&config.value)
+                });
+                let missing = txn.query_one(aggregate(|rows| {
+                    let shared = rows.join(IssuePullRequestShared);
+                    rows.filter(
+                        shared
+                            .comments_synced_timestamp
+                            .unwrap_or(0)
+                            .lt(&shared.updated_timestamp),
+                    );
+                    rows.count_distinct(shared)
+                }));
+                let missing_profiles = txn.query_one(aggregate(|rows| {
+                    let user = rows.join(User);
+                    rows.filter(user.profile_synced_timestamp.is_none());
+                    rows.count_distinct(user)
+                }));
+                (listings, missing, missing_profiles)
+            })
+            .await;
+
+        let listings: Vec<ListingProgress> = listings
+            .iter()
+            .filter_map(|value| serde_json::from_str(value).ok())
+            .collect();
+
+        let remaining_requests = listings
+            .iter()
+            .filter_map(ListingProgress::remaining_pages)
+            .sum::<usize>() as u64
+            + missing_comment_syncs as u64
+            + missing_profiles as u64;
+
+        let average_time_between_requests =
+            self.limits.lock().await.average_time_between_requests();
+        let eta = (!average_time_between_requests.is_zero())
+            .then(|| average_time_between_requests.mul_f64(remaining_requests as f64));
+
+        Progress {
+            listings,
+            missing_comment_syncs,
+            missing_profiles,
+            remaining_requests,
+            eta,
+        }
+    }
+
+    /// Called after every page of an old listing was processed.
+    pub(crate) async fn record_listing_progress(
+        &self,
+        repo: &Repo,
+        listing: Listing,
+        page_num: usize,
+        has_next: bool,
+        last_page: Option<usize>,
+    ) {
+        let repo = format!("{}/{}", repo.organization, repo.name);
+        let key = format!("{KEY_PREFIX}{repo}/{listing:?}");
+
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let previous = txn
+                    .query_one(Config.key(&key))
+                    .and_then(|row| serde_json::from_str(&txn.lazy(row).value).ok());
+                let mut progress = previous.unwrap_or(ListingProgress {
+                    repo,
+                    listing,
+                    page: 0,
+                    total_pages: None,
+                    passes: 0,
+                });
+
+                // request pages count from 0
+                progress.page = page_num + 1;
+                if has_next {
+                    progress.total_pages = last_page.or(progress.total_pages);
+                } else {
+                    progress.total_pages = Some(progress.page);
+                    progress.passes += 1;
+                }
+
+                let value = serde_json::to_string(&progress).unwrap();
+                if let Err(existing) = txn.insert(Config {
+                    key,
+                    value: value.clone(),
+                }) {
+                    txn.mutable(existing).value = value;
+                }
+            })
+            .await
+    }
+
+    /// Called when all (new) comments of an issue or pull request were fetched.
+    pub(crate) async fn comments_synced(&self, issue_number: u64) {
+        let now = Utc::now().timestamp();
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                if let Some(shared) =
+                    txn.query_one(IssuePullRequestShared.number(issue_number as i64))
+                {
+                    txn.mutable(shared).comments_synced_timestamp = Some(now);
+                }
+            })
+            .await
+    }
+}
@@ -0,0 +1,860 @@
+//! Answers to common questions about the synced data, as plain Rust types.
+//!
+//! These save consumers from joining the [`schema`](crate::schema) tables themselves,
+//! and from knowing how values are stored there,
+//! like booleans as integers and [enums](crate::enums) as text.
+//! For anything more specific, see [`GithubDb::find`] and [`GithubDb::transaction`].
+
+use chrono::{DateTime, Utc};
+use rust_query::{TableRow, Transaction};
+
+use crate::{
+    GithubDb, ItemSummary, Repo,
+    database::schema::{self, Schema},
+    enums::{AuthorAssociation, ItemState, MergeableState, Permission, TransitionKind, UserKind},
+    filter::timestamp,
+};
+
+/// A pull request with the details that only pull requests have.
+#[derive(Debug, Clone)]
+pub struct PullRequestSummary {
+    pub item: ItemSummary,
+    pub draft: bool,
+    /// None if not merged
+    pub merged_at: Option<DateTime<Utc>>,
+    pub mergeable_state: MergeableState,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changed_files: u64,
+    pub commits: u64,
+    /// Logins of the users whose review is requested
+    pub requested_reviewers: Vec<String>,
+}
+
+/// A comment on an issue or pull request.
+#[derive(Debug, Clone)]
+pub struct CommentSummary {
+    pub id: u64,
+    /// Login of the author
+    pub author: String,
+    pub author_association: AuthorAssociation,
+    /// None for comments that weren't synced since it was stored
+    pub html_url: Option<String>,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A close, reopen or merge of an issue or pull request.
+#[derive(Debug, Clone)]
+pub struct StateTransitionSummary {
+    pub kind: TransitionKind,
+    /// Login of who did it, None if not known
+    pub actor: Option<String>,
+    /// For reopens, the last update of the item when it was seen open again,
+    /// so at or after the reopen itself
+    pub at: DateTime<Utc>,
+}
+
+/// Where a user or team was mentioned.
+#[derive(Debug, Clone)]
+pub struct MentionSummary {
+    /// The issue or pull request it's in
+    pub number: u64,
+    /// None if it's in the description
+    pub comment_id: Option<u64>,
+    /// Login of who wrote it
+    pub author: String,
+    /// When the description or comment was created
+    pub at: DateTime<Utc>,
+}
+
+/// A discussion, without its comments.
+#[derive(Debug, Clone)]
+pub struct DiscussionSummary {
+    pub number: u64,
+    pub title: String,
+    /// Name of its category
+    pub category: String,
+    /// Login of the author
+    pub author: String,
+    pub author_association: AuthorAssociation,
+    pub html_url: String,
+    /// None if not closed
+    pub closed_at: Option<DateTime<Utc>>,
+    pub locked: bool,
+    /// None if no comment was marked as the answer
+    pub answer_chosen_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A comment on a discussion, or a reply to one.
+#[derive(Debug, Clone)]
+pub struct DiscussionCommentSummary {
+    /// The GraphQL node id
+    pub node_id: String,
+    /// Node id of the comment this replies to, None for top-level comments
+    pub reply_to: Option<String>,
+    /// Login of the author
+    pub author: String,
+    pub author_association: AuthorAssociation,
+    pub html_url: String,
+    pub body: String,
+    /// Whether it was marked as the answer
+    pub is_answer: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A release, with the commit its tag points at.
+#[derive(Debug, Clone)]
+pub struct ReleaseSummary {
+    pub tag_name: String,
+    /// None if it has no name, GitHub shows the tag name then
+    pub name: Option<String>,
+    /// Login of the author
+    pub author: String,
+    pub html_url: String,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub created_at: DateTime<Utc>,
+    /// None for drafts
+    pub published_at: Option<DateTime<Utc>>,
+    /// None if the tag isn't in the database (yet), like for drafts
+    pub commit_sha: Option<String>,
+}
+
+/// The metadata of a repository, as of the last time it was fetched.
+#[derive(Debug, Clone)]
+pub struct RepositorySummary {
+    pub description: Option<String>,
+    pub default_branch: Option<String>,
+    /// Sorted by name
+    pub topics: Vec<String>,
+    /// Like `public`, None if not reported
+    pub visibility: Option<String>,
+    pub archived: bool,
+    pub synced_at: DateTime<Utc>,
+    /// The counts of the last fetch, there's always at least one snapshot
+    pub counts: RepoSnapshotSummary,
+}
+
+/// The counts of a repository at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoSnapshotSummary {
+    pub at: DateTime<Utc>,
+    pub stars: u64,
+    pub forks: u64,
+    /// Users that watch the repository
+    pub watchers: u64,
+    /// Open issues and pull requests
+    pub open_issues: u64,
+}
+
+/// A user with access to a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollaboratorSummary {
+    pub login: String,
+    /// The highest permission they have, also through teams and the organization
+    pub permission: Permission,
+}
+
+/// A team with access to a repository.
+#[derive(Debug, Clone)]
+pub struct RepoTeamSummary {
+    /// As used in mentions, like `compiler` in `@rust-lang/compiler`
+    pub slug: String,
+    pub name: String,
+    pub permission: Permission,
+    /// Logins, sorted
+    pub members: Vec<String>,
+}
+
+/// A user, with the details from their profile once it's fetched.
+#[derive(Debug, Clone)]
+pub struct UserSummary {
+    pub id: u64,
+    pub login: String,
+    /// The name from the profile, `login` if there is none
+    pub name: String,
+    /// None if not known yet
+    pub kind: Option<UserKind>,
+    pub company: Option<String>,
+    pub location: Option<String>,
+    /// None if the profile wasn't fetched yet
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl UserSummary {
+    pub fn is_bot(&self) -> bool {
+        self.kind == Some(UserKind::Bot)
+    }
+}
+
+impl GithubDb {
+    /// Open pull requests by `author`, newest first.
+    pub async fn open_prs_by(&self, author: &str) -> Vec<PullRequestSummary> {
+        let author = author.to_string();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let pr = rows.join(PullRequest);
+                    rows.filter(pr.shared.author.name.eq(&author));
+                    rows.filter(pr.shared.state.eq(ItemState::Open.as_str()));
+                    rows.order_by()
+                        .desc(&pr.shared.created_timestamp)
+                        .into_iter(&pr)
+                        .collect::<Vec<_>>()
+                });
+                rows.into_iter().map(|pr| load_pr(txn, pr)).collect()
+            })
+            .await
+    }
+
+    /// Open pull requests that have a pending review request for `reviewer`, oldest first.
+    pub async fn prs_awaiting_review_from(&self, reviewer: &str) -> Vec<PullRequestSummary> {
+        let reviewer = reviewer.to_string();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let request = rows.join(ReviewRequest);
+                    rows.filter(request.user.name.eq(&reviewer));
+                    rows.filter(request.outdated.eq(0));
+                    rows.filter(request.pr.shared.state.eq(ItemState::Open.as_str()));
+                    rows.order_by()
+                        .asc(&request.pr.shared.created_timestamp)
+                        .into_iter(&request.pr)
+                        .collect::<Vec<_>>()
+                });
+                rows.into_iter().map(|pr| load_pr(txn, pr)).collect()
+            })
+            .await
+    }
+
+    /// Issues, open or closed, that currently have the label `label`, newest first.
+    pub async fn issues_with_label(&self, label: &str) -> Vec<ItemSummary> {
+        let label = label.to_string();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let link = rows.join(LabelLink);
+                    rows.filter(link.label.name.eq(&label));
+                    rows.filter(link.outdated.eq(0));
+                    let issue = rows.join(Issue);
+                    rows.filter(issue.shared.eq(&link.issue_or_pr));
+                    rows.order_by()
+                        .desc(&link.issue_or_pr.created_timestamp)
+                        .into_iter(&link.issue_or_pr)
+                        .collect::<Vec<_>>()
+                });
+                rows.into_iter()
+                    // pull requests are also stored as issues
+                    .filter(|row| txn.query_one(PullRequest.shared(*row)).is_none())
+                    .map(|row| ItemSummary::load(txn, row))
+                    .collect()
+            })
+            .await
+    }
+
+    /// A pull request by its number, None if it isn't in the database (yet).
+    pub async fn pull_request(&self, repo: &Repo, number: u64) -> Option<PullRequestSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                let shared = find_shared(txn, &repo, number)?;
+                let pr = txn.query_one(schema::PullRequest.shared(shared))?;
+                Some(load_pr(txn, pr))
+            })
+            .await
+    }
+
+    /// The comments on an issue or pull request in the order they were written,
+    /// None if the issue or pull request isn't in the database (yet).
+    pub async fn comment_thread(&self, repo: &Repo, number: u64) -> Option<Vec<CommentSummary>> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let shared = find_shared(txn, &repo, number)?;
+                let comments = txn.query(|rows| {
+                    let comment = rows.join(Comment);
+                    rows.filter(comment.issue_or_pr.eq(shared));
+                    rows.order_by()
+                        .asc(&comment.created_timestamp)
+                        .asc(&comment.comment_id)
+                        .into_iter(&comment)
+                        .collect::<Vec<_>>()
+                });
+                Some(
+                    comments
+                        .into_iter()
+                        .map(|row| {
+                            let comment = txn.lazy(row);
+                            CommentSummary {
+                                id: comment.comment_id as u64,
+                                author: comment.author.name.clone(),
+                                author_association: comment
+                                    .author_association
+                                    .parse()
+                                    .unwrap_or(AuthorAssociation::Unknown),
+                                html_url: comment.html_url.clone(),
+                                text: comment.text.clone(),
+                                created_at: timestamp(comment.created_timestamp),
+                                updated_at: timestamp(comment.updated_timestamp),
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .await
+    }
+
+    /// When an issue or pull request was closed, reopened and merged, oldest first,
+    /// None if the issue or pull request isn't in the database (yet).
+    pub async fn state_history(
+        &self,
+        repo: &Repo,
+        number: u64,
+    ) -> Option<Vec<StateTransitionSummary>> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let shared = find_shared(txn, &repo, number)?;
+                let transitions = txn.query(|rows| {
+                    let transition = rows.join(StateTransition);
+                    rows.filter(transition.issue_or_pr.eq(shared));
+                    rows.order_by()
+                        .asc(&transition.timestamp)
+                        .asc(&transition.kind)
+                        .into_iter(&transition)
+                        .collect::<Vec<_>>()
+                });
+                Some(
+                    transitions
+                        .into_iter()
+                        .filter_map(|row| {
+                            let transition = txn.lazy(row);
+                            Some(StateTransitionSummary {
+                                kind: transition.kind.parse().ok()?,
+                                actor: transition.actor.as_ref().map(|user| user.name.clone()),
+                                at: timestamp(transition.timestamp),
+                            })
+                        })
+                        .collect(),
+                )
+            })
+            .await
+    }
+
+    /// Where `name`, a login or `org/team` in any case, was mentioned, newest first.
+    /// Mentions in code and quotes don't count.
+    pub async fn mentions_of(&self, name: &str) -> Vec<MentionSummary> {
+        let name = name.to_lowercase();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let mention = rows.join(Mention);
+                    rows.filter(mention.name.eq(&name));
+                    rows.into_vec(mention)
+                });
+                let mut mentions: Vec<_> = rows
+                    .into_iter()
+                    .map(|row| {
+                        let mention = txn.lazy(row);
+                        let shared = &mention.issue_or_pr;
+                        match &mention.comment {
+                            Some(comment) => MentionSummary {
+                                number: shared.number as u64,
+                                comment_id: Some(comment.comment_id as u64),
+                                author: comment.author.name.clone(),
+                                at: timestamp(comment.created_timestamp),
+                            },
+                            None => MentionSummary {
+                                number: shared.number as u64,
+                                comment_id: None,
+                                author: shared.author.name.clone(),
+                                at: timestamp(shared.created_timestamp),
+                            },
+                        }
+                    })
+                    .collect();
+                mentions.sort_by_key(|mention| std::cmp::Reverse(mention.at));
+                mentions
+            })
+            .await
+    }
+
+    /// The discussions of `repo`, most recently updated first.
+    pub async fn discussions(&self, repo: &Repo) -> Vec<DiscussionSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let discussion = rows.join(Discussion);
+                    rows.filter(discussion.repo.organization.eq(&repo.organization));
+                    rows.filter(discussion.repo.name.eq(&repo.name));
+                    rows.order_by()
+                        .desc(&discussion.updated_timestamp)
+                        .desc(&discussion.number)
+                        .into_iter(&discussion)
+                        .collect::<Vec<_>>()
+                });
+                rows.into_iter()
+                    .map(|row| {
+                        let discussion = txn.lazy(row);
+                        DiscussionSummary {
+                            number: discussion.number as u64,
+                            title: discussion.title.clone(),
+                            category: discussion.category.name.clone(),
+                            author: discussion.author.name.clone(),
+                            author_association: discussion
+                                .author_association
+                                .parse()
+                                .unwrap_or(AuthorAssociation::Unknown),
+                            html_url: discussion.html_url.clone(),
+                            closed_at: discussion.closed_at_timestamp.map(timestamp),
+                            locked: discussion.locked != 0,
+                            answer_chosen_at: discussion.answer_chosen_timestamp.map(timestamp),
+                            created_at: timestamp(discussion.created_timestamp),
+                            updated_at: timestamp(discussion.updated_timestamp),
+                        }
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// The comments and replies on a discussion in the order they were written,
+    /// None if the discussion isn't in the database (yet).
+    pub async fn discussion_thread(
+        &self,
+        repo: &Repo,
+        number: u64,
+    ) -> Option<Vec<DiscussionCommentSummary>> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let discussion = txn.query(|rows| {
+                    let discussion = rows.join(Discussion);
+                    rows.filter(discussion.repo.organization.eq(&repo.organization));
+                    rows.filter(discussion.repo.name.eq(&repo.name));
+                    rows.filter(discussion.number.eq(number as i64));
+                    rows.into_iter(&discussion).next()
+                })?;
+                let comments = txn.query(|rows| {
+                    let comment = rows.join(DiscussionComment);
+                    rows.filter(comment.discussion.eq(discussion));
+                    rows.order_by()
+                        .asc(&comment.created_timestamp)
+                        .asc(&comment.node_id)
+                        .into_iter(&comment)
+                        .collect::<Vec<_>>()
+                });
+                Some(
+                    comments
+                        .into_iter()
+                        .map(|row| {
+                            let comment = txn.lazy(row);
+                            DiscussionCommentSummary {
+                                node_id: comment.node_id.clone(),
+                                reply_to: comment
+                                    .parent
+                                    .as_ref()
+                                    .map(|parent| parent.node_id.clone()),
+                                author: comment.author.name.clone(),
+                                author_association: comment
+                                    .author_association
+                                    .parse()
+                                    .unwrap_or(AuthorAssociation::Unknown),
+                                html_url: comment.html_url.clone(),
+                                body: comment.body.clone(),
+                                is_answer: comment.is_answer != 0,
+                                created_at: timestamp(comment.created_timestamp),
+                                updated_at: timestamp(comment.updated_timestamp),
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .await
+    }
+
+    /// The releases of `repo`, drafts included, most recently created first.
+    pub async fn releases(&self, repo: &Repo) -> Vec<ReleaseSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let rows = txn.query(|rows| {
+                    let release = rows.join(Release);
+                    rows.filter(release.repo.organization.eq(&repo.organization));
+                    rows.filter(release.repo.name.eq(&repo.name));
+                    rows.order_by()
+                        .desc(&release.created_timestamp)
+                        .desc(&release.release_id)
+                        .into_iter(&release)
+                        .collect::<Vec<_>>()
+                });
+                rows.into_iter().map(|row| load_release(txn, row)).collect()
+            })
+            .await
+    }
+
+    /// The metadata of a repository, None if it wasn't fetched yet.
+    pub async fn repository(&self, repo: &Repo) -> Option<RepositorySummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let repo_row =
+                    txn.query_one(Repo.organization(&repo.organization).name(&repo.name))?;
+                let metadata = txn.lazy(txn.query_one(RepoMetadata.repo(repo_row))?);
+                let topics = txn.query(|rows| {
+                    let topic = rows.join(RepoTopic);
+                    rows.filter(topic.repo.eq(repo_row));
+                    rows.order_by()
+                        .asc(&topic.topic)
+                        .into_iter(&topic.topic)
+                        .collect()
+                });
+                let counts = repo_snapshots(txn, repo_row).pop()?;
+
+                Some(RepositorySummary {
+                    description: metadata.description.clone(),
+                    default_branch: metadata.default_branch.clone(),
+                    topics,
+                    visibility: metadata.visibility.clone(),
+                    archived: metadata.archived != 0,
+                    synced_at: timestamp(metadata.synced_timestamp),
+                    counts,
+                })
+            })
+            .await
+    }
+
+    /// The counts of a repository every time its metadata was fetched, oldest first,
+    /// see [`GithubDb::with_repo_refresh_interval`].
+    pub async fn repo_history(&self, repo: &Repo) -> Vec<RepoSnapshotSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                match txn.query_one(Repo.organization(&repo.organization).name(&repo.name)) {
+                    Some(repo_row) => repo_snapshots(txn, repo_row),
+                    None => Vec::new(),
+                }
+            })
+            .await
+    }
+
+    /// The users with at least permission `at_least` on a repository, like everyone who
+    /// can triage. Most access first, then by login.
+    pub async fn collaborators(
+        &self,
+        repo: &Repo,
+        at_least: Permission,
+    ) -> Vec<CollaboratorSummary> {
+        let repo = repo.clone();
+        let mut collaborators: Vec<_> = self
+            .db
+            .transaction(move |txn| {
+                use schema::*;
+
+                txn.query(|rows| {
+                    let collaborator = rows.join(Collaborator);
+                    rows.filter(collaborator.repo.organization.eq(&repo.organization));
+                    rows.filter(collaborator.repo.name.eq(&repo.name));
+                    rows.into_vec((&collaborator.user.name, &collaborator.permission))
+                })
+            })
+            .await
+            .into_iter()
+            .filter_map(|(login, permission)| {
+                let permission = permission.parse().ok()?;
+                (permission >= at_least).then_some(CollaboratorSummary { login, permission })
+            })
+            .collect();
+        collaborators.sort_by(|a, b| b.permission.cmp(&a.permission).then(a.login.cmp(&b.login)));
+        collaborators
+    }
+
+    /// The permission of a user on a repository, None if they have no access or
+    /// it wasn't synced yet.
+    ///
+    /// The highest of their permission as a collaborator, and those of the teams with
+    /// access that they're in.
+    pub async fn permission_of(&self, repo: &Repo, login: &str) -> Option<Permission> {
+        let repo = repo.clone();
+        let login = login.to_string();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let repo_row =
+                    txn.query_one(Repo.organization(&repo.organization).name(&repo.name))?;
+                let direct = txn.query(|rows| {
+                    let collaborator = rows.join(Collaborator);
+                    rows.filter(collaborator.repo.eq(repo_row));
+                    rows.filter(collaborator.user.name.eq(&login));
+                    rows.into_vec(&collaborator.permission)
+                });
+                let through_teams = txn.query(|rows| {
+                    let member = rows.join(TeamMember);
+                    rows.filter(member.user.name.eq(&login));
+                    let repo_team = rows.join(RepoTeam);
+                    rows.filter(repo_team.repo.eq(repo_row));
+                    rows.filter(repo_team.team.eq(&member.team));
+                    rows.into_vec(&repo_team.permission)
+                });
+                direct
+                    .iter()
+                    .chain(&through_teams)
+                    .filter_map(|permission| permission.parse().ok())
+                    .max()
+            })
+            .await
+    }
+
+    /// The teams with access to a repository, with their members. Sorted by slug.
+    pub async fn repo_teams(&self, repo: &Repo) -> Vec<RepoTeamSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let teams = txn.query(|rows| {
+                    let repo_team = rows.join(RepoTeam);
+                    rows.filter(repo_team.repo.organization.eq(&repo.organization));
+                    rows.filter(repo_team.repo.name.eq(&repo.name));
+                    rows.order_by()
+                        .asc(&repo_team.team.slug)
+                        .into_iter((
+                            &repo_team.team,
+                            (
+                                &repo_team.team.slug,
+                                (&repo_team.name, &repo_team.permission),
+                            ),
+                        ))
+                        .collect::<Vec<_>>()
+                });
+                teams
+                    .into_iter()
+                    .map(|(team, (slug, (name, permission)))| RepoTeamSummary {
+                        slug,
+                        name,
+                        permission: permission.parse().unwrap_or(Permission::Read),
+                        members: team_members(txn, team),
+                    })
+                    .collect()
+            })
+            .await
+    }
+
+    /// The logins of the members of a team, like one that was mentioned, sorted.
+    /// Only the members of teams with access to one of the synced repositories are known.
+    pub async fn team_members(&self, organization: &str, slug: &str) -> Vec<String> {
+        let organization = organization.to_string();
+        let slug = slug.to_string();
+        self.db
+            .transaction(move |txn| {
+                match txn.query_one(schema::Team.organization(&organization).slug(&slug)) {
+                    Some(team) => team_members(txn, team),
+                    None => Vec::new(),
+                }
+            })
+            .await
+    }
+
+    /// The first published release that includes a merged pull request,
+    /// None if it isn't merged or wasn't released yet.
+    ///
+    /// Commit history isn't synced, so this is the release tagged at the merge commit if
+    /// there is one, otherwise the first one published after the merge. That assumes
+    /// releases are cut from the branch the pull request was merged into.
+    pub async fn first_release_with(&self, repo: &Repo, number: u64) -> Option<ReleaseSummary> {
+        let repo = repo.clone();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let shared = find_shared(txn, &repo, number)?;
+                let pr = txn.lazy(txn.query_one(PullRequest.shared(shared))?);
+                let merged_at = pr.merged_at_timestamp?;
+                let merge_commit_sha = pr.merge_commit_sha.clone();
+                let repo_row = pr.shared.repo.table_row();
+
+                let published = txn.query(|rows| {
+                    let release = rows.join(Release);
+                    rows.filter(release.repo.eq(repo_row));
+                    rows.filter(release.draft.eq(0));
+                    let published = rows.filter_some(&release.published_timestamp);
+                    rows.order_by()
+                        .asc(published)
+                        .into_iter((&release, &release.tag_name))
+                        .collect::<Vec<_>>()
+                });
+                let tagged_at_merge = |tag_name: &str| {
+                    txn.query_one(Tag.repo(repo_row).name(tag_name))
+                        .is_some_and(|tag| {
+                            Some(&txn.lazy(tag).commit_sha) == merge_commit_sha.as_ref()
+                        })
+                };
+                let row = published
+                    .iter()
+                    .find(|(_, tag_name)| tagged_at_merge(tag_name))
+                    .or_else(|| {
+                        published.iter().find(|(release, _)| {
+                            txn.lazy(*release)
+                                .published_timestamp
+                                .is_some_and(|published| published >= merged_at)
+                        })
+                    })?;
+                Some(load_release(txn, row.0))
+            })
+            .await
+    }
+
+    /// A user by their login, None if they aren't in the database (yet).
+    pub async fn user(&self, login: &str) -> Option<UserSummary> {
+        let login = login.to_string();
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                let row = txn.query(|rows| {
+                    let user = rows.join(User);
+                    rows.filter(user.name.eq(&login));
+                    rows.into_iter(&user).next()
+                })?;
+                let user = txn.lazy(row);
+                Some(UserSummary {
+                    id: user.github_id as u64,
+                    login: user.name.clone(),
+                    name: user.display_name.clone(),
+                    kind: user.kind.as_deref().and_then(|kind| kind.parse().ok()),
+                    company: user.company.clone(),
+                    location: user.location.clone(),
+                    created_at: user.account_created_timestamp.map(timestamp),
+                })
+            })
+            .await
+    }
+}
+
+fn find_shared(
+    txn: &Transaction<Schema>,
+    repo: &Repo,
+    number: u64,
+) -> Option<TableRow<schema::IssuePullRequestShared>> {
+    let row = txn.query_one(schema::IssuePullRequestShared.number(number as i64))?;
+    let stored = &txn.lazy(row).repo;
+    (stored.organization == repo.organization && stored.name == repo.name).then_some(row)
+}
+
+fn team_members(txn: &Transaction<Schema>, team: TableRow<schema::Team>) -> Vec<String> {
+    txn.query(|rows| {
+        let member = rows.join(schema::TeamMember);
+        rows.filter(member.team.eq(team));
+        rows.order_by()
+            .asc(&member.user.name)
+            .into_iter(&member.user.name)
+            .collect()
+    })
+}
+
+fn repo_snapshots(
+    txn: &Transaction<Schema>,
+    repo: TableRow<schema::Repo>,
+) -> Vec<RepoSnapshotSummary> {
+    let snapshots = txn.query(|rows| {
+        let snapshot = rows.join(schema::RepoSnapshot);
+        rows.filter(snapshot.repo.eq(repo));
+        rows.order_by()
+            .asc(&snapshot.timestamp)
+            .into_iter(&snapshot)
+            .collect::<Vec<_>>()
+    });
+    snapshots
+        .into_iter()
+        .map(|row| {
+            let snapshot = txn.lazy(row);
+            RepoSnapshotSummary {
+                at: timestamp(snapshot.timestamp),
+                stars: snapshot.stars as u64,
+                forks: snapshot.forks as u64,
+                watchers: snapshot.watchers as u64,
+                open_issues: snapshot.open_issues as u64,
+            }
+        })
+        .collect()
+}
+
+fn load_release(txn: &Transaction<Schema>, row: TableRow<schema::Release>) -> ReleaseSummary {
+    let release = txn.lazy(row);
+    let tag = txn.query_one(
+        schema::Tag
+            .repo(release.repo.table_row())
+            .name(&release.tag_name),
+    );
+
+    ReleaseSummary {
+        tag_name: release.tag_name.clone(),
+        name: release.name.clone(),
+        author: release.author.name.clone(),
+        html_url: release.html_url.clone(),
+        draft: release.draft != 0,
+        prerelease: release.prerelease != 0,
+        created_at: timestamp(release.created_timestamp),
+        published_at: release.published_timestamp.map(timestamp),
+        commit_sha: tag.map(|tag| txn.lazy(tag).commit_sha.clone()),
+    }
+}
+
+fn load_pr(txn: &Transaction<Schema>, row: TableRow<schema::PullRequest>) -> PullRequestSummary {
+    use schema::*;
+
+    let requested_reviewers = txn.query(|rows| {
+        let request = rows.join(ReviewRequest);
+        rows.filter(request.pr.eq(row));
+        rows.filter(request.outdated.eq(0));
+        rows.order_by()
+            .asc(&request.user.name)
+            .into_iter(&request.user.name)
+            .collect::<Vec<_>>()
+    });
+    let pr = txn.lazy(row);
+
+    PullRequestSummary {
+        item: ItemSummary::load(txn, pr.shared.table_row()),
+        draft: pr.draft != 0,
+        merged_at: pr.merged_at_timestamp.map(timestamp),
+        mergeable_state: pr
+            .mergeable_state
+            .parse()
+            .unwrap_or(MergeableState::Unknown),
+        additions: pr.num_additions as u64,
+        deletions: pr.num_deletions as u64,
+        changed_files: pr.num_changed_files as u64,
+        commits: pr.num_commits as u64,
+        requested_reviewers,
+    }
+}
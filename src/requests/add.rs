@@ -53,4 +53,33 @@ impl GithubDb {
         )
         .await;
     }
+
+    /// Queue a [`Request::DiscussionComments`] for each `(node_id, replies, cursor)` of
+    /// the comments of discussion `number` that didn't fit in a listing, unless nothing
+    /// changed on it.
+    pub(crate) async fn add_discussion_comments_reqs(
+        &self,
+        status: ProcessStatus,
+        repo: Repo,
+        number: u64,
+        more: Vec<(String, bool, String)>,
+    ) {
+        if let ProcessStatus::Unchanged = status {
+            return;
+        }
+        for (node_id, replies, cursor) in more {
+            self.add_req(
+                Priority::Comments,
+                Request::DiscussionComments {
+                    repo: repo.clone(),
+                    discussion_number: number,
+                    node_id,
+                    replies,
+                    page: 1,
+                    url: Some(cursor),
+                },
+            )
+            .await;
+        }
+    }
 }
@@ -1,32 +1,21 @@
 use crate::{
     GithubDb, ProcessStatus, Repo,
+    forge::ForgePage,
+    progress::Listing,
     requests::{ListType, Priority, Request},
 };
-use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
-use http::Uri;
-use octocrab::{Page, params::Direction};
 
 macro_rules! build_request {
-    ($_self: tt, $url: ident, $repo: ident $($other_args: ident)*) => {
+    ($_self: tt, $repo: ident $($other_args: ident)*) => {
         macro_rules! request {
             ($e: expr) => {{
-                let page: Result<Page<_>, _> = if let Some(page) = $url
-                    && let Ok(i) = Uri::from_str(&page)
-                {
-                    let Some(page) = $_self.octocrab().await.get_page(&Some(i)).await.transpose() else {
-                        return;
-                    };
-                    page
-                } else {
-                    $e
-                };
-
-                match page {
-                    Ok(mut i) => (i.take_items(), i.next),
+                match $e {
+                    Ok(page) => page,
                     Err(e) => {
                         tracing::error!("{e:?}");
+                        $_self.metrics.request_failed();
                         return;
                     }
                 }
@@ -37,10 +26,11 @@ macro_rules! build_request {
             ($items: ident, $method: ident) => {{
                 let mut any_updated = false;
                 for issue in $items {
-                    if !matches!(
-                        $_self.$method($repo.clone(), issue, $($other_args),*).await,
-                        ProcessStatus::Unchanged
-                    ) {
+                    let status = $_self.$method($repo.clone(), issue, $($other_args),*).await;
+                    $_self
+                        .metrics
+                        .processed(stringify!($method).trim_start_matches("process_"), status);
+                    if !matches!(status, ProcessStatus::Unchanged) {
                         any_updated = true;
                     }
                 }
@@ -58,26 +48,29 @@ impl GithubDb {
         url: Option<String>,
         list_type: ListType,
     ) {
-        build_request!(self, url, repo);
-        let (items, next) = request!(
-            self.octocrab()
-                .await
-                .pulls(&repo.organization, &repo.name)
-                .list()
-                .sort(octocrab::params::pulls::Sort::Updated)
-                .direction(match list_type {
-                    ListType::New => Direction::Descending,
-                    ListType::Old => Direction::Ascending,
-                })
-                .state(octocrab::params::State::All)
-                .page(page_num as u32)
-                .per_page(100)
-                .send()
+        build_request!(self, repo);
+        let ForgePage {
+            items,
+            next,
+            last_page,
+        } = request!(
+            self.forge
+                .list_prs(&repo, list_type, page_num, url.as_deref())
                 .await
         );
 
         tracing::debug!("processing {} {list_type} pulls", items.len());
         let any_updated = iter!(items, process_pr);
+        if list_type == ListType::Old {
+            self.record_listing_progress(
+                &repo,
+                Listing::Pulls,
+                page_num,
+                next.is_some(),
+                last_page,
+            )
+            .await;
+        }
         let next_page_num = if next.is_some() { page_num + 1 } else { 0 };
 
         match (list_type, any_updated) {
@@ -87,7 +80,7 @@ impl GithubDb {
                     Request::NewPr {
                         repo,
                         page: next_page_num,
-                        url: next.map(|i| i.to_string()),
+                        url: next,
                     },
                 )
                 .await;
@@ -102,7 +95,7 @@ impl GithubDb {
                     Request::OldPr {
                         repo,
                         page: next_page_num,
-                        url: next.map(|i| i.to_string()),
+                        url: next,
                     },
                 )
                 .await;
@@ -118,26 +111,29 @@ impl GithubDb {
         url: Option<String>,
         list_type: ListType,
     ) {
-        build_request!(self, url, repo);
-        let (items, next) = request!(
-            self.octocrab()
-                .await
-                .issues(&repo.organization, &repo.name)
-                .list()
-                .sort(octocrab::params::issues::Sort::Updated)
-                .direction(match list_type {
-                    ListType::New => Direction::Descending,
-                    ListType::Old => Direction::Ascending,
-                })
-                .state(octocrab::params::State::All)
-                .page(page_num as u32)
-                .per_page(100)
-                .send()
+        build_request!(self, repo);
+        let ForgePage {
+            items,
+            next,
+            last_page,
+        } = request!(
+            self.forge
+                .list_issues(&repo, list_type, page_num, url.as_deref())
                 .await
         );
 
         tracing::debug!("processing {} {list_type} issues", items.len());
         let any_updated = iter!(items, process_issue);
+        if list_type == ListType::Old {
+            self.record_listing_progress(
+                &repo,
+                Listing::Issues,
+                page_num,
+                next.is_some(),
+                last_page,
+            )
+            .await;
+        }
 
         let next_page_num = if next.is_some() { page_num + 1 } else { 0 };
 
@@ -148,7 +144,7 @@ impl GithubDb {
                     Request::NewIssue {
                         repo,
                         page: next_page_num,
-                        url: next.map(|i| i.to_string()),
+                        url: next,
                     },
                 )
                 .await;
@@ -163,7 +159,7 @@ impl GithubDb {
                     Request::OldIssue {
                         repo,
                         page: next_page_num,
-                        url: next.map(|i| i.to_string()),
+                        url: next,
                     },
                 )
                 .await;
@@ -172,29 +168,272 @@ impl GithubDb {
         }
     }
 
-    async fn handle_list_comments(
+    async fn handle_list_discussions(
         &self,
         repo: Repo,
-        issue_number: u64,
-        since_timestamp: Option<i64>,
         page_num: usize,
         url: Option<String>,
+        list_type: ListType,
     ) {
-        build_request!(self, url, repo issue_number);
-        let (items, next) = request!({
-            let octocrab = self.octocrab().await;
-            let comments = octocrab.issues(&repo.organization, &repo.name);
-            let mut comments = comments.list_comments(issue_number);
+        build_request!(self, repo);
+        let ForgePage {
+            items,
+            next,
+            last_page,
+        } = request!(
+            self.forge
+                .list_discussions(&repo, list_type, page_num, url.as_deref())
+                .await
+        );
+
+        tracing::debug!("processing {} {list_type} discussions", items.len());
+        let any_updated = iter!(items, process_discussion);
+        if list_type == ListType::Old {
+            self.record_listing_progress(
+                &repo,
+                Listing::Discussions,
+                page_num,
+                next.is_some(),
+                last_page,
+            )
+            .await;
+        }
 
-            if let Some(since) = since_timestamp
-                && let Some(stamp) = DateTime::<Utc>::from_timestamp_secs(since - 100)
-            {
-                // - 100 for some leaway
-                comments = comments.since(stamp);
+        let next_page_num = if next.is_some() { page_num + 1 } else { 0 };
+
+        match (list_type, any_updated) {
+            (ListType::New, true) => {
+                self.add_req(
+                    Priority::Update,
+                    Request::NewDiscussion {
+                        repo,
+                        page: next_page_num,
+                        url: next,
+                    },
+                )
+                .await;
             }
+            (ListType::Old, updated) => {
+                self.add_req(
+                    if updated {
+                        Priority::Update
+                    } else {
+                        Priority::Index
+                    },
+                    Request::OldDiscussion {
+                        repo,
+                        page: next_page_num,
+                        url: next,
+                    },
+                )
+                .await;
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_list_releases(&self, repo: Repo, page_num: usize, url: Option<String>) {
+        build_request!(self, repo);
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_releases(&repo, page_num, url.as_deref())
+                .await
+        );
 
-            comments.page(page_num as u32).per_page(100).send().await
-        });
+        tracing::debug!("processing {} releases", items.len());
+        let any_updated = iter!(items, process_release);
+
+        if any_updated && let Some(next) = next {
+            self.add_req(
+                Priority::Index,
+                Request::Releases {
+                    repo,
+                    page: page_num + 1,
+                    url: Some(next),
+                },
+            )
+            .await;
+        }
+    }
+
+    async fn handle_list_tags(&self, repo: Repo, page_num: usize, url: Option<String>) {
+        build_request!(self, repo);
+        let ForgePage { items, next, .. } =
+            request!(self.forge.list_tags(&repo, page_num, url.as_deref()).await);
+
+        tracing::debug!("processing {} tags", items.len());
+        iter!(items, process_tag);
+
+        // tags are ordered by name, so changes can be on any page
+        if let Some(next) = next {
+            self.add_req(
+                Priority::Index,
+                Request::Tags {
+                    repo,
+                    page: page_num + 1,
+                    url: Some(next),
+                },
+            )
+            .await;
+        }
+    }
+
+    async fn handle_repository(&self, repo: Repo) {
+        let repository = match self.forge.repository(&repo).await {
+            Ok(repository) => repository,
+            Err(e) => {
+                tracing::error!("{e:?}");
+                self.metrics.request_failed();
+                return;
+            }
+        };
+
+        let status = self.process_repository(repo, repository).await;
+        self.metrics.processed("repository", status);
+    }
+
+    async fn handle_list_collaborators(
+        &self,
+        repo: Repo,
+        started_timestamp: i64,
+        page_num: usize,
+        url: Option<String>,
+    ) {
+        build_request!(self, repo);
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_collaborators(&repo, page_num, url.as_deref())
+                .await
+        );
+
+        tracing::debug!("processing {} collaborators", items.len());
+        iter!(items, process_collaborator);
+
+        match next {
+            Some(next) => {
+                self.add_req(
+                    Priority::Index,
+                    Request::Collaborators {
+                        repo,
+                        started_timestamp,
+                        page: page_num + 1,
+                        url: Some(next),
+                    },
+                )
+                .await;
+            }
+            None => self.forget_collaborators(repo, started_timestamp).await,
+        }
+    }
+
+    async fn handle_list_repo_teams(
+        &self,
+        repo: Repo,
+        started_timestamp: i64,
+        page_num: usize,
+        url: Option<String>,
+    ) {
+        build_request!(self, repo);
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_repo_teams(&repo, page_num, url.as_deref())
+                .await
+        );
+
+        tracing::debug!("processing {} teams", items.len());
+        let slugs: Vec<_> = items.iter().map(|team| team.slug.clone()).collect();
+        iter!(items, process_repo_team);
+        for slug in slugs {
+            self.add_req(
+                Priority::Index,
+                Request::TeamMembers {
+                    organization: repo.organization.clone(),
+                    slug,
+                    started_timestamp,
+                    page: 0,
+                    url: None,
+                },
+            )
+            .await;
+        }
+
+        match next {
+            Some(next) => {
+                self.add_req(
+                    Priority::Index,
+                    Request::RepoTeams {
+                        repo,
+                        started_timestamp,
+                        page: page_num + 1,
+                        url: Some(next),
+                    },
+                )
+                .await;
+            }
+            None => self.forget_repo_teams(repo, started_timestamp).await,
+        }
+    }
+
+    async fn handle_list_team_members(
+        &self,
+        organization: String,
+        slug: String,
+        started_timestamp: i64,
+        page_num: usize,
+        url: Option<String>,
+    ) {
+        let slug_ref = slug.as_str();
+        build_request!(self, organization slug_ref);
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_team_members(&organization, &slug, page_num, url.as_deref())
+                .await
+        );
+
+        tracing::debug!(
+            "processing {} members of {organization}/{slug}",
+            items.len()
+        );
+        iter!(items, process_team_member);
+
+        match next {
+            Some(next) => {
+                self.add_req(
+                    Priority::Index,
+                    Request::TeamMembers {
+                        organization,
+                        slug,
+                        started_timestamp,
+                        page: page_num + 1,
+                        url: Some(next),
+                    },
+                )
+                .await;
+            }
+            None => {
+                self.forget_team_members(organization, slug, started_timestamp)
+                    .await
+            }
+        }
+    }
+
+    async fn handle_list_comments(
+        &self,
+        repo: Repo,
+        issue_number: u64,
+        since_timestamp: Option<i64>,
+        page_num: usize,
+        url: Option<String>,
+    ) {
+        build_request!(self, repo issue_number);
+        // - 100 for some leaway
+        let since =
+            since_timestamp.and_then(|since| DateTime::<Utc>::from_timestamp_secs(since - 100));
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_comments(&repo, issue_number, since, page_num, url.as_deref())
+                .await
+        );
 
         tracing::debug!("processing {} comments", items.len());
         let any_updated = iter!(items, process_comment);
@@ -207,16 +446,69 @@ impl GithubDb {
                     issue_number,
                     since_timestamp,
                     page: page_num + 1,
-                    url: Some(next.to_string()),
+                    url: Some(next),
+                },
+            )
+            .await;
+        } else {
+            self.comments_synced(issue_number).await;
+        }
+    }
+
+    async fn handle_list_discussion_comments(
+        &self,
+        repo: Repo,
+        discussion_number: u64,
+        node_id: String,
+        replies: bool,
+        page_num: usize,
+        url: Option<String>,
+    ) {
+        let parent = replies.then_some(node_id.as_str());
+        build_request!(self, repo discussion_number parent);
+        let ForgePage { items, next, .. } = request!(
+            self.forge
+                .list_discussion_comments(&node_id, page_num, url.as_deref())
+                .await
+        );
+
+        tracing::debug!("processing {} discussion comments", items.len());
+        iter!(items, process_discussion_comment);
+
+        if let Some(next) = next {
+            self.add_req(
+                Priority::Comments,
+                Request::DiscussionComments {
+                    repo,
+                    discussion_number,
+                    node_id,
+                    replies,
+                    page: page_num + 1,
+                    url: Some(next),
                 },
             )
             .await;
         }
     }
 
+    async fn handle_user_profile(&self, user_id: u64) {
+        let profile = match self.forge.user_profile(user_id).await {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                tracing::error!("{e:?}");
+                self.metrics.request_failed();
+                None
+            }
+        };
+        // also when it failed, so a deleted account doesn't stop the walk
+        self.store_profile(user_id, profile).await;
+        self.queue_next_profile(true).await;
+    }
+
     pub async fn handle_request(&self, r: Request) {
         tracing::debug!("{r:?}");
         tracing::info!("handling request {}", r.name());
+        self.metrics.request_handled(r.name());
         match r {
             Request::OldPr { repo, page, url } => {
                 self.handle_list_prs(repo, page, url, ListType::Old).await
@@ -232,6 +524,47 @@ impl GithubDb {
                 self.handle_list_issues(repo, page, url, ListType::New)
                     .await
             }
+            Request::OldDiscussion { repo, page, url } => {
+                self.handle_list_discussions(repo, page, url, ListType::Old)
+                    .await
+            }
+            Request::NewDiscussion { repo, page, url } => {
+                self.handle_list_discussions(repo, page, url, ListType::New)
+                    .await
+            }
+            Request::Releases { repo, page, url } => {
+                self.handle_list_releases(repo, page, url).await
+            }
+            Request::Tags { repo, page, url } => self.handle_list_tags(repo, page, url).await,
+            Request::Repository { repo } => self.handle_repository(repo).await,
+            Request::Collaborators {
+                repo,
+                started_timestamp,
+                page,
+                url,
+            } => {
+                self.handle_list_collaborators(repo, started_timestamp, page, url)
+                    .await
+            }
+            Request::RepoTeams {
+                repo,
+                started_timestamp,
+                page,
+                url,
+            } => {
+                self.handle_list_repo_teams(repo, started_timestamp, page, url)
+                    .await
+            }
+            Request::TeamMembers {
+                organization,
+                slug,
+                started_timestamp,
+                page,
+                url,
+            } => {
+                self.handle_list_team_members(organization, slug, started_timestamp, page, url)
+                    .await
+            }
             Request::Comments {
                 repo,
                 issue_number,
@@ -242,6 +575,25 @@ impl GithubDb {
                 self.handle_list_comments(repo, issue_number, since_timestamp, page, url)
                     .await
             }
+            Request::DiscussionComments {
+                repo,
+                discussion_number,
+                node_id,
+                replies,
+                page,
+                url,
+            } => {
+                self.handle_list_discussion_comments(
+                    repo,
+                    discussion_number,
+                    node_id,
+                    replies,
+                    page,
+                    url,
+                )
+                .await
+            }
+            Request::UserProfile { user_id } => self.handle_user_profile(user_id).await,
         }
     }
 }
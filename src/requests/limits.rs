@@ -8,10 +8,27 @@ use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 
 use crate::requests::Priority;
 
+/// What happened when [`RequestLimits`] asked for the next request in a category.
+pub enum Grant {
+    /// A request was started and uses up one unit of budget.
+    Started,
+    /// A GraphQL request was started, which uses up this many points of the separate
+    /// GraphQL budget instead.
+    StartedGraphql(f64),
+    /// There was nothing to do in this category.
+    Empty,
+    /// Too many requests are in flight already. Nothing is started and the
+    /// remaining budget is kept for the next update.
+    Saturated,
+}
+
 pub struct RequestLimits {
     global_limit: usize,
     category_limits: [(f64, Instant); Priority::ALL.len()],
     saved_up: f64,
+    /// Points for GraphQL requests, which GitHub budgets separately from REST requests
+    /// but with as many points per hour. Shared by all categories.
+    graphql_limit: (f64, Instant),
     measured_rps: ConstGenericRingBuffer<Instant, 4096>,
 }
 
@@ -24,6 +41,7 @@ impl Display for RequestLimits {
         }
 
         res.field("saved-up", &self.saved_up);
+        res.field("graphql", &self.graphql_limit.0);
 
         res.finish()
     }
@@ -37,6 +55,7 @@ impl RequestLimits {
             // .map(|i| (0.2 * limit as f64 * i.fraction(), Instant::now())),
             category_limits: Priority::ALL.map(|_| (0.0, Instant::now())),
             saved_up: 0.0,
+            graphql_limit: (0.0, Instant::now()),
             measured_rps: ConstGenericRingBuffer::new(),
         }
     }
@@ -54,13 +73,32 @@ impl RequestLimits {
         )
     }
 
-    pub async fn update(&mut self, next_request: impl AsyncFn(Priority) -> bool) {
+    /// The measured request rate, None until at least two requests were measured.
+    pub fn requests_per_hour(&self) -> Option<u64> {
+        (self.measured_rps.len() >= 2).then(|| {
+            let average = self.average_time_between_requests();
+            (3600 * 1000 / average.as_millis().max(1)) as u64
+        })
+    }
+
+    /// Start requests while there's budget for them. `next_request` is told whether
+    /// there are GraphQL points left, otherwise it shouldn't start GraphQL requests.
+    pub async fn update(&mut self, next_request: impl AsyncFn(Priority, bool) -> Grant) {
+        // The limits are per hour.
+        const LIMIT_DURATION: Duration = Duration::from_secs(3600);
+
         let mut saved_up = self.saved_up;
+        let mut saturated = false;
 
-        for category in Priority::ALL {
-            // The limit is in requests per hour.
-            const LIMIT_DURATION: Duration = Duration::from_secs(3600);
+        let now = Instant::now();
+        let (graphql_points, graphql_time) = &mut self.graphql_limit;
+        let elapsed = now.duration_since(*graphql_time);
+        *graphql_time = now;
+        *graphql_points +=
+            (elapsed.as_secs_f64() / LIMIT_DURATION.as_secs_f64()) * self.global_limit as f64;
+        *graphql_points = graphql_points.min(0.2 * self.global_limit as f64);
 
+        for category in Priority::ALL {
             let now = Instant::now();
             let (before_count, before_time) = &mut self.category_limits[category as usize];
             let elapsed = now.duration_since(*before_time);
@@ -73,18 +111,30 @@ impl RequestLimits {
             *before_count += new_requests_allowed + saved_up;
             saved_up = 0.0;
 
-            while *before_count >= 1.0 {
-                if next_request(category).await {
-                    self.measured_rps.enqueue(Instant::now());
-                    *before_count -= 1.0;
-                } else {
-                    break;
+            while !saturated && *before_count >= 1.0 {
+                match next_request(category, self.graphql_limit.0 > 0.0).await {
+                    Grant::Started => {
+                        self.measured_rps.enqueue(Instant::now());
+                        *before_count -= 1.0;
+                    }
+                    Grant::StartedGraphql(points) => {
+                        self.measured_rps.enqueue(Instant::now());
+                        self.graphql_limit.0 -= points;
+                    }
+                    Grant::Empty => break,
+                    Grant::Saturated => saturated = true,
                 }
             }
 
             let limit = 0.2 * self.global_limit as f64 * category.fraction();
             if *before_count >= limit {
-                saved_up = *before_count - limit;
+                // When saturated, lower priorities can't start anything either,
+                // so the budget stays with this category (up to its limit).
+                saved_up = if saturated {
+                    0.0
+                } else {
+                    *before_count - limit
+                };
                 *before_count = limit;
             }
         }
@@ -2,12 +2,17 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use crate::Repo;
+use crate::{
+    Repo,
+    forge::discussion::{COMMENTS_PAGE_POINTS, DISCUSSIONS_PAGE_POINTS},
+};
 
 pub mod add;
 pub mod handle;
 pub mod limits;
+pub mod queue;
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum ListType {
     New,
     Old,
@@ -22,7 +27,8 @@ impl Display for ListType {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// The category a request is budgeted under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Priority {
     // high prioriry, when things changed!
     Update = 0,
@@ -32,7 +38,15 @@ pub enum Priority {
 }
 
 impl Priority {
-    const ALL: [Priority; 3] = [Self::Update, Self::Comments, Self::Index];
+    pub(crate) const ALL: [Priority; 3] = [Self::Update, Self::Comments, Self::Index];
+
+    /// The category stored in the `Request` table.
+    pub(crate) fn from_discriminant(category: i64) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|p| *p as i64 == category)
+            .expect("unknown request category")
+    }
 
     fn fraction(&self) -> f64 {
         // must add to 1.0
@@ -44,6 +58,16 @@ impl Priority {
     }
 }
 
+/// The name of a request, and the repository, issue number, `(organization, slug)`
+/// of a team and GraphQL node id that it's about, see [`Request::listing`].
+pub(crate) type ListingKey<'a> = (
+    &'static str,
+    Option<&'a Repo>,
+    Option<u64>,
+    Option<(&'a str, &'a str)>,
+    Option<&'a str>,
+);
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     /// List oldest PRs. If an old PR page changed,
@@ -77,6 +101,65 @@ pub enum Request {
         page: usize,
         url: Option<String>,
     },
+    /// Like [`Request::NewIssue`] and [`Request::OldIssue`], for discussions.
+    /// Their first comments are listed with them, see [`Request::DiscussionComments`]
+    /// for the rest.
+    NewDiscussion {
+        repo: Repo,
+        page: usize,
+        url: Option<String>,
+    },
+    OldDiscussion {
+        repo: Repo,
+        page: usize,
+        url: Option<String>,
+    },
+    /// List releases, newest first. Like [`Request::NewPr`], the next page is only listed
+    /// if anything changed on this one, so the first sync walks them all.
+    ///
+    /// Issued at `Index` priority, see [`GithubDb::with_release_refresh_interval`](crate::GithubDb::with_release_refresh_interval).
+    Releases {
+        repo: Repo,
+        page: usize,
+        url: Option<String>,
+    },
+    /// Like [`Request::Releases`], for tags. These are ordered by name rather than by
+    /// date, so a new or moved tag can be on any page, and all pages are listed.
+    Tags {
+        repo: Repo,
+        page: usize,
+        url: Option<String>,
+    },
+    /// Fetch the metadata of a repository, and store a snapshot of its counts.
+    ///
+    /// Issued at `Index` priority, see [`GithubDb::with_repo_refresh_interval`](crate::GithubDb::with_repo_refresh_interval).
+    Repository { repo: Repo },
+    /// List the users with access to a repository. All pages are listed, and after the
+    /// last one the collaborators that weren't listed since `started_timestamp` are deleted.
+    ///
+    /// Issued at `Index` priority, see [`GithubDb::with_access_refresh_interval`](crate::GithubDb::with_access_refresh_interval).
+    Collaborators {
+        repo: Repo,
+        started_timestamp: i64,
+        page: usize,
+        url: Option<String>,
+    },
+    /// Like [`Request::Collaborators`], for the teams with access to a repository.
+    /// Queues a [`Request::TeamMembers`] for every team that's listed.
+    RepoTeams {
+        repo: Repo,
+        started_timestamp: i64,
+        page: usize,
+        url: Option<String>,
+    },
+    /// Like [`Request::Collaborators`], for the members of a team.
+    TeamMembers {
+        organization: String,
+        slug: String,
+        started_timestamp: i64,
+        page: usize,
+        url: Option<String>,
+    },
     Comments {
         repo: Repo,
         issue_number: u64,
@@ -84,15 +167,96 @@ pub enum Request {
         page: usize,
         url: Option<String>,
     },
+    /// List the comments of a discussion past those listed with it, or with `replies`,
+    /// the replies to a comment past those listed with the comment. `url` is the GraphQL
+    /// cursor to continue after. New comments are at the end, so all pages are listed.
+    ///
+    /// Issued at `Comments` priority when a discussion or comment with more changed.
+    DiscussionComments {
+        repo: Repo,
+        discussion_number: u64,
+        /// Of the discussion, or of the comment if `replies`
+        node_id: String,
+        replies: bool,
+        page: usize,
+        url: Option<String>,
+    },
+    /// Fetch the full profile of a user. Handling it queues the next user
+    /// without a profile, so only one of these is queued at a time.
+    ///
+    /// Issued at `Index` priority when users without a profile are found
+    /// and none is queued, see [`crate::users`].
+    UserProfile { user_id: u64 },
 }
 impl Request {
+    /// The [`name`](Request::name)s of the requests that go to the GraphQL api.
+    pub const GRAPHQL: [&'static str; 3] = ["NewDiscussion", "OldDiscussion", "DiscussionComments"];
+
     pub fn name(&self) -> &'static str {
         match self {
             Request::OldPr { .. } => "OldPr",
             Request::NewPr { .. } => "NewPr",
             Request::NewIssue { .. } => "NewIssue",
             Request::OldIssue { .. } => "OldIssue",
+            Request::NewDiscussion { .. } => "NewDiscussion",
+            Request::OldDiscussion { .. } => "OldDiscussion",
+            Request::Releases { .. } => "Releases",
+            Request::Tags { .. } => "Tags",
+            Request::Repository { .. } => "Repository",
+            Request::Collaborators { .. } => "Collaborators",
+            Request::RepoTeams { .. } => "RepoTeams",
+            Request::TeamMembers { .. } => "TeamMembers",
             Request::Comments { .. } => "Comments",
+            Request::DiscussionComments { .. } => "DiscussionComments",
+            Request::UserProfile { .. } => "UserProfile",
+        }
+    }
+
+    /// The points this costs from GitHub's GraphQL budget, None for REST requests.
+    pub fn graphql_points(&self) -> Option<f64> {
+        match self {
+            Request::NewDiscussion { .. } | Request::OldDiscussion { .. } => {
+                Some(DISCUSSIONS_PAGE_POINTS as f64)
+            }
+            Request::DiscussionComments { .. } => Some(COMMENTS_PAGE_POINTS as f64),
+            _ => None,
+        }
+    }
+
+    /// What this request pages through. Requests for the next page return the same.
+    pub(crate) fn listing(&self) -> ListingKey<'_> {
+        match self {
+            Request::OldPr { repo, .. }
+            | Request::NewPr { repo, .. }
+            | Request::NewIssue { repo, .. }
+            | Request::OldIssue { repo, .. }
+            | Request::NewDiscussion { repo, .. }
+            | Request::OldDiscussion { repo, .. }
+            | Request::Releases { repo, .. }
+            | Request::Tags { repo, .. }
+            | Request::Repository { repo }
+            | Request::Collaborators { repo, .. }
+            | Request::RepoTeams { repo, .. } => (self.name(), Some(repo), None, None, None),
+            Request::Comments {
+                repo, issue_number, ..
+            } => (self.name(), Some(repo), Some(*issue_number), None, None),
+            Request::DiscussionComments {
+                repo,
+                discussion_number,
+                node_id,
+                ..
+            } => (
+                self.name(),
+                Some(repo),
+                Some(*discussion_number),
+                None,
+                Some(node_id),
+            ),
+            Request::TeamMembers {
+                organization, slug, ..
+            } => (self.name(), None, None, Some((organization, slug)), None),
+            // one walk over all users
+            Request::UserProfile { .. } => (self.name(), None, None, None, None),
         }
     }
 }
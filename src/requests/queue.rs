@@ -0,0 +1,93 @@
+//! Inspecting and managing the queue of pending requests.
+
+use crate::{GithubDb, database::schema, requests::Priority};
+
+/// A request waiting in the queue, see [`GithubDb::queue`].
+#[derive(Debug, Clone)]
+pub struct QueuedRequest {
+    /// Within a category, requests are handled in the order of their sequence number
+    pub sequence_number: i64,
+    pub priority: Priority,
+    /// Kind of request, like `OldPr` or `Comments`
+    pub name: String,
+    /// The parameters of the request, as json
+    pub data: String,
+}
+
+impl GithubDb {
+    /// Up to `limit` queued requests, optionally only those of one category,
+    /// in the order they were queued.
+    pub async fn queue(&self, priority: Option<Priority>, limit: usize) -> Vec<QueuedRequest> {
+        self.db
+            .transaction(move |txn| {
+                use schema::*;
+
+                txn.query(|rows| {
+                    let request = rows.join(Request);
+                    if let Some(priority) = priority {
+                        rows.filter(request.category.eq(priority as i64));
+                    }
+                    rows.order_by()
+                        .asc(&request.sequence_number)
+                        .into_iter((
+                            &request.sequence_number,
+                            (&request.category, (&request.name, &request.data)),
+                        ))
+                        .take(limit)
+                        .map(
+                            |(sequence_number, (category, (name, data)))| QueuedRequest {
+                                sequence_number,
+                                priority: Priority::from_discriminant(category),
+                                name,
+                                data: String::from_utf8_lossy(&data).into_owned(),
+                            },
+                        )
+                        .collect()
+                })
+            })
+            .await
+    }
+
+    /// Remove one queued request, returns whether it was still queued.
+    pub async fn remove_queued(&self, sequence_number: i64) -> bool {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let Some(request) = txn.query_one(Request.sequence_number(sequence_number)) else {
+                    return false;
+                };
+                txn.downgrade()
+                    .delete(request)
+                    .expect("requests are not referenced")
+            })
+            .await
+    }
+
+    /// Remove all queued requests, or only those of one category.
+    /// Returns how many were removed.
+    ///
+    /// The walks over all old issues and pull requests are restarted
+    /// the next time the database is opened if their requests are gone.
+    pub async fn clear_queue(&self, priority: Option<Priority>) -> usize {
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let requests = txn.query(|rows| {
+                    let request = rows.join(Request);
+                    if let Some(priority) = priority {
+                        rows.filter(request.category.eq(priority as i64));
+                    }
+                    rows.into_vec(request)
+                });
+
+                let txn = txn.downgrade();
+                requests
+                    .into_iter()
+                    .filter(|request| txn.delete(*request).expect("requests are not referenced"))
+                    .count()
+            })
+            .await
+    }
+}
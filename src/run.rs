@@ -0,0 +1,110 @@
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use tokio::time::{Instant, Interval, MissedTickBehavior, interval, interval_at, timeout};
+
+use crate::GithubDb;
+
+/// How often [`GithubDb::run`] calls [`GithubDb::update`].
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What happened during a call to [`GithubDb::run`].
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    /// Number of times the scheduler was driven.
+    pub ticks: u64,
+    /// Request handlers that ran to completion.
+    pub requests_finished: u64,
+    /// Request handlers that panicked.
+    pub requests_panicked: u64,
+    /// Request handlers that were still running when the shutdown timeout expired.
+    pub requests_aborted: u64,
+    /// How long it took to drain the in-flight handlers after the shutdown signal.
+    pub shutdown_duration: Duration,
+    /// Snapshots that were written, see [`GithubDb::with_snapshots`].
+    pub snapshots: u64,
+}
+
+/// Never completes without an interval.
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+impl GithubDb {
+    /// Drive the scheduler until `shutdown` completes.
+    ///
+    /// After the shutdown signal, no new requests are started and requests that are
+    /// already running get [`GithubDb::with_shutdown_timeout`] to finish.
+    ///
+    /// Also writes the [snapshots](GithubDb::with_snapshots), if configured.
+    ///
+    /// ```no_run
+    /// # async fn f(gh: std::sync::Arc<github_db::GithubDb>) {
+    /// let summary = gh.run(async { tokio::signal::ctrl_c().await.unwrap() }).await;
+    /// # }
+    /// ```
+    pub async fn run(self: Arc<Self>, shutdown: impl Future<Output = ()>) -> RunSummary {
+        let finished_before = self.tasks_finished.load(Ordering::Relaxed);
+        let panicked_before = self.tasks_panicked.load(Ordering::Relaxed);
+
+        let mut summary = RunSummary::default();
+        let mut ticker = interval(UPDATE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut snapshots = self.snapshots.as_ref().map(|snapshots| {
+            let mut snapshots = interval_at(Instant::now() + snapshots.every, snapshots.every);
+            snapshots.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            snapshots
+        });
+
+        let mut shutdown = std::pin::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = ticker.tick() => {
+                    self.clone().update().await;
+                    summary.ticks += 1;
+                }
+                _ = tick(&mut snapshots) => {
+                    match self.snapshot().await {
+                        Ok(_) => summary.snapshots += 1,
+                        Err(e) => tracing::error!("couldn't write snapshot: {e}"),
+                    }
+                }
+            }
+        }
+
+        tracing::info!("shutting down, waiting for in-flight requests");
+        let start = tokio::time::Instant::now();
+        let mut tasks = self.tasks.lock().await;
+
+        let drained = timeout(self.shutdown_timeout, async {
+            while let Some(res) = tasks.join_next().await {
+                self.record_task_result(res);
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            summary.requests_aborted = tasks.len() as u64;
+            tracing::warn!(
+                "{} requests still running after {:?}, aborting them",
+                summary.requests_aborted,
+                self.shutdown_timeout
+            );
+            tasks.shutdown().await;
+        }
+
+        summary.shutdown_duration = start.elapsed();
+        summary.requests_finished = self.tasks_finished.load(Ordering::Relaxed) - finished_before;
+        summary.requests_panicked = self.tasks_panicked.load(Ordering::Relaxed) - panicked_before;
+        summary
+    }
+}
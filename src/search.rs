@@ -0,0 +1,190 @@
+use std::fmt::Display;
+
+use rust_query::Transaction;
+use tokio::task;
+
+use crate::{
+    GithubDb,
+    database::{
+        schema::{self, Schema},
+        search::{self, IndexMatch, IndexedItem},
+    },
+    enums::ItemState,
+};
+
+/// Whether an item is an issue or a pull request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Issue,
+    PullRequest,
+}
+
+/// Narrows down the results of [`GithubDb::search`].
+#[derive(Clone, Debug)]
+pub struct SearchFilters {
+    pub repo: Option<crate::Repo>,
+    pub kind: Option<ItemKind>,
+    /// Only open (`Some(true)`) or closed (`Some(false)`) items
+    pub open: Option<bool>,
+    /// Also match the text of comments, not just titles and descriptions
+    pub comments: bool,
+    pub limit: usize,
+}
+
+impl Default for SearchFilters {
+    fn default() -> Self {
+        Self {
+            repo: None,
+            kind: None,
+            open: None,
+            comments: true,
+            limit: 20,
+        }
+    }
+}
+
+/// One result of [`GithubDb::search`].
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub repo: crate::Repo,
+    pub number: u64,
+    pub kind: ItemKind,
+    pub title: String,
+    pub open: bool,
+    /// Set when the match is in a comment rather than the title or description
+    pub comment_id: Option<u64>,
+    /// The matching text, with matched terms between `[` and `]`
+    pub snippet: String,
+    /// Lower is more relevant
+    pub rank: f64,
+}
+
+#[derive(Debug)]
+pub enum SearchError {
+    /// The query isn't valid FTS5 syntax
+    Query(String),
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::Query(e) => write!(f, "invalid search query: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl GithubDb {
+    /// Full-text search through titles, descriptions and comments.
+    ///
+    /// `query` uses the [FTS5 query syntax](https://sqlite.org/fts5.html#full_text_query_syntax),
+    /// so `ICE borrowck` finds items containing both words, `"ICE in borrowck"` the exact phrase,
+    /// and `borrow*` any word starting with `borrow`.
+    /// Results are ranked best first, with matches in titles ranking higher.
+    pub async fn search(
+        &self,
+        query: &str,
+        filters: SearchFilters,
+    ) -> Result<Vec<SearchHit>, SearchError> {
+        const BATCH: usize = 100;
+
+        let mut hits = Vec::new();
+        let mut offset = 0;
+        loop {
+            let db = self.raw_db.clone();
+            let query = query.to_string();
+            let comments = filters.comments;
+            let matches = task::spawn_blocking(move || {
+                search::matches(&db.rusqlite_connection(), &query, comments, offset, BATCH)
+            })
+            .await
+            .expect("search panicked")
+            .map_err(|e| SearchError::Query(e.to_string()))?;
+            offset += BATCH;
+
+            let done = matches.len() < BATCH;
+            hits.extend(
+                self.db
+                    .transaction({
+                        let filters = filters.clone();
+                        move |txn| resolve(txn, matches, &filters)
+                    })
+                    .await,
+            );
+
+            if done || hits.len() >= filters.limit {
+                hits.truncate(filters.limit);
+                return Ok(hits);
+            }
+        }
+    }
+}
+
+/// Look up what the index matches refer to and apply the filters.
+fn resolve(
+    txn: &Transaction<Schema>,
+    matches: Vec<IndexMatch>,
+    filters: &SearchFilters,
+) -> Vec<SearchHit> {
+    use schema::*;
+
+    let mut hits = Vec::new();
+    for IndexMatch {
+        item,
+        snippet,
+        rank,
+    } in matches
+    {
+        let (shared, comment_id) = match item {
+            IndexedItem::Shared { number } => {
+                let Some(shared) = txn.query_one(IssuePullRequestShared.number(number)) else {
+                    continue;
+                };
+                (shared, None)
+            }
+            IndexedItem::Comment { comment_id } => {
+                let Some(comment) = txn.query_one(Comment.comment_id(comment_id)) else {
+                    continue;
+                };
+                (
+                    txn.lazy(comment).issue_or_pr.table_row(),
+                    Some(comment_id as u64),
+                )
+            }
+        };
+
+        let kind = match txn.query_one(PullRequest.shared(shared)) {
+            Some(_) => ItemKind::PullRequest,
+            None => ItemKind::Issue,
+        };
+        let shared = txn.lazy(shared);
+        let repo = crate::Repo {
+            organization: shared.repo.organization.clone(),
+            name: shared.repo.name.clone(),
+        };
+        let open = shared.state == ItemState::Open.as_str();
+
+        if filters.kind.is_some_and(|k| k != kind)
+            || filters.open.is_some_and(|o| o != open)
+            || filters
+                .repo
+                .as_ref()
+                .is_some_and(|r| r.organization != repo.organization || r.name != repo.name)
+        {
+            continue;
+        }
+
+        hits.push(SearchHit {
+            repo,
+            number: shared.number as u64,
+            kind,
+            title: shared.title.clone(),
+            open,
+            comment_id,
+            snippet,
+            rank,
+        });
+    }
+    hits
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rust_query::aggregate;
+
+use crate::{GithubDb, forge::TokenBudget, requests::Priority, schema};
+
+/// A snapshot of what's in the database and how fast it's being filled.
+///
+/// Returned by [`GithubDb::stats`].
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub computed_at: DateTime<Utc>,
+
+    pub prs: i64,
+    pub issues: i64,
+    /// Issues and pull requests together
+    pub shared: i64,
+    pub users: i64,
+    pub comments: i64,
+    pub labels: i64,
+
+    /// Number of queued requests in every category
+    pub queue: Vec<(Priority, i64)>,
+
+    pub average_time_between_requests: Duration,
+    /// Measured request rate, derived from `average_time_between_requests`.
+    /// None until at least two requests were measured, like right after startup.
+    pub requests_per_hour: Option<u64>,
+
+    /// Budget left on every api token. Empty if the forge couldn't tell.
+    pub tokens: Vec<TokenBudget>,
+}
+
+impl Stats {
+    /// Total number of queued requests.
+    pub fn pending_requests(&self) -> i64 {
+        self.queue.iter().map(|(_, n)| n).sum()
+    }
+}
+
+impl GithubDb {
+    /// Statistics about the database, the request queue and the api budget.
+    ///
+    /// These are cached, and recomputed at most once per [`GithubDb::with_stats_max_age`].
+    pub async fn stats(&self) -> Stats {
+        let mut cache = self.stats_cache.lock().await;
+        if let Some(stats) = &*cache {
+            let age = (Utc::now() - stats.computed_at)
+                .to_std()
+                .unwrap_or_default();
+            if age < self.stats_max_age {
+                return stats.clone();
+            }
+        }
+
+        let stats = self.compute_stats().await;
+        *cache = Some(stats.clone());
+        stats
+    }
+
+    async fn compute_stats(&self) -> Stats {
+        let computed_at = Utc::now();
+
+        let (prs, issues, shared, users, comments, labels, queue) = self
+            .db
+            .transaction(move |txn| {
+                use schema::*;
+                (
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(PullRequest);
+                        row.count_distinct(r)
+                    })),
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(Issue);
+                        row.count_distinct(r)
+                    })),
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(IssuePullRequestShared);
+                        row.count_distinct(r)
+                    })),
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(User);
+                        row.count_distinct(r)
+                    })),
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(Comment);
+                        row.count_distinct(r)
+                    })),
+                    txn.query_one(aggregate(|row| {
+                        let r = row.join(Label);
+                        row.count_distinct(r)
+                    })),
+                    Priority::ALL
+                        .into_iter()
+                        .map(|c| {
+                            let n = txn.query_one(aggregate(|row| {
+                                let r = row.join(Request);
+                                row.filter(r.category.eq(c as i64));
+                                row.count_distinct(r)
+                            }));
+                            (c, n)
+                        })
+                        .collect(),
+                )
+            })
+            .await;
+
+        let limits = self.limits.lock().await;
+        let average_time_between_requests = limits.average_time_between_requests();
+        let requests_per_hour = limits.requests_per_hour();
+        drop(limits);
+
+        let tokens = self.forge.rate_limits().await.unwrap_or_else(|e| {
+            tracing::warn!("couldn't get rate limits: {e}");
+            Vec::new()
+        });
+
+        Stats {
+            computed_at,
+            prs,
+            issues,
+            shared,
+            users,
+            comments,
+            labels,
+            queue,
+            average_time_between_requests,
+            requests_per_hour,
+            tokens,
+        }
+    }
+
+    /// Log the current [`Stats`].
+    pub(crate) async fn log_stats(&self) {
+        let stats = self.stats().await;
+
+        tracing::info!("{}", self.limits.lock().await);
+        tracing::info!(
+            "prs: {} issues: {} shared: {} users: {} comments: {} labels: {} pending requests: {} {:?}",
+            stats.prs,
+            stats.issues,
+            stats.shared,
+            stats.users,
+            stats.comments,
+            stats.labels,
+            stats.pending_requests(),
+            stats.queue,
+        );
+        match stats.requests_per_hour {
+            Some(requests_per_hour) => tracing::info!(
+                "average time between requests: {:?} i.e. {requests_per_hour} req/h",
+                stats.average_time_between_requests,
+            ),
+            None => tracing::info!("no requests measured yet"),
+        }
+        let progress = self.progress().await;
+        for listing in &progress.listings {
+            tracing::info!(
+                "indexing {} {:?}: page {} of {:?}, {} passes",
+                listing.repo,
+                listing.listing,
+                listing.page,
+                listing.total_pages,
+                listing.passes,
+            );
+        }
+        tracing::info!(
+            "missing comment syncs: {}, missing profiles: {}, remaining requests: {}, eta: {:?}",
+            progress.missing_comment_syncs,
+            progress.missing_profiles,
+            progress.remaining_requests,
+            progress.eta,
+        );
+
+        for (i, token) in stats.tokens.iter().enumerate() {
+            tracing::info!(
+                "token {i}: {}/{} used, resets at {}",
+                token.used,
+                token.limit,
+                token.reset
+            );
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Full user profiles, which listings don't include.
+//!
+//! Listings only have a user's login, id and type. Their name, company, location
+//! and when the account was created are fetched with
+//! [`Request::UserProfile`](requests::Request::UserProfile), one user at a time
+//! at `Index` priority, so it shares its budget with the walk over old items.
+//!
+//! Profiles are fetched once. Those that fail, like of deleted accounts, aren't retried.
+//!
+//! Whatever a deleted account authored is attributed to the [ghost](GHOST_LOGIN) user,
+//! like GitHub itself does, so nothing is left out for lack of an author.
+
+use std::sync::atomic::Ordering;
+
+use chrono::Utc;
+use octocrab::models::UserProfile;
+use rust_query::aggregate;
+
+use crate::{
+    GithubDb,
+    enums::UserKind,
+    requests::{self, Priority},
+    schema,
+};
+
+/// The id of GitHub's `ghost` account, which stands in for deleted accounts.
+pub const GHOST_ID: u64 = 10137;
+/// The login of GitHub's `ghost` account, see [`GHOST_ID`].
+pub const GHOST_LOGIN: &str = "ghost";
+
+impl GithubDb {
+    /// Queue a [`requests::Request::UserProfile`] for the next user without a profile,
+    /// unless one is queued already.
+    ///
+    /// `continuing` is set by the profile request itself, which doesn't count as running.
+    pub(crate) async fn queue_next_profile(&self, continuing: bool) {
+        let name = "UserProfile";
+        let sequence_number = self.request_sequence_number.fetch_add(1, Ordering::Relaxed);
+        let running = self.running.clone();
+
+        let queued = self
+            .db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                // in the same transaction that requests are taken from the queue in,
+                // so a request is always either queued or running
+                if !continuing && running.lock().unwrap().is_running(name) {
+                    return None;
+                }
+                let queued = txn.query_one(aggregate(|rows| {
+                    let request = rows.join(Request);
+                    rows.filter(request.name.eq(name));
+                    rows.count_distinct(request)
+                }));
+                if queued > 0 {
+                    return None;
+                }
+
+                let user_id = txn.query(|rows| {
+                    let user = rows.join(User);
+                    rows.filter(user.profile_synced_timestamp.is_none());
+                    rows.order_by()
+                        .asc(&user.github_id)
+                        .into_iter(&user.github_id)
+                        .next()
+                })?;
+                let request = requests::Request::UserProfile {
+                    user_id: user_id as u64,
+                };
+                txn.insert(Request {
+                    name,
+                    category: Priority::Index as i64,
+                    sequence_number,
+                    data: serde_json::to_vec(&request).unwrap(),
+                })
+                .expect("duplicate sequence number");
+                Some(request)
+            })
+            .await;
+
+        if let Some(request) = queued {
+            tracing::debug!("add request: {request:?} at p {:?}", Priority::Index);
+        }
+    }
+
+    /// Store a fetched profile, or only that fetching it was tried if it's None.
+    pub(crate) async fn store_profile(&self, user_id: u64, profile: Option<UserProfile>) {
+        let bots = self.bots.clone();
+        self.db
+            .transaction_mut_ok(move |txn| {
+                use schema::*;
+
+                let Some(row) = txn.query_one(User.github_id(user_id as i64)) else {
+                    return;
+                };
+                let mut user = txn.mutable(row);
+                user.profile_synced_timestamp = Some(Utc::now().timestamp());
+                let Some(profile) = profile else {
+                    return;
+                };
+
+                let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+                let kind = if bots.contains(&profile.login) {
+                    Some(UserKind::Bot)
+                } else {
+                    profile.r#type.parse().ok()
+                };
+                user.display_name = non_empty(profile.name).unwrap_or(profile.login.clone());
+                user.login = profile.login.to_lowercase();
+                user.name = profile.login;
+                user.kind = kind.map(|kind| kind.as_str().to_string());
+                user.company = non_empty(profile.company);
+                user.location = non_empty(profile.location);
+                user.account_created_timestamp = Some(profile.created_at.timestamp());
+            })
+            .await
+    }
+}
@@ -0,0 +1,146 @@
+//! Collaborators, the teams with access to a repository, and their members.
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{Harness, REPO};
+use github_db::{
+    Repo,
+    enums::Permission,
+    forge::fake::FakeGithub,
+    queries::{CollaboratorSummary, RepoTeamSummary},
+    schema::{Assignment, Collaborator},
+};
+
+/// The collaborators and teams with access to `repo`.
+async fn access(h: &Harness, repo: &Repo) -> (Vec<CollaboratorSummary>, Vec<RepoTeamSummary>) {
+    let collaborators = h.gh.collaborators(repo, Permission::Read).await;
+    (collaborators, h.gh.repo_teams(repo).await)
+}
+
+fn collaborator(login: &str, permission: Permission) -> CollaboratorSummary {
+    CollaboratorSummary {
+        login: login.to_string(),
+        permission,
+    }
+}
+
+#[tokio::test]
+async fn permissions() {
+    let fake = FakeGithub::new().with_page_size(2);
+    fake.set_collaborator(REPO, "alice", Some("admin"));
+    fake.set_collaborator(REPO, "bob", Some("triage"));
+    fake.set_collaborator(REPO, "carol", Some("write"));
+    fake.set_collaborator(REPO, "dave", Some("read"));
+    fake.set_collaborator(REPO, "erin", Some("maintain"));
+    fake.set_team("rust-lang", "compiler", "Compiler", &["carol", "frank"]);
+    fake.set_team("rust-lang", "release", "Release", &["alice"]);
+    fake.set_team_permission(REPO, "compiler", Some("write"));
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    let (collaborators, teams) = h
+        .sync_until_with(
+            async || access(&h, &repo).await,
+            |(c, t)| c.len() == 5 && t.len() == 1 && t[0].members.len() == 2,
+        )
+        .await;
+
+    assert_eq!(
+        collaborators,
+        [
+            collaborator("alice", Permission::Admin),
+            collaborator("erin", Permission::Maintain),
+            collaborator("carol", Permission::Write),
+            collaborator("bob", Permission::Triage),
+            collaborator("dave", Permission::Read),
+        ]
+    );
+    assert_eq!(teams[0].slug, "compiler");
+    assert_eq!(teams[0].name, "Compiler");
+    assert_eq!(teams[0].permission, Permission::Write);
+    assert_eq!(teams[0].members, ["carol", "frank"]);
+
+    let triagers = h.gh.collaborators(&repo, Permission::Triage).await;
+    assert_eq!(triagers.len(), 4);
+    assert_eq!(
+        h.gh.permission_of(&repo, "frank").await,
+        Some(Permission::Write)
+    );
+    assert_eq!(h.gh.permission_of(&repo, "mallory").await, None);
+    // mentioned teams are the same teams
+    assert_eq!(
+        h.gh.team_members("rust-lang", "compiler").await,
+        ["carol", "frank"]
+    );
+    // teams without access to a synced repository aren't listed
+    assert!(h.gh.team_members("rust-lang", "release").await.is_empty());
+}
+
+#[tokio::test]
+async fn lost_access_is_removed() {
+    let fake = Arc::new(FakeGithub::new());
+    fake.set_collaborator(REPO, "alice", Some("write"));
+    fake.set_collaborator(REPO, "bob", Some("triage"));
+    fake.set_team("rust-lang", "compiler", "Compiler", &["alice", "bob"]);
+    fake.set_team("rust-lang", "triage", "Triage", &["bob"]);
+    fake.set_team_permission(REPO, "compiler", Some("write"));
+    fake.set_team_permission(REPO, "triage", Some("triage"));
+    let h = Harness::with_config(fake.clone(), |gh| {
+        gh.with_access_refresh_interval(Duration::from_millis(200))
+    })
+    .await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(
+        async || access(&h, &repo).await,
+        |(c, t)| c.len() == 2 && t.len() == 2,
+    )
+    .await;
+
+    fake.set_collaborator(REPO, "alice", Some("maintain"));
+    fake.set_collaborator(REPO, "bob", None);
+    fake.set_team("rust-lang", "compiler", "Compiler", &["alice"]);
+    fake.set_team_permission(REPO, "triage", None);
+    let (collaborators, teams) = h
+        .sync_until_with(
+            async || access(&h, &repo).await,
+            |(c, t)| c.len() == 1 && t.len() == 1 && t[0].members.len() == 1,
+        )
+        .await;
+    assert_eq!(collaborators, [collaborator("alice", Permission::Maintain)]);
+    assert_eq!(teams[0].slug, "compiler");
+
+    assert_eq!(h.gh.permission_of(&repo, "bob").await, None);
+}
+
+#[tokio::test]
+async fn assignees_without_write_access() {
+    let fake = FakeGithub::new();
+    fake.set_collaborator(REPO, "alice", Some("write"));
+    fake.set_collaborator(REPO, "bob", Some("triage"));
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "carol");
+    fake.edit(REPO, ice, |issue| {
+        issue.assignees = vec!["alice".to_string(), "bob".to_string()]
+    });
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 1).await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(async || access(&h, &repo).await, |(c, _)| c.len() == 2)
+        .await;
+
+    // collaborators are linked to the same users as assignments
+    let without_write =
+        h.gh.transaction(|txn| {
+            txn.query(|rows| {
+                let assignment = rows.join(Assignment);
+                rows.filter(assignment.outdated.eq(0));
+                let collaborator = rows.join(Collaborator);
+                rows.filter(collaborator.user.eq(&assignment.user));
+                rows.filter(collaborator.repo.eq(&assignment.issue_or_pr.repo));
+                rows.filter(collaborator.permission.eq(Permission::Triage.as_str()));
+                rows.into_vec(&assignment.user.name)
+            })
+        })
+        .await;
+    assert_eq!(without_write, ["bob"]);
+}
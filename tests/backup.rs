@@ -0,0 +1,185 @@
+//! Backups and snapshots of a database that's being synced.
+
+mod common;
+
+use std::{
+    fs,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use common::{Harness, REPO};
+use github_db::{
+    GithubDb, QueuedRequest, Repo,
+    forge::{Forge, ForgeError, ForgePage, ListType, async_trait, fake::FakeGithub},
+};
+use octocrab::models::{
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+};
+use tempfile::TempDir;
+use tokio::sync::Semaphore;
+
+/// Holds listings of old pull requests until released, so they stay in flight.
+struct Stalled {
+    fake: Arc<FakeGithub>,
+    release: Semaphore,
+    waiting: AtomicUsize,
+}
+
+#[async_trait]
+impl Forge for Stalled {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        if list_type == ListType::Old {
+            self.waiting.fetch_add(1, Ordering::SeqCst);
+            drop(self.release.acquire().await);
+        }
+        self.fake.list_prs(repo, list_type, page, url).await
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        self.fake.list_issues(repo, list_type, page, url).await
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        self.fake
+            .list_comments(repo, issue_number, since, page, url)
+            .await
+    }
+}
+
+async fn open(path: &std::path::Path, forge: Arc<dyn Forge>, repos: &[&str]) -> GithubDb {
+    // boxed, the future is large enough to overflow the test thread's stack when used a few times
+    Box::pin(GithubDb::new_with_forge(path, forge, 10_000_000, repos))
+        .await
+        .with_stats_max_age(Duration::ZERO)
+}
+
+fn old_prs(queue: &[QueuedRequest]) -> Vec<&QueuedRequest> {
+    queue.iter().filter(|r| r.name == "OldPr").collect()
+}
+
+#[tokio::test]
+async fn backup_is_a_copy() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    fake.add_pr(REPO, "fix the ICE", "carol");
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 2 && c.prs == 1 && c.comments == 1)
+        .await;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("backup.sqlite");
+    h.gh.backup_to(&path).await.unwrap();
+    let files: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+    assert_eq!(files.len(), 1, "no temporary or wal files are left");
+
+    let copy = open(&path, Arc::new(FakeGithub::new()), &[]).await;
+    let stats = copy.stats().await;
+    assert_eq!((stats.shared, stats.prs, stats.comments), (2, 1, 1));
+    assert_eq!(
+        copy.find(&"is:issue borrowck".parse().unwrap(), 10)
+            .await
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn restored_backup_resumes_running_requests() {
+    let fake = Arc::new(FakeGithub::new().with_page_size(1));
+    for i in 0..3 {
+        fake.add_pr(REPO, &format!("pr {i}"), "carol");
+    }
+    let stalled = Arc::new(Stalled {
+        fake: fake.clone(),
+        release: Semaphore::new(0),
+        waiting: AtomicUsize::new(0),
+    });
+    let h = Harness::new(stalled.clone()).await;
+
+    let start = Instant::now();
+    while stalled.waiting.load(Ordering::SeqCst) == 0 {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.gh.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // taken from the queue while it's being handled
+    assert!(old_prs(&h.gh.queue(None, 100).await).is_empty());
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("backup.sqlite");
+    h.gh.backup_to(&path).await.unwrap();
+    stalled.release.add_permits(1);
+
+    let copy = open(&path, fake.clone(), &[]).await;
+    let queue = copy.queue(None, 100).await;
+    let old = old_prs(&queue);
+    assert_eq!(old.len(), 1);
+    assert!(old[0].data.contains("\"page\":0"));
+
+    let copy = open(&path, fake.clone(), &[REPO]).await;
+    let queue = copy.queue(None, 100).await;
+    assert_eq!(old_prs(&queue).len(), 1, "not queued again at startup");
+
+    let copy = Arc::new(copy);
+    let start = Instant::now();
+    while copy.stats().await.prs < 3 {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        copy.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn snapshots_rotate() {
+    let fake = FakeGithub::new();
+    fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let dir = TempDir::new().unwrap();
+    let h = Harness::with_config(Arc::new(fake), |gh| {
+        gh.with_snapshots(dir.path(), Duration::from_millis(100), 2)
+    })
+    .await;
+
+    let summary =
+        h.gh.clone()
+            .run(tokio::time::sleep(Duration::from_millis(700)))
+            .await;
+    assert!(summary.snapshots >= 3, "{summary:?}");
+
+    let mut names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 2, "{names:?}");
+    assert!(
+        names
+            .iter()
+            .all(|name| name.starts_with("snapshot-") && name.ends_with(".sqlite"))
+    );
+}
@@ -0,0 +1,88 @@
+//! Recording a sync session and replaying it.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{Harness, REPO, populate};
+use github_db::{
+    Repo,
+    forge::{
+        Forge, ListType,
+        cassette::{Recorder, Replayer},
+        fake::FakeGithub,
+    },
+};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn replay_reproduces_recorded_session() {
+    let dir = TempDir::new().unwrap();
+    let cassette = dir.path().join("session.jsonl");
+
+    let fake = FakeGithub::new().with_page_size(4);
+    populate(&fake, 6, 5);
+    let recorder = Recorder::create(Arc::new(fake), &cassette).unwrap();
+
+    let recorded = Harness::new(Arc::new(recorder))
+        .await
+        .sync_until(|c| c.shared == 11 && c.prs == 5 && c.comments == 6)
+        .await;
+
+    let replayer = Arc::new(Replayer::open(&cassette).unwrap());
+    assert!(replayer.remaining() > 0);
+
+    let replayed = Harness::new(replayer.clone())
+        .await
+        .sync_until(|c| c == recorded)
+        .await;
+    assert_eq!(replayed, recorded);
+}
+
+#[tokio::test]
+async fn replay_fails_unknown_requests() {
+    let dir = TempDir::new().unwrap();
+    let cassette = dir.path().join("session.jsonl");
+
+    let fake = FakeGithub::new();
+    populate(&fake, 1, 1);
+    let recorder = Recorder::create(Arc::new(fake), &cassette).unwrap();
+    let repo: Repo = REPO.parse().unwrap();
+    recorder
+        .list_prs(&repo, ListType::New, 0, None)
+        .await
+        .unwrap();
+
+    let replayer = Replayer::open(&cassette).unwrap();
+    assert_eq!(replayer.remaining(), 1);
+
+    // a different page wasn't recorded
+    assert!(
+        replayer
+            .list_prs(&repo, ListType::New, 1, None)
+            .await
+            .is_err()
+    );
+    // neither was a different listing order
+    assert!(
+        replayer
+            .list_prs(&repo, ListType::Old, 0, None)
+            .await
+            .is_err()
+    );
+
+    let page = replayer
+        .list_prs(&repo, ListType::New, 0, None)
+        .await
+        .unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(replayer.remaining(), 0);
+
+    // every interaction is replayed only once
+    assert!(
+        replayer
+            .list_prs(&repo, ListType::New, 0, None)
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,115 @@
+//! The `github-db` binary against a database synced from the fake.
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::{path::Path, process::Command, sync::Arc};
+
+use common::{Harness, REPO};
+use github_db::forge::fake::FakeGithub;
+
+fn github_db(db: &Path, args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_github-db"))
+        .arg("--db")
+        .arg(db)
+        .args(args)
+        .env_remove("GITHUB_DB_CONFIG")
+        .env("RUST_LOG", "warn")
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn commands() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "bob", "borrowck strikes again");
+    fake.add_pr(REPO, "fix borrowck", "carol");
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 2 && c.prs == 1 && c.comments == 1)
+        .await;
+    let db = h.db_path();
+
+    let (ok, out, _) = github_db(&db, &["find", "is:pr is:open"]);
+    assert!(ok);
+    assert_eq!(out.lines().count(), 1);
+    assert!(out.contains("fix borrowck"));
+
+    let (ok, out, _) = github_db(&db, &["search", "borrowck", "--kind", "issue"]);
+    assert!(ok);
+    assert!(out.contains("ICE in borrowck"));
+    assert!(out.contains("[borrowck] strikes again"));
+
+    let (ok, out, _) = github_db(&db, &["stats"]);
+    assert!(ok);
+    assert!(out.contains("pull requests  1"));
+
+    let (ok, out, _) = github_db(&db, &["queue", "list", "--priority", "index"]);
+    assert!(ok);
+    assert!(out.lines().all(|line| line.contains("Index")));
+    let (ok, out, _) = github_db(&db, &["queue", "clear", "--priority", "index"]);
+    assert!(ok);
+    assert!(out.starts_with("removed "));
+    let (_, out, _) = github_db(&db, &["queue", "list", "--priority", "index"]);
+    assert!(out.is_empty());
+
+    let export = tempfile::TempDir::new().unwrap();
+    let dir = export.path().to_str().unwrap();
+    let (ok, out, _) = github_db(
+        &db,
+        &["export", dir, "--format", "csv", "--since", "2020-01-01"],
+    );
+    assert!(ok);
+    assert!(out.contains("issues.csv"));
+    assert!(export.path().join("comments.csv").exists());
+
+    let (ok, _, err) = github_db(&db, &["find", "lable:foo"]);
+    assert!(!ok);
+    assert!(err.contains("did you mean `label`?"));
+}
+
+#[test]
+fn migrate_creates_a_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let db = dir.path().join("new.sqlite");
+    let (ok, out, _) = github_db(&db, &["migrate"]);
+    assert!(ok);
+    assert!(out.ends_with("is up to date\n"));
+    assert!(db.exists());
+
+    let (ok, _, err) = github_db(&db, &["sync"]);
+    assert!(!ok);
+    assert!(err.contains("no repositories"));
+}
+
+#[test]
+fn reading_needs_a_migrated_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let db = dir.path().join("new.sqlite");
+    let (ok, _, err) = github_db(&db, &["stats"]);
+    assert!(!ok);
+    assert!(err.contains("doesn't exist"));
+    assert!(!db.exists());
+
+    let (ok, _, _) = github_db(&db, &["migrate"]);
+    assert!(ok);
+    let (ok, out, _) = github_db(&db, &["stats"]);
+    assert!(ok);
+    assert!(out.contains("pull requests  0"));
+
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap();
+    conn.pragma_update(None, "user_version", version - 1)
+        .unwrap();
+    drop(conn);
+    let (ok, _, err) = github_db(&db, &["find", "is:open"]);
+    assert!(!ok);
+    assert!(err.contains("migrate it first"));
+}
@@ -0,0 +1,129 @@
+//! Shared setup for the integration tests.
+#![allow(dead_code)]
+
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use github_db::{
+    GithubDb,
+    forge::{Forge, fake::FakeGithub},
+    rust_query::aggregate,
+    schema::{Comment, IssuePullRequestShared, LabelLink, PullRequest},
+};
+use tempfile::TempDir;
+
+pub const REPO: &str = "rust-lang/rust";
+
+pub struct Harness {
+    pub gh: Arc<GithubDb>,
+    dir: TempDir,
+}
+
+impl Harness {
+    pub async fn new(forge: Arc<dyn Forge>) -> Self {
+        Self::with_config(forge, |gh| gh).await
+    }
+
+    /// Like [`Harness::new`], with extra configuration of the [`GithubDb`].
+    pub async fn with_config(
+        forge: Arc<dyn Forge>,
+        configure: impl FnOnce(GithubDb) -> GithubDb,
+    ) -> Self {
+        let dir = TempDir::new().unwrap();
+        let gh = GithubDb::new_with_forge(dir.path().join("db.sqlite"), forge, 10_000_000, &[REPO])
+            .await
+            .with_refresh_interval(Duration::from_millis(200));
+        let gh = configure(gh);
+
+        Self {
+            gh: Arc::new(gh),
+            dir,
+        }
+    }
+
+    pub fn db_path(&self) -> PathBuf {
+        self.dir.path().join("db.sqlite")
+    }
+
+    /// Drive the scheduler until `done` returns true for the database counts.
+    pub async fn sync_until(&self, done: impl Fn(Counts) -> bool) -> Counts {
+        self.sync_until_with(async || self.counts().await, |counts| done(*counts))
+            .await
+    }
+
+    /// Drive the scheduler until `done` returns true for what `probe` looks up.
+    pub async fn sync_until_with<T: Debug>(
+        &self,
+        probe: impl AsyncFn() -> T,
+        done: impl Fn(&T) -> bool,
+    ) -> T {
+        let start = Instant::now();
+        loop {
+            self.gh.clone().update().await;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            let found = probe().await;
+            if done(&found) {
+                return found;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(20),
+                "sync didn't finish, got {found:?}"
+            );
+        }
+    }
+
+    pub async fn counts(&self) -> Counts {
+        self.gh
+            .transaction(|txn| Counts {
+                shared: txn.query_one(aggregate(|rows| {
+                    let r = rows.join(IssuePullRequestShared);
+                    rows.count_distinct(r)
+                })),
+                prs: txn.query_one(aggregate(|rows| {
+                    let r = rows.join(PullRequest);
+                    rows.count_distinct(r)
+                })),
+                comments: txn.query_one(aggregate(|rows| {
+                    let r = rows.join(Comment);
+                    rows.count_distinct(r)
+                })),
+                label_links: txn.query_one(aggregate(|rows| {
+                    let r = rows.join(LabelLink);
+                    rows.count_distinct(r)
+                })),
+            })
+            .await
+    }
+
+    pub async fn title(&self, number: u64) -> Option<String> {
+        self.gh
+            .transaction(move |txn| {
+                txn.query_one(IssuePullRequestShared.number(number as i64))
+                    .map(|row| txn.lazy(row).title.clone())
+            })
+            .await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Counts {
+    pub shared: i64,
+    pub prs: i64,
+    pub comments: i64,
+    pub label_links: i64,
+}
+
+pub fn populate(fake: &FakeGithub, issues: u64, prs: u64) {
+    for i in 0..issues {
+        let number = fake.add_issue(REPO, &format!("issue {i}"), "alice");
+        fake.add_comment(REPO, number, "bob", "a comment");
+    }
+    for i in 0..prs {
+        fake.add_pr(REPO, &format!("pr {i}"), "carol");
+    }
+}
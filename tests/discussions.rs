@@ -0,0 +1,151 @@
+//! Discussions, which are listed over GraphQL together with their comments.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{Harness, REPO};
+use github_db::{GHOST_LOGIN, Listing, Repo, enums::AuthorAssociation, forge::fake::FakeGithub};
+
+#[tokio::test]
+async fn answered_question() {
+    let fake = FakeGithub::new();
+    fake.set_association("alice", "MEMBER");
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let question = fake.add_discussion(REPO, "Q&A", "How do I bisect?", "bob");
+    let hint = fake.add_discussion_comment(REPO, question, None, "alice", "use cargo-bisect-rustc");
+    fake.add_discussion_comment(REPO, question, Some(hint), "bob", "thanks, that worked");
+    fake.mark_answer(REPO, question, hint, "bob");
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    let discussions = h
+        .sync_until_with(async || h.gh.discussions(&repo).await, |d| d.len() == 1)
+        .await;
+
+    // discussions are numbered like issues
+    assert_eq!((ice, question), (1, 2));
+    let discussion = &discussions[0];
+    assert_eq!(discussion.number, question);
+    assert_eq!(discussion.title, "How do I bisect?");
+    assert_eq!(discussion.category, "Q&A");
+    assert_eq!(discussion.author, "bob");
+    assert_eq!(
+        discussion.author_association,
+        AuthorAssociation::Contributor
+    );
+    assert_eq!(
+        discussion.html_url,
+        format!("https://github.com/{REPO}/discussions/{question}")
+    );
+    assert!(discussion.answer_chosen_at.is_some());
+    assert_eq!(discussion.closed_at, None);
+
+    let thread = h.gh.discussion_thread(&repo, question).await.unwrap();
+    assert_eq!(thread.len(), 2);
+    assert_eq!(thread[0].author, "alice");
+    assert_eq!(thread[0].author_association, AuthorAssociation::Member);
+    assert!(thread[0].is_answer);
+    assert_eq!(thread[0].reply_to, None);
+    assert_eq!(thread[1].body, "thanks, that worked");
+    assert!(!thread[1].is_answer);
+    assert_eq!(thread[1].reply_to.as_ref(), Some(&thread[0].node_id));
+
+    assert!(h.gh.discussion_thread(&repo, ice).await.is_none());
+}
+
+#[tokio::test]
+async fn old_discussions_are_paged() {
+    let fake = FakeGithub::new().with_page_size(2);
+    for i in 0..5 {
+        fake.add_discussion(REPO, "Ideas", &format!("idea {i}"), "alice");
+    }
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(async || h.gh.discussions(&repo).await, |d| d.len() == 5)
+        .await;
+
+    h.sync_until_with(
+        async || h.gh.progress().await,
+        |progress| {
+            progress
+                .listings
+                .iter()
+                .any(|l| l.listing == Listing::Discussions && l.passes > 0)
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn comments_past_the_listing_are_paged() {
+    let fake = Arc::new(FakeGithub::new().with_page_size(2));
+    let idea = fake.add_discussion(REPO, "Ideas", "const generics everywhere", "alice");
+    let mut first = None;
+    for i in 0..5 {
+        let comment = fake.add_discussion_comment(REPO, idea, None, "bob", &format!("+{i}"));
+        first.get_or_insert(comment);
+    }
+    for i in 0..5 {
+        fake.add_discussion_comment(REPO, idea, first, "carol", &format!("reply {i}"));
+    }
+    let h = Harness::new(fake.clone()).await;
+    let repo: Repo = REPO.parse().unwrap();
+    let thread = h
+        .sync_until_with(
+            async || h.gh.discussion_thread(&repo, idea).await,
+            |thread| thread.as_ref().is_some_and(|thread| thread.len() == 10),
+        )
+        .await
+        .unwrap();
+    let replies = thread.iter().filter(|c| c.reply_to.is_some()).count();
+    assert_eq!(replies, 5);
+    assert!(
+        fake.calls()
+            .iter()
+            .any(|call| call.starts_with("discussion comments DC_"))
+    );
+}
+
+#[tokio::test]
+async fn changes_are_picked_up() {
+    let fake = Arc::new(FakeGithub::new());
+    let idea = fake.add_discussion(REPO, "Ideas", "const generics everywhere", "alice");
+    let h = Harness::new(fake.clone()).await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(async || h.gh.discussions(&repo).await, |d| d.len() == 1)
+        .await;
+
+    fake.add_discussion_comment(REPO, idea, None, "bob", "see the RFC");
+    fake.edit_discussion(REPO, idea, |discussion| {
+        discussion.closed = true;
+        discussion.locked = true;
+    });
+    let discussions = h
+        .sync_until_with(
+            async || h.gh.discussions(&repo).await,
+            |d| d[0].closed_at.is_some(),
+        )
+        .await;
+    assert!(discussions[0].locked);
+
+    let thread = h.gh.discussion_thread(&repo, idea).await.unwrap();
+    assert_eq!(thread.len(), 1);
+    assert_eq!(thread[0].body, "see the RFC");
+}
+
+#[tokio::test]
+async fn deleted_authors_become_the_ghost() {
+    let fake = FakeGithub::new();
+    let idea = fake.add_discussion(REPO, "Ideas", "const generics everywhere", "mallory");
+    fake.add_discussion_comment(REPO, idea, None, "mallory", "bump");
+    fake.delete_user("mallory");
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    let discussions = h
+        .sync_until_with(async || h.gh.discussions(&repo).await, |d| d.len() == 1)
+        .await;
+    assert_eq!(discussions[0].author, GHOST_LOGIN);
+
+    let thread = h.gh.discussion_thread(&repo, idea).await.unwrap();
+    assert_eq!(thread[0].author, GHOST_LOGIN);
+}
@@ -0,0 +1,136 @@
+//! Exporting the database to files.
+
+mod common;
+
+use std::{fs, path::Path, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use common::{Harness, REPO};
+use github_db::{
+    export::{ExportFormat, ExportOptions},
+    forge::fake::FakeGithub,
+};
+use serde_json::Value;
+
+async fn synced() -> Harness {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.labels = vec!["I-ICE".to_string()];
+        issue.assignees = vec!["bob".to_string()];
+    });
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    fake.edit(REPO, fix, |pr| {
+        pr.requested_reviewers = vec!["alice".to_string()];
+        pr.merged = true;
+    });
+
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 2 && c.prs == 1 && c.comments == 1 && c.label_links == 1)
+        .await;
+    h
+}
+
+fn jsonl(dir: &Path, table: &str) -> Vec<Value> {
+    fs::read_to_string(dir.join(format!("{table}.jsonl")))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn jsonl_has_every_table() {
+    let h = synced().await;
+    let dir = tempfile::TempDir::new().unwrap();
+
+    let summary =
+        h.gh.export(dir.path(), ExportOptions::default())
+            .await
+            .unwrap();
+    assert_eq!(summary.files.len(), 10);
+
+    let issues = jsonl(dir.path(), "issues");
+    assert_eq!(issues.len(), 2);
+    let ice = issues.iter().find(|i| i["number"] == 1).unwrap();
+    assert_eq!(ice["repo"], REPO);
+    assert_eq!(ice["state"], "open");
+    assert_eq!(ice["is_pull_request"], false);
+    assert_eq!(ice["author"], "alice");
+    assert_eq!(ice["created_at"], "2020-01-01T00:01:00Z");
+    assert_eq!(ice["closed_at"], Value::Null);
+
+    let prs = jsonl(dir.path(), "pull_requests");
+    assert_eq!(prs.len(), 1);
+    assert_eq!(prs[0]["number"], 2);
+    assert!(prs[0]["merged_at"].is_string());
+    assert!(prs[0]["mergeable_state"].is_string());
+
+    let comments = jsonl(dir.path(), "comments");
+    assert_eq!(comments[0]["body"], "can reproduce");
+    assert_eq!(comments[0]["number"], 1);
+
+    assert_eq!(jsonl(dir.path(), "labels")[0]["name"], "I-ICE");
+    assert_eq!(jsonl(dir.path(), "label_links")[0]["label"], "I-ICE");
+    assert_eq!(jsonl(dir.path(), "assignments")[0]["user"], "bob");
+    assert_eq!(jsonl(dir.path(), "review_requests")[0]["user"], "alice");
+    assert_eq!(jsonl(dir.path(), "users").len(), 3);
+}
+
+#[tokio::test]
+async fn csv_and_incremental() {
+    let h = synced().await;
+    let dir = tempfile::TempDir::new().unwrap();
+
+    // only the pull request was changed after its creation at 00:04
+    let since: DateTime<Utc> = "2020-01-01T00:04:00Z".parse().unwrap();
+    h.gh.export(
+        dir.path(),
+        ExportOptions {
+            format: ExportFormat::Csv,
+            since: Some(since),
+        },
+    )
+    .await
+    .unwrap();
+
+    let issues = fs::read_to_string(dir.path().join("issues.csv")).unwrap();
+    let mut lines = issues.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("repo,number,is_pull_request,title,")
+    );
+    let rows: Vec<_> = lines.collect();
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].starts_with("rust-lang/rust,2,true,fix the ICE,"));
+
+    let comments = fs::read_to_string(dir.path().join("comments.csv")).unwrap();
+    assert_eq!(comments.lines().count(), 1, "only the header");
+    let users = fs::read_to_string(dir.path().join("users.csv")).unwrap();
+    assert_eq!(users.lines().count(), 4, "users are always exported");
+}
+
+#[cfg(feature = "parquet")]
+#[tokio::test]
+async fn parquet() {
+    let h = synced().await;
+    let dir = tempfile::TempDir::new().unwrap();
+
+    let summary =
+        h.gh.export(
+            dir.path(),
+            ExportOptions {
+                format: ExportFormat::Parquet,
+                since: None,
+            },
+        )
+        .await
+        .unwrap();
+    let (path, rows) = &summary.files[0];
+    assert!(path.ends_with("issues.parquet"));
+    assert_eq!(*rows, 2);
+    assert_eq!(&fs::read(path).unwrap()[..4], b"PAR1");
+}
@@ -0,0 +1,124 @@
+//! The GitHub-style filter language.
+
+mod common;
+
+use common::{Harness, REPO};
+use github_db::{Filter, ItemKind, forge::fake::FakeGithub};
+
+async fn synced() -> Harness {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.labels = vec!["T-compiler".to_string(), "I-ICE".to_string()];
+        issue.assignees = vec!["bob".to_string()];
+    });
+    let typo = fake.add_issue(REPO, "typo in docs", "bob");
+    fake.edit(REPO, typo, |issue| issue.closed = true);
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    fake.edit(REPO, fix, |pr| {
+        pr.labels = vec!["T-compiler".to_string()];
+        pr.requested_reviewers = vec!["alice".to_string()];
+    });
+    let refactor = fake.add_pr(REPO, "refactor borrowck", "dave");
+    fake.edit(REPO, refactor, |pr| pr.merged = true);
+
+    let h = Harness::new(std::sync::Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 4 && c.prs == 2 && c.label_links == 3)
+        .await;
+    h
+}
+
+async fn numbers(h: &Harness, query: &str) -> Vec<u64> {
+    let filter = Filter::parse(query).unwrap();
+    h.gh.find(&filter, 10)
+        .await
+        .into_iter()
+        .map(|item| item.number)
+        .collect()
+}
+
+#[tokio::test]
+async fn qualifiers_match() {
+    let h = synced().await;
+
+    assert_eq!(numbers(&h, "").await, [4, 3, 2, 1]);
+    assert_eq!(numbers(&h, "is:pr").await, [4, 3]);
+    assert_eq!(numbers(&h, "is:issue is:open").await, [1]);
+    assert_eq!(numbers(&h, "state:closed").await, [4, 2]);
+    assert_eq!(numbers(&h, "is:merged").await, [4]);
+    assert_eq!(numbers(&h, "label:t-compiler").await, [3, 1]);
+    assert_eq!(numbers(&h, "label:T-compiler -label:I-ICE").await, [3]);
+    assert_eq!(numbers(&h, "no:label").await, [4, 2]);
+    assert_eq!(numbers(&h, "author:Bob").await, [2]);
+    assert_eq!(numbers(&h, "assignee:bob").await, [1]);
+    assert_eq!(numbers(&h, "-no:assignee").await, [1]);
+    assert_eq!(numbers(&h, "review-requested:alice").await, [3]);
+    assert_eq!(numbers(&h, "is:pr no:review-requested").await, [4]);
+    assert_eq!(numbers(&h, "borrowck").await, [4, 1]);
+    assert_eq!(numbers(&h, "-borrowck -ICE").await, [2]);
+    assert!(
+        numbers(&h, "repo:rust-lang/rust is:locked")
+            .await
+            .is_empty()
+    );
+    assert!(numbers(&h, "repo:rust-lang/cargo").await.is_empty());
+}
+
+#[tokio::test]
+async fn dates_and_sorting() {
+    let h = synced().await;
+
+    // the fake clock starts at 2020-01-01 and ticks a minute per change
+    assert_eq!(numbers(&h, "created:2020-01-01").await, [4, 3, 2, 1]);
+    assert!(numbers(&h, "created:>2020-01-01").await.is_empty());
+    assert!(numbers(&h, "created:<2020-01-01").await.is_empty());
+    assert_eq!(
+        numbers(&h, "created:>=2020-01-01T00:03:00Z").await,
+        [4, 3, 2]
+    );
+    assert_eq!(numbers(&h, "created:*..2020-01-01T00:03:00Z").await, [2, 1]);
+    assert_eq!(numbers(&h, "closed:2020-01-01").await, [4, 2]);
+    assert_eq!(numbers(&h, "-closed:<=2020-01-01").await, [3, 1]);
+
+    assert_eq!(numbers(&h, "sort:created-asc").await, [1, 2, 3, 4]);
+    assert_eq!(numbers(&h, "sort:updated").await, [4, 3, 2, 1]);
+
+    let filter = Filter::parse("is:pr").unwrap();
+    let items = h.gh.find(&filter, 1).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].kind, ItemKind::PullRequest);
+    assert_eq!(items[0].author, "dave");
+    assert!(!items[0].open);
+    assert!(items[0].closed_at.is_some());
+}
+
+#[test]
+fn errors_point_at_the_problem() {
+    let error = Filter::parse("is:pr lable:foo").unwrap_err();
+    assert_eq!(error.span, 6..11);
+    assert_eq!(
+        error.to_string(),
+        "unknown qualifier `lable`, did you mean `label`?\n  is:pr lable:foo\n        ^^^^^"
+    );
+
+    let error = Filter::parse("label:\"good first").unwrap_err();
+    assert_eq!(error.message, "unterminated quote");
+    assert_eq!(error.span, 6..17);
+
+    let error = Filter::parse("updated:>2025-13-01").unwrap_err();
+    assert_eq!(error.span, 9..19);
+
+    // quoted values are pointed at without their quotes
+    let error = Filter::parse(r#"created:"2025-13-01""#).unwrap_err();
+    assert_eq!(error.span, 9..19);
+    assert!(error.to_string().ends_with("\n           ^^^^^^^^^^"));
+    let error = Filter::parse(r#"created:"xé""#).unwrap_err();
+    assert_eq!(error.span, 9..12);
+    assert!(error.to_string().ends_with("\n           ^^"));
+
+    assert!(Filter::parse("is:nonsense").is_err());
+    assert!(Filter::parse("author:").is_err());
+    assert!(Filter::parse("sort:created sort:updated").is_err());
+    assert!(Filter::parse("sort:comments").is_err());
+    assert!(Filter::parse("label:\"good first issue\" -is:draft").is_ok());
+}
@@ -0,0 +1,277 @@
+//! Bootstrapping a database from exports and GH Archive files.
+
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, REPO};
+use flate2::{Compression, write::GzEncoder};
+use github_db::{
+    GHOST_LOGIN, GithubDb, Priority, Repo,
+    export::ExportOptions,
+    forge::{Forge, ListType, fake::FakeGithub},
+    import::ImportSummary,
+};
+use serde_json::json;
+use tempfile::TempDir;
+
+async fn synced() -> Harness {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.labels = vec!["I-ICE".to_string()];
+        issue.assignees = vec!["bob".to_string()];
+        issue.closed = true;
+    });
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    fake.edit(REPO, fix, |pr| {
+        pr.requested_reviewers = vec!["alice".to_string()];
+        pr.merged = true;
+    });
+
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 2 && c.prs == 1 && c.comments == 1 && c.label_links == 1)
+        .await;
+    h
+}
+
+/// A database that never syncs anything by itself.
+async fn offline(dir: &TempDir) -> GithubDb {
+    GithubDb::new_with_forge(
+        dir.path().join("db.sqlite"),
+        Arc::new(FakeGithub::new()),
+        0,
+        &[],
+    )
+    .await
+}
+
+fn read(dir: &Path, table: &str) -> String {
+    fs::read_to_string(dir.join(format!("{table}.jsonl"))).unwrap()
+}
+
+#[tokio::test]
+async fn dump_round_trip() {
+    let h = synced().await;
+    let export = TempDir::new().unwrap();
+    h.gh.export(export.path(), ExportOptions::default())
+        .await
+        .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let gh = offline(&dir).await;
+    let summary = gh.import_dump(export.path()).await.unwrap();
+    assert_eq!(
+        summary,
+        ImportSummary {
+            issues: 1,
+            pull_requests: 1,
+            comments: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(gh.progress().await.missing_comment_syncs, 0);
+
+    let again = TempDir::new().unwrap();
+    gh.export(again.path(), ExportOptions::default())
+        .await
+        .unwrap();
+    for table in [
+        "issues",
+        "pull_requests",
+        "comments",
+        "users",
+        "labels",
+        "label_links",
+        "assignments",
+        "review_requests",
+    ] {
+        assert_eq!(
+            read(export.path(), table),
+            read(again.path(), table),
+            "{table} changed"
+        );
+    }
+
+    // importing again changes nothing
+    let summary = gh.import_dump(export.path()).await.unwrap();
+    assert_eq!(summary.stale, 3);
+    assert_eq!(summary.issues + summary.pull_requests + summary.comments, 0);
+}
+
+#[tokio::test]
+async fn newer_data_is_kept() {
+    let fake = Arc::new(FakeGithub::new());
+    let number = fake.add_issue(REPO, "old title", "alice");
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.shared == 1).await;
+
+    let export = TempDir::new().unwrap();
+    h.gh.export(export.path(), ExportOptions::default())
+        .await
+        .unwrap();
+
+    fake.edit(REPO, number, |issue| issue.title = "new title".to_string());
+    let start = Instant::now();
+    while h.title(number).await.as_deref() != Some("new title") {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.gh.clone().update().await;
+    }
+
+    let summary = h.gh.import_dump(export.path()).await.unwrap();
+    assert_eq!(summary.stale, 1);
+    assert_eq!(h.title(number).await.as_deref(), Some("new title"));
+}
+
+#[tokio::test]
+async fn missing_dump() {
+    let dir = TempDir::new().unwrap();
+    let gh = offline(&dir).await;
+    let error = gh.import_dump(dir.path().join("nope")).await.unwrap_err();
+    assert!(error.to_string().contains("issues.jsonl"));
+}
+
+#[tokio::test]
+async fn gh_archive() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    fake.add_pr(REPO, "fix the ICE", "carol");
+
+    let repo: Repo = REPO.parse().unwrap();
+    let issues = fake
+        .list_issues(&repo, ListType::New, 1, None)
+        .await
+        .unwrap();
+    let issue = issues.items.iter().find(|i| i.number == ice).unwrap();
+    let comments = fake.list_comments(&repo, ice, None, 1, None).await.unwrap();
+    let prs = fake.list_prs(&repo, ListType::New, 1, None).await.unwrap();
+    let mut undated = json!(prs.items[0]);
+    undated["title"] = "rewritten".into();
+    undated["created_at"] = serde_json::Value::Null;
+    undated["updated_at"] = serde_json::Value::Null;
+
+    let event = |kind: &str, repo: &str, payload| {
+        json!({ "type": kind, "repo": { "name": repo }, "payload": payload }).to_string()
+    };
+    let lines = [
+        event(
+            "IssuesEvent",
+            REPO,
+            json!({ "action": "opened", "issue": issue }),
+        ),
+        event(
+            "IssueCommentEvent",
+            REPO,
+            json!({ "action": "created", "issue": issue, "comment": comments.items[0] }),
+        ),
+        event(
+            "PullRequestEvent",
+            REPO,
+            json!({ "action": "opened", "pull_request": prs.items[0] }),
+        ),
+        event(
+            "PullRequestEvent",
+            REPO,
+            json!({ "action": "edited", "pull_request": undated }),
+        ),
+        event("IssuesEvent", "rust-lang/cargo", json!({ "issue": issue })),
+        event("PushEvent", REPO, json!({ "size": 1 })),
+        event("IssuesEvent", REPO, json!({ "issue": "not an issue" })),
+        "{ truncated".to_string(),
+    ];
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("2020-01-01-0.json.gz");
+    let mut file = GzEncoder::new(fs::File::create(&path).unwrap(), Compression::default());
+    file.write_all(lines.join("\n").as_bytes()).unwrap();
+    file.finish().unwrap();
+
+    let gh = offline(&dir).await;
+    let summary = gh
+        .import_archive(&path, std::slice::from_ref(&repo))
+        .await
+        .unwrap();
+    assert_eq!(
+        summary,
+        ImportSummary {
+            issues: 1,
+            pull_requests: 1,
+            comments: 1,
+            // the comment event's issue was already imported, and the
+            // pull request event without timestamps isn't known to be newer
+            stale: 2,
+            invalid: 2,
+        }
+    );
+    assert_eq!(
+        gh.comment_thread(&repo, ice).await.unwrap()[0].text,
+        "can reproduce"
+    );
+
+    let prs = gh.find(&"is:pr".parse().unwrap(), 10).await;
+    assert_eq!(prs[0].title, "fix the ICE");
+
+    // the archive only has part of the comments
+    let queued = gh.queue(Some(Priority::Comments), 10).await;
+    assert_eq!(queued.len(), 2);
+}
+
+#[tokio::test]
+async fn gh_archive_deleted_authors() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    fake.add_pr(REPO, "fix the ICE", "carol");
+
+    let repo: Repo = REPO.parse().unwrap();
+    let issues = fake
+        .list_issues(&repo, ListType::New, 1, None)
+        .await
+        .unwrap();
+    let mut issue = json!(issues.items.iter().find(|i| i.number == ice).unwrap());
+    let comments = fake.list_comments(&repo, ice, None, 1, None).await.unwrap();
+    let mut comment = json!(comments.items[0]);
+    let prs = fake.list_prs(&repo, ListType::New, 1, None).await.unwrap();
+    let mut pr = json!(prs.items[0]);
+    for object in [&mut issue, &mut comment, &mut pr] {
+        object["user"] = serde_json::Value::Null;
+    }
+
+    let event = |kind: &str, payload| {
+        json!({ "type": kind, "repo": { "name": REPO }, "payload": payload }).to_string()
+    };
+    let lines = [
+        event("IssuesEvent", json!({ "action": "opened", "issue": issue })),
+        event(
+            "IssueCommentEvent",
+            json!({ "action": "created", "issue": issue, "comment": comment }),
+        ),
+        event(
+            "PullRequestEvent",
+            json!({ "action": "opened", "pull_request": pr }),
+        ),
+    ];
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("2020-01-01-0.json");
+    fs::write(&path, lines.join("\n")).unwrap();
+
+    let gh = offline(&dir).await;
+    let summary = gh.import_archive(&path, &[]).await.unwrap();
+    assert_eq!(
+        (summary.issues, summary.pull_requests, summary.comments),
+        (1, 1, 1)
+    );
+    assert_eq!(summary.invalid, 0);
+    let thread = gh.comment_thread(&repo, ice).await.unwrap();
+    assert_eq!(thread[0].author, GHOST_LOGIN);
+    let found = gh.find(&"author:ghost".parse().unwrap(), 10).await;
+    assert_eq!(found.len(), 2);
+}
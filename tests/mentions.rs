@@ -0,0 +1,143 @@
+//! Mentions extracted from descriptions and comments.
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, REPO};
+use github_db::{
+    Repo,
+    forge::fake::FakeGithub,
+    rust_query::aggregate,
+    schema::{Mention, Team},
+};
+
+const DESCRIPTION: &str = "\
+cc @bob and @rust-lang/compiler, thanks @bob!
+
+```rust
+// @carol isn't pinged in code
+```
+> @dave wrote this in a quote
+
+mail eve@example.com, or `@frank`
+
+    @gina isn't pinged in indented code
+";
+
+async fn names(h: &Harness, names: &[&str]) -> Vec<usize> {
+    let mut counts = Vec::new();
+    for name in names {
+        counts.push(h.gh.mentions_of(name).await.len());
+    }
+    counts
+}
+
+/// How many mentions are linked to a user.
+async fn linked(h: &Harness) -> i64 {
+    h.gh.transaction(|txn| {
+        txn.query_one(aggregate(|rows| {
+            let mention = rows.join(Mention);
+            rows.filter(mention.user.is_some());
+            rows.count_distinct(mention)
+        }))
+    })
+    .await
+}
+
+#[tokio::test]
+async fn description_mentions() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.body = Some(DESCRIPTION.to_string())
+    });
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 1).await;
+
+    assert_eq!(
+        names(
+            &h,
+            &["bob", "rust-lang/compiler", "rust-lang", "carol", "dave"]
+        )
+        .await,
+        [1, 1, 0, 0, 0]
+    );
+    assert_eq!(
+        names(&h, &["eve", "example", "example.com", "frank", "gina"]).await,
+        [0, 0, 0, 0, 0]
+    );
+
+    let bob = h.gh.mentions_of("bob").await;
+    assert_eq!((bob[0].number, bob[0].comment_id), (ice, None));
+    assert_eq!(bob[0].author, "alice");
+
+    let teams =
+        h.gh.transaction(|txn| {
+            txn.query(|rows| {
+                let team = rows.join(Team);
+                rows.into_vec((&team.organization, &team.slug))
+            })
+        })
+        .await;
+    assert_eq!(teams, [("rust-lang".to_string(), "compiler".to_string())]);
+}
+
+#[tokio::test]
+async fn comment_edits_update_mentions() {
+    let fake = Arc::new(FakeGithub::new());
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let comment = fake.add_comment(REPO, ice, "bob", "@alice can you bisect?");
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.comments == 1).await;
+
+    let mentions = h.gh.mentions_of("alice").await;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].comment_id, Some(comment));
+    assert_eq!(mentions[0].author, "bob");
+
+    fake.edit_comment(REPO, comment, "never mind, @carol found it");
+    let repo: Repo = REPO.parse().unwrap();
+    let start = Instant::now();
+    while h.gh.comment_thread(&repo, ice).await.unwrap()[0].text != "never mind, @carol found it" {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.gh.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(names(&h, &["alice", "carol"]).await, [0, 1]);
+}
+
+#[tokio::test]
+async fn users_are_linked_once_they_show_up() {
+    let fake = Arc::new(FakeGithub::new());
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "alice", "@bob do you know what's going on?");
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.comments == 1).await;
+
+    assert_eq!(linked(&h).await, 0);
+
+    fake.add_comment(REPO, ice, "bob", "no idea");
+    h.sync_until(|c| c.comments == 2).await;
+    assert_eq!(linked(&h).await, 1);
+}
+
+#[tokio::test]
+async fn logins_are_case_insensitive() {
+    let fake = Arc::new(FakeGithub::new());
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "alice", "@RALFJUNG do you know what's going on?");
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.comments == 1).await;
+    assert_eq!(names(&h, &["ralfjung", "RalfJung"]).await, [1, 1]);
+    assert_eq!(linked(&h).await, 0);
+
+    // links the earlier mention once the user shows up, and finds `alice` right away
+    fake.add_comment(REPO, ice, "RalfJung", "no idea, cc @Alice");
+    h.sync_until(|c| c.comments == 2).await;
+    assert_eq!(names(&h, &["alice"]).await, [1]);
+    assert_eq!(linked(&h).await, 2);
+}
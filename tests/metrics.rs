@@ -0,0 +1,67 @@
+//! The OpenMetrics output.
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{Harness, populate};
+use github_db::forge::fake::FakeGithub;
+
+#[tokio::test]
+async fn counters_are_rendered() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 3, 2);
+    fake.fail_next(1);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.shared == 5 && c.prs == 2 && c.comments == 3)
+        .await;
+
+    let metrics = h.gh.metrics().await;
+    assert!(metrics.ends_with("# EOF\n"));
+    assert!(metrics.contains("github_db_request_errors_total 1\n"));
+    assert!(metrics.contains("github_db_requests_handled_total{request=\"OldPr\"}"));
+    assert!(metrics.contains("github_db_processed_total{kind=\"comment\",status=\"new\"} 3\n"));
+    assert!(metrics.contains("github_db_queue_depth{category=\"Comments\"}"));
+    assert!(metrics.contains("github_db_rows{table=\"comment\"}"));
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn metrics_are_served() {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    let h = Harness::new(Arc::new(FakeGithub::new())).await;
+
+    // find a free port
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(h.gh.clone().serve_metrics(addr));
+
+    let get = async |path: &str| {
+        let mut stream = loop {
+            match TcpStream::connect(addr).await {
+                Ok(s) => break s,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+
+    let response = get("/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("# EOF\n"));
+    assert!(get("/").await.starts_with("HTTP/1.1 404"));
+}
@@ -0,0 +1,190 @@
+//! Opening databases written by older versions of the schema.
+
+mod common;
+
+use std::{path::Path, sync::Arc};
+
+use common::{Harness, REPO};
+use github_db::{
+    GithubDb, Repo,
+    enums::{AuthorAssociation, MergeableState, StateReason, TransitionKind},
+    forge::fake::FakeGithub,
+};
+use octocrab::models::{issues::IssueStateReason, pulls};
+use rusqlite::Connection;
+use tempfile::TempDir;
+
+/// Turn the tables back into schema version 3, which stored octocrab's discriminants
+/// for `state_reason` and `mergeable_state`, and variant names for `author_association`,
+/// and had no `state` column, `StateTransition` table, profile or login columns of `User`
+/// association and urls of `Comment`, `Mention` and `Team` tables, discussions, releases,
+/// tags, repository metadata or collaborators and team members.
+fn downgrade_to_v3(path: &Path) {
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(&format!(
+        "UPDATE issue_pull_request_shared SET
+            state_reason = CASE WHEN state_reason IS NULL THEN NULL ELSE '{}' END,
+            author_association = 'FirstTimeContributor';
+        UPDATE pull_request SET mergeable_state = '{}';",
+        IssueStateReason::NotPlanned as i64,
+        pulls::MergeableState::Clean as i64,
+    ))
+    .unwrap();
+
+    conn.pragma_update(None, "foreign_keys", false).unwrap();
+    conn.execute_batch(
+        "DROP TABLE state_transition;
+        DROP TABLE release;
+        DROP TABLE tag;
+        DROP TABLE repo_metadata;
+        DROP TABLE repo_topic;
+        DROP TABLE repo_snapshot;
+        DROP TABLE collaborator;
+        DROP TABLE repo_team;
+        DROP TABLE team_member;
+        DROP TABLE discussion_comment;
+        DROP TABLE discussion;
+        DROP TABLE discussion_category;
+        DROP TABLE mention;
+        DROP TABLE team;
+        DROP INDEX user_index_0;
+        ALTER TABLE user DROP COLUMN login;
+        ALTER TABLE user DROP COLUMN kind;
+        ALTER TABLE user DROP COLUMN company;
+        ALTER TABLE user DROP COLUMN location;
+        ALTER TABLE user DROP COLUMN account_created_timestamp;
+        ALTER TABLE user DROP COLUMN profile_synced_timestamp;
+        ALTER TABLE comment DROP COLUMN author_association;
+        ALTER TABLE comment DROP COLUMN html_url;
+        ALTER TABLE comment DROP COLUMN node_id;",
+    )
+    .unwrap();
+    for table in ["issue_pull_request_shared", "pull_request"] {
+        let sql: String = conn
+            .query_row(
+                "SELECT sql FROM sqlite_schema WHERE name = ?1",
+                [table],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let sql = sql
+            .replacen(&format!("\"{table}\""), &format!("\"{table}_v3\""), 1)
+            .replace("\"state_reason\" text", "\"state_reason\" integer")
+            .replace("\"mergeable_state\" text", "\"mergeable_state\" integer")
+            .replace("\"state\" text NOT NULL, ", "");
+        let columns: Vec<String> = conn
+            .prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|column| column != "state")
+            .collect();
+        let columns = columns.join(", ");
+        conn.execute_batch(&format!(
+            "{sql};
+            INSERT INTO {table}_v3 ({columns}) SELECT {columns} FROM {table};
+            DROP TABLE {table};
+            ALTER TABLE {table}_v3 RENAME TO {table};"
+        ))
+        .unwrap();
+    }
+    conn.pragma_update(None, "user_version", 3).unwrap();
+}
+
+/// Sync `fake`, and open a copy of the database that was downgraded to version 3.
+async fn migrated(fake: FakeGithub, done: impl Fn(common::Counts) -> bool) -> (TempDir, GithubDb) {
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(done).await;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("db.sqlite");
+    h.gh.backup_to(&path).await.unwrap();
+    downgrade_to_v3(&path);
+
+    let gh = GithubDb::new_with_forge(&path, Arc::new(FakeGithub::new()), 0, &[]).await;
+    (dir, gh)
+}
+
+#[tokio::test]
+async fn v3_enums_become_text() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| issue.closed = true);
+    fake.add_comment(REPO, ice, "bob", "can reproduce, cc @carol");
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    let (_dir, gh) = migrated(fake, |c| c.shared == 2 && c.prs == 1 && c.comments == 1).await;
+
+    let issues = gh.find(&"is:issue".parse().unwrap(), 10).await;
+    assert_eq!(issues[0].state_reason, Some(StateReason::NotPlanned));
+    assert_eq!(
+        issues[0].author_association,
+        AuthorAssociation::FirstTimeContributor
+    );
+
+    let repo: Repo = REPO.parse().unwrap();
+    let pr = gh.pull_request(&repo, fix).await.unwrap();
+    assert_eq!(pr.mergeable_state, MergeableState::Clean);
+    assert_eq!(pr.item.state_reason, None);
+
+    let thread = gh.comment_thread(&repo, ice).await.unwrap();
+    assert_eq!(thread[0].author_association, AuthorAssociation::Unknown);
+    assert_eq!(thread[0].html_url, None);
+
+    let mentions = gh.mentions_of("carol").await;
+    assert_eq!(mentions.len(), 1);
+    assert_eq!(mentions[0].comment_id, Some(thread[0].id));
+    // carol was already in the database, so the mention is linked to that user
+    let linked = gh
+        .transaction(|txn| {
+            txn.query(|rows| {
+                let mention = rows.join(github_db::schema::Mention);
+                let user = rows.filter_some(&mention.user);
+                rows.into_vec(&user.name)
+            })
+        })
+        .await;
+    assert_eq!(linked, ["carol"]);
+}
+
+#[tokio::test]
+async fn v4_state_and_transitions_are_filled() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.closed = true;
+        issue.closed_by = Some("bob".to_string());
+    });
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    fake.edit(REPO, fix, |pr| {
+        pr.merged = true;
+        pr.closed_by = Some("alice".to_string());
+    });
+    fake.add_issue(REPO, "still open", "dave");
+    let (_dir, gh) = migrated(fake, |c| c.shared == 3 && c.prs == 1).await;
+
+    let open = gh.find(&"is:open".parse().unwrap(), 10).await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].title, "still open");
+
+    let repo: Repo = REPO.parse().unwrap();
+    let history = gh.state_history(&repo, ice).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].kind, TransitionKind::Closed);
+    assert_eq!(history[0].actor.as_deref(), Some("bob"));
+
+    let kinds: Vec<_> = gh
+        .state_history(&repo, fix)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.kind, t.actor))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (TransitionKind::Closed, Some("alice".to_string())),
+            (TransitionKind::Merged, Some("alice".to_string())),
+        ]
+    );
+}
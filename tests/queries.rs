@@ -0,0 +1,150 @@
+//! The typed helpers in `github_db::queries`.
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, REPO};
+use github_db::{
+    ItemKind, Repo,
+    enums::{AuthorAssociation, MergeableState, TransitionKind},
+    forge::fake::FakeGithub,
+};
+
+async fn synced() -> Harness {
+    let fake = FakeGithub::new();
+    fake.set_association("bob", "MEMBER");
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| issue.labels = vec!["I-ICE".to_string()]);
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    fake.add_comment(REPO, ice, "alice", "bisected it");
+
+    let fix = fake.add_pr(REPO, "fix the ICE", "carol");
+    fake.edit(REPO, fix, |pr| {
+        pr.labels = vec!["I-ICE".to_string()];
+        pr.requested_reviewers = vec!["bob".to_string(), "alice".to_string()];
+    });
+    let old = fake.add_pr(REPO, "old attempt", "carol");
+    fake.edit(REPO, old, |pr| {
+        pr.requested_reviewers = vec!["bob".to_string()];
+        pr.closed = true;
+    });
+    let other = fake.add_pr(REPO, "unrelated", "dave");
+    fake.edit(REPO, other, |pr| pr.merged = true);
+
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 4 && c.prs == 3 && c.comments == 2 && c.label_links == 2)
+        .await;
+    h
+}
+
+#[tokio::test]
+async fn pull_requests() {
+    let h = synced().await;
+
+    let open = h.gh.open_prs_by("carol").await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].item.title, "fix the ICE");
+    assert_eq!(open[0].item.kind, ItemKind::PullRequest);
+    assert!(!open[0].draft);
+    assert_eq!(open[0].requested_reviewers, ["alice", "bob"]);
+
+    let awaiting = h.gh.prs_awaiting_review_from("bob").await;
+    assert_eq!(awaiting.len(), 1);
+    assert_eq!(awaiting[0].item.number, 2);
+    assert!(h.gh.prs_awaiting_review_from("dave").await.is_empty());
+
+    let repo: Repo = REPO.parse().unwrap();
+    let merged = h.gh.pull_request(&repo, 4).await.unwrap();
+    assert!(merged.merged_at.is_some());
+    assert!(!merged.item.open);
+    assert_eq!(merged.mergeable_state, MergeableState::Unknown);
+    assert_eq!(
+        merged.item.author_association,
+        AuthorAssociation::Contributor
+    );
+    assert!(h.gh.pull_request(&repo, 1).await.is_none());
+    let cargo: Repo = "rust-lang/cargo".parse().unwrap();
+    assert!(h.gh.pull_request(&cargo, 4).await.is_none());
+}
+
+#[tokio::test]
+async fn issues_and_comments() {
+    let h = synced().await;
+
+    let ices = h.gh.issues_with_label("I-ICE").await;
+    assert_eq!(ices.len(), 1);
+    assert_eq!(ices[0].number, 1);
+    assert_eq!(ices[0].kind, ItemKind::Issue);
+    assert!(h.gh.issues_with_label("T-compiler").await.is_empty());
+
+    let repo: Repo = REPO.parse().unwrap();
+    let thread = h.gh.comment_thread(&repo, 1).await.unwrap();
+    let url = format!(
+        "https://github.com/{REPO}/issues/1#issuecomment-{}",
+        thread[0].id
+    );
+    assert_eq!(thread[0].html_url, Some(url));
+    let thread: Vec<_> = thread
+        .iter()
+        .map(|c| (c.author.as_str(), c.author_association, c.text.as_str()))
+        .collect();
+    assert_eq!(
+        thread,
+        [
+            ("bob", AuthorAssociation::Member, "can reproduce"),
+            ("alice", AuthorAssociation::Contributor, "bisected it"),
+        ]
+    );
+    assert_eq!(h.gh.comment_thread(&repo, 2).await.unwrap().len(), 0);
+    assert!(h.gh.comment_thread(&repo, 99).await.is_none());
+}
+
+#[tokio::test]
+async fn state_history() {
+    let fake = Arc::new(FakeGithub::new());
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.closed = true;
+        issue.closed_by = Some("bob".to_string());
+    });
+    let h = Harness::new(fake.clone()).await;
+    let repo: Repo = REPO.parse().unwrap();
+
+    let history_len = async |len| {
+        let start = Instant::now();
+        loop {
+            let history = h.gh.state_history(&repo, ice).await.unwrap_or_default();
+            if history.len() == len {
+                return history;
+            }
+            assert!(start.elapsed() < Duration::from_secs(20));
+            h.gh.clone().update().await;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    history_len(1).await;
+
+    fake.edit(REPO, ice, |issue| issue.closed = false);
+    history_len(2).await;
+    assert_eq!(h.gh.find(&"is:open".parse().unwrap(), 10).await.len(), 1);
+    fake.edit(REPO, ice, |issue| issue.closed = true);
+    let history: Vec<_> = history_len(3)
+        .await
+        .into_iter()
+        .map(|t| (t.kind, t.actor))
+        .collect();
+    assert_eq!(
+        history,
+        [
+            (TransitionKind::Closed, Some("bob".to_string())),
+            (TransitionKind::Reopened, None),
+            (TransitionKind::Closed, Some("bob".to_string())),
+        ]
+    );
+    assert_eq!(h.gh.find(&"is:closed".parse().unwrap(), 10).await.len(), 1);
+    assert!(h.gh.state_history(&repo, 99).await.is_none());
+}
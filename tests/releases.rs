@@ -0,0 +1,161 @@
+//! Releases and tags, which are listed on a slow schedule.
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{Harness, REPO};
+use github_db::{Repo, forge::fake::FakeGithub};
+
+#[tokio::test]
+async fn releases_and_tags() {
+    let fake = FakeGithub::new().with_page_size(2);
+    for i in 0..4 {
+        fake.add_tag(REPO, &format!("0.{i}.0"), &format!("{i:040x}"));
+    }
+    fake.add_release(REPO, "1.0.0", &format!("{:040x}", 10), "alice");
+    let beta = fake.add_release(REPO, "1.1.0-beta.1", &format!("{:040x}", 11), "bob");
+    fake.edit_release(REPO, beta, |release| {
+        release.prerelease = true;
+        release.body = Some("try it out".to_string());
+    });
+    let draft = fake.add_release(REPO, "1.1.0", &format!("{:040x}", 12), "alice");
+    fake.edit_release(REPO, draft, |release| release.draft = true);
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    let tags = async || {
+        h.gh.transaction(|txn| {
+            txn.query(|rows| {
+                let tag = rows.join(github_db::schema::Tag);
+                rows.into_vec(&tag.name)
+            })
+        })
+        .await
+    };
+    // releases are linked to their tag once the tags are listed, which takes 4 pages
+    let (releases, _) = h
+        .sync_until_with(
+            async || (h.gh.releases(&repo).await, tags().await),
+            |(r, tags)| r.len() == 3 && tags.len() == 7,
+        )
+        .await;
+
+    let names: Vec<_> = releases.iter().map(|r| r.tag_name.as_str()).collect();
+    assert_eq!(names, ["1.1.0", "1.1.0-beta.1", "1.0.0"]);
+    assert!(releases[0].draft);
+    assert!(releases[1].prerelease);
+    assert_eq!(releases[1].author, "bob");
+    assert!(releases[1].published_at.is_some());
+    assert_eq!(releases[2].name.as_deref(), Some("1.0.0"));
+    assert_eq!(releases[2].commit_sha, Some(format!("{:040x}", 10)));
+    assert_eq!(
+        releases[2].html_url,
+        format!("https://github.com/{REPO}/releases/tag/1.0.0")
+    );
+
+    // the tags without a release are on later pages
+    let mut tags = tags().await;
+    tags.sort();
+    assert_eq!(
+        tags,
+        [
+            "0.0.0",
+            "0.1.0",
+            "0.2.0",
+            "0.3.0",
+            "1.0.0",
+            "1.1.0",
+            "1.1.0-beta.1"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn moved_tags_are_updated() {
+    let fake = Arc::new(FakeGithub::new());
+    fake.add_release(REPO, "1.0.0", &format!("{:040x}", 1), "alice");
+    let h = Harness::with_config(fake.clone(), |gh| {
+        gh.with_release_refresh_interval(Duration::from_millis(200))
+    })
+    .await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(
+        async || h.gh.releases(&repo).await,
+        |r| r.len() == 1 && r[0].commit_sha.is_some(),
+    )
+    .await;
+
+    fake.add_tag(REPO, "1.0.0", &format!("{:040x}", 2));
+    let releases = h
+        .sync_until_with(
+            async || h.gh.releases(&repo).await,
+            |r| r[0].commit_sha.as_deref() == Some(&format!("{:040x}", 2)),
+        )
+        .await;
+    assert_eq!(releases.len(), 1);
+}
+
+#[tokio::test]
+async fn tags_on_later_pages_are_picked_up() {
+    let fake = Arc::new(FakeGithub::new().with_page_size(2));
+    for i in 0..4 {
+        fake.add_tag(REPO, &format!("1.{i}.0"), &format!("{i:040x}"));
+    }
+    let h = Harness::with_config(fake.clone(), |gh| {
+        gh.with_release_refresh_interval(Duration::from_millis(200))
+    })
+    .await;
+    let tags = async || {
+        h.gh.transaction(|txn| {
+            txn.query(|rows| {
+                let tag = rows.join(github_db::schema::Tag);
+                rows.into_vec((&tag.name, &tag.commit_sha))
+            })
+        })
+        .await
+    };
+    h.sync_until_with(tags, |tags| tags.len() == 4).await;
+
+    // the first page is unchanged, the new tag and the moved one are on the last page
+    fake.add_tag(REPO, "0.9.0", &format!("{:040x}", 9));
+    fake.add_tag(REPO, "1.0.0", &format!("{:040x}", 10));
+    let tags = h
+        .sync_until_with(tags, |tags| {
+            tags.len() == 5
+                && tags
+                    .iter()
+                    .any(|(name, sha)| name == "1.0.0" && sha.ends_with('a'))
+        })
+        .await;
+    assert!(tags.iter().any(|(name, _)| name == "0.9.0"));
+}
+
+#[tokio::test]
+async fn first_release_with_pr() {
+    let fake = FakeGithub::new();
+    let tagged = fake.add_pr(REPO, "fix the ICE", "alice");
+    let later = fake.add_pr(REPO, "faster borrowck", "bob");
+    let open = fake.add_pr(REPO, "new lint", "carol");
+    fake.edit(REPO, tagged, |pr| pr.merged = true);
+    // the fake's merge commits are the pull request's number in hex
+    fake.add_release(REPO, "1.0.0", &format!("{tagged:040x}"), "alice");
+    fake.edit(REPO, later, |pr| pr.merged = true);
+    fake.add_release(REPO, "1.1.0", &format!("{:040x}", 100), "alice");
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.prs == 3).await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(
+        async || h.gh.releases(&repo).await,
+        |r| r.len() == 2 && r.iter().all(|r| r.commit_sha.is_some()),
+    )
+    .await;
+
+    let release = async |number| {
+        h.gh.first_release_with(&repo, number)
+            .await
+            .map(|release| release.tag_name)
+    };
+    assert_eq!(release(tagged).await.as_deref(), Some("1.0.0"));
+    assert_eq!(release(later).await.as_deref(), Some("1.1.0"));
+    assert_eq!(release(open).await, None);
+}
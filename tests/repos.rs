@@ -0,0 +1,85 @@
+//! Repository metadata, and the snapshots of its counts.
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{Harness, REPO};
+use github_db::{Repo, forge::fake::FakeGithub};
+
+#[tokio::test]
+async fn metadata() {
+    let fake = FakeGithub::new();
+    fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let fixed = fake.add_issue(REPO, "typo in the book", "bob");
+    fake.edit(REPO, fixed, |issue| issue.closed = true);
+    fake.add_pr(REPO, "faster borrowck", "carol");
+    fake.edit_repo(REPO, |repo| {
+        repo.description = Some("Empowering everyone".to_string());
+        repo.default_branch = "master".to_string();
+        repo.topics = vec!["rust".to_string(), "compiler".to_string()];
+        repo.stars = 100;
+        repo.forks = 10;
+        repo.watchers = 5;
+    });
+    let h = Harness::new(Arc::new(fake)).await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(
+        async || h.gh.repo_history(&repo).await,
+        |history| !history.is_empty(),
+    )
+    .await;
+
+    let summary = h.gh.repository(&repo).await.unwrap();
+    assert_eq!(summary.description.as_deref(), Some("Empowering everyone"));
+    assert_eq!(summary.default_branch.as_deref(), Some("master"));
+    assert_eq!(summary.topics, ["compiler", "rust"]);
+    assert_eq!(summary.visibility.as_deref(), Some("public"));
+    assert!(!summary.archived);
+    let counts = summary.counts;
+    assert_eq!((counts.stars, counts.forks, counts.watchers), (100, 10, 5));
+    // like on GitHub, open pull requests count as open issues
+    assert_eq!(counts.open_issues, 2);
+
+    let unknown: Repo = "rust-lang/cargo".parse().unwrap();
+    assert!(h.gh.repository(&unknown).await.is_none());
+    assert!(h.gh.repo_history(&unknown).await.is_empty());
+}
+
+#[tokio::test]
+async fn counts_are_a_time_series() {
+    let fake = Arc::new(FakeGithub::new());
+    fake.edit_repo(REPO, |repo| {
+        repo.topics = vec!["rust".to_string(), "compiler".to_string()];
+        repo.stars = 100;
+    });
+    let h = Harness::with_config(fake.clone(), |gh| {
+        gh.with_repo_refresh_interval(Duration::from_millis(200))
+    })
+    .await;
+    let repo: Repo = REPO.parse().unwrap();
+    h.sync_until_with(
+        async || h.gh.repo_history(&repo).await,
+        |history| !history.is_empty(),
+    )
+    .await;
+
+    fake.edit_repo(REPO, |repo| {
+        repo.topics = vec!["rust".to_string()];
+        repo.stars = 150;
+        repo.archived = true;
+    });
+    let history = h
+        .sync_until_with(
+            async || h.gh.repo_history(&repo).await,
+            |history| history.last().is_some_and(|last| last.stars == 150),
+        )
+        .await;
+    assert_eq!(history[0].stars, 100);
+    assert!(history.windows(2).all(|w| w[0].at <= w[1].at));
+
+    let summary = h.gh.repository(&repo).await.unwrap();
+    assert_eq!(summary.topics, ["rust"]);
+    assert!(summary.archived);
+    assert_eq!(summary.counts.stars, 150);
+}
@@ -0,0 +1,158 @@
+//! Full-text search over synced data.
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, REPO};
+use github_db::{GithubDb, ItemKind, SearchFilters, forge::fake::FakeGithub};
+
+async fn synced() -> (Arc<FakeGithub>, Harness) {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.edit(REPO, ice, |issue| {
+        issue.body = Some("the compiler panicked".to_string());
+    });
+    let diag = fake.add_issue(REPO, "confusing diagnostic", "bob");
+    fake.add_comment(REPO, diag, "carol", "this is caused by borrowck too");
+    let pr = fake.add_pr(REPO, "fix borrowck ICE", "dave");
+    fake.edit(REPO, pr, |pr| pr.closed = true);
+
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.shared == 3 && c.prs == 1 && c.comments == 1)
+        .await;
+    (fake, h)
+}
+
+#[tokio::test]
+async fn titles_rank_above_comments() {
+    let (_, h) = synced().await;
+
+    let hits =
+        h.gh.search("borrowck", SearchFilters::default())
+            .await
+            .unwrap();
+    assert_eq!(hits.len(), 3);
+    assert!(hits[..2].iter().all(|h| h.comment_id.is_none()));
+    assert_eq!(hits[2].number, 2);
+    assert!(hits[2].comment_id.is_some());
+    assert_eq!(hits[2].snippet, "this is caused by [borrowck] too");
+
+    let hits =
+        h.gh.search("panicked", SearchFilters::default())
+            .await
+            .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].title, "ICE in borrowck");
+}
+
+#[tokio::test]
+async fn filters_apply() {
+    let (_, h) = synced().await;
+
+    let search = async |filters| h.gh.search("borrowck", filters).await.unwrap();
+
+    let prs = search(SearchFilters {
+        kind: Some(ItemKind::PullRequest),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(prs.len(), 1);
+    assert!(!prs[0].open);
+
+    let open = search(SearchFilters {
+        open: Some(true),
+        comments: false,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].kind, ItemKind::Issue);
+
+    let other_repo = search(SearchFilters {
+        repo: Some("rust-lang/cargo".parse().unwrap()),
+        ..Default::default()
+    })
+    .await;
+    assert!(other_repo.is_empty());
+
+    let limited = search(SearchFilters {
+        limit: 1,
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(limited.len(), 1);
+}
+
+#[tokio::test]
+async fn edits_update_the_index() {
+    let (fake, h) = synced().await;
+
+    fake.edit(REPO, 1, |issue| issue.title = "ICE in typeck".to_string());
+    h.sync_until(|_| true).await;
+    let start = Instant::now();
+    while h
+        .gh
+        .search("typeck", SearchFilters::default())
+        .await
+        .unwrap()
+        .is_empty()
+    {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.sync_until(|_| true).await;
+    }
+
+    let hits =
+        h.gh.search("borrowck", SearchFilters::default())
+            .await
+            .unwrap();
+    assert!(hits.iter().all(|h| h.number != 1));
+}
+
+#[tokio::test]
+async fn invalid_queries_are_errors() {
+    let (_, h) = synced().await;
+    assert!(
+        h.gh.search("\"unbalanced", SearchFilters::default())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn index_is_built_for_existing_databases() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("db.sqlite");
+
+    let fake = Arc::new(FakeGithub::new());
+    fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let gh = Arc::new(
+        GithubDb::new_with_forge(&path, fake.clone(), 10_000_000, &[REPO])
+            .await
+            .with_stats_max_age(Duration::ZERO),
+    );
+    while gh.stats().await.shared == 0 {
+        gh.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // let the requests that are still running finish, they'd race the reopened database
+    gh.clone().run(async {}).await;
+    drop(gh);
+
+    // a database from before the search index existed
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch("DROP TABLE search")
+        .unwrap();
+
+    let gh = GithubDb::new_with_forge(&path, fake, 10_000_000, &[REPO]).await;
+    let hits = gh
+        .search("borrowck", SearchFilters::default())
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+}
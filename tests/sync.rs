@@ -0,0 +1,214 @@
+//! Full sync cycles against a [`FakeGithub`].
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{Harness, REPO, populate};
+use github_db::{Event, Listing, forge::fake::FakeGithub};
+
+#[tokio::test]
+async fn initial_sync_walks_all_pages() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 23, 12);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+
+    let counts = h
+        .sync_until(|c| c.shared == 35 && c.prs == 12 && c.comments == 23)
+        .await;
+    assert_eq!(counts.label_links, 0);
+
+    // the old listings keep walking the pages until they reach the last one
+    let called = |call: &str| fake.calls().iter().any(|c| c == call);
+    h.sync_until(|_| {
+        called("pulls rust-lang/rust old page 3") && called("issues rust-lang/rust old page 7")
+    })
+    .await;
+    assert!(!called("pulls rust-lang/rust old page 4"));
+}
+
+#[tokio::test]
+async fn edits_are_picked_up() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 10, 10);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.shared == 20 && c.comments == 10).await;
+
+    let mut events = h.gh.subscribe();
+
+    fake.edit(REPO, 3, |issue| {
+        issue.title = "renamed".to_string();
+        issue.labels.push("T-compiler".to_string());
+    });
+    fake.add_comment(REPO, 4, "dave", "another comment");
+    let pr = fake.add_pr(REPO, "new pr", "erin");
+
+    h.sync_until(|c| c.shared == 21 && c.comments == 11 && c.label_links == 1)
+        .await;
+    assert_eq!(h.title(3).await.as_deref(), Some("renamed"));
+
+    let expected: [&dyn Fn(&Event) -> bool; 4] = [
+        &|e| {
+            matches!(e, Event::IssueUpdated { number: 3, changed_fields, .. }
+                if changed_fields.contains(&"title"))
+        },
+        &|e| matches!(e, Event::LabelAdded { number: 3, label, .. } if label == "T-compiler"),
+        &|e| {
+            matches!(
+                e,
+                Event::CommentAdded {
+                    issue_number: 4,
+                    ..
+                }
+            )
+        },
+        &|e| matches!(e, Event::PrCreated { number, .. } if *number == pr),
+    ];
+
+    let mut seen = Vec::new();
+    while !expected.iter().all(|f| seen.iter().any(f)) {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .unwrap_or_else(|_| panic!("missing events, got {seen:?}"))
+            .unwrap();
+        seen.push(event);
+    }
+}
+
+#[tokio::test]
+async fn labels_are_removed() {
+    let fake = FakeGithub::new();
+    let number = fake.add_issue(REPO, "labeled", "alice");
+    fake.edit(REPO, number, |issue| {
+        issue.labels = vec!["A-diagnostics".to_string(), "C-bug".to_string()];
+    });
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.label_links == 2).await;
+
+    fake.edit(REPO, number, |issue| {
+        issue.labels.pop();
+    });
+    h.sync_until(|c| c.label_links == 1).await;
+}
+
+#[tokio::test]
+async fn failed_requests_are_not_fatal() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 8, 8);
+    fake.fail_next(3);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+
+    h.sync_until(|c| c.shared == 16 && c.prs == 8).await;
+    assert!(fake.calls().len() > 3);
+}
+
+#[tokio::test]
+async fn run_shuts_down() {
+    let fake = FakeGithub::new();
+    populate(&fake, 3, 3);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+
+    let summary =
+        h.gh.clone()
+            .run(tokio::time::sleep(Duration::from_millis(500)))
+            .await;
+
+    assert!(summary.ticks >= 1);
+    assert_eq!(summary.requests_aborted, 0);
+    assert_eq!(summary.requests_panicked, 0);
+    assert!(summary.requests_finished > 0);
+}
+
+#[tokio::test]
+async fn stats_are_cached() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 4, 3);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    h.sync_until(|c| c.shared == 7 && c.prs == 3 && c.comments == 4)
+        .await;
+
+    let stats = h.gh.stats().await;
+    assert_eq!(stats.queue.len(), 3);
+
+    // nothing is recomputed within the cache period
+    fake.add_issue(REPO, "late", "alice");
+    h.sync_until(|c| c.shared == 8).await;
+    let cached = h.gh.stats().await;
+    assert_eq!(cached.computed_at, stats.computed_at);
+    assert_eq!(cached.shared, stats.shared);
+}
+
+#[tokio::test]
+async fn stats_count_entities() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 4, 3);
+    let fake = Arc::new(fake);
+    let h = Harness::with_config(fake.clone(), |gh| gh.with_stats_max_age(Duration::ZERO)).await;
+    // nothing was measured before the first requests
+    assert_eq!(h.gh.stats().await.requests_per_hour, None);
+    h.sync_until(|c| c.shared == 7 && c.prs == 3 && c.comments == 4)
+        .await;
+
+    let stats = h.gh.stats().await;
+    assert!(stats.requests_per_hour.is_some());
+    assert_eq!(stats.shared, 7);
+    assert_eq!(stats.prs, 3);
+    assert_eq!(stats.comments, 4);
+    // alice, bob and carol
+    assert_eq!(stats.users, 3);
+    assert!(stats.tokens.is_empty());
+}
+
+#[tokio::test]
+async fn indexing_progress_is_tracked() {
+    let fake = FakeGithub::new().with_page_size(5);
+    populate(&fake, 8, 12);
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+
+    // the first page reports how many pages there are
+    let progress = loop {
+        h.sync_until(|_| true).await;
+        let progress = h.gh.progress().await;
+        // pulls, issues and discussions
+        if progress.listings.len() == 3 {
+            break progress;
+        }
+    };
+    let pulls = progress
+        .listings
+        .iter()
+        .find(|l| l.listing == Listing::Pulls)
+        .unwrap();
+    assert_eq!(pulls.total_pages, Some(3));
+
+    let start = Instant::now();
+    loop {
+        h.sync_until(|_| true).await;
+        let progress = h.gh.progress().await;
+        if progress.listings.iter().all(|l| l.passes > 0) && progress.missing_comment_syncs == 0 {
+            assert_eq!(progress.remaining_requests, 0);
+            let issues = progress
+                .listings
+                .iter()
+                .find(|l| l.listing == Listing::Issues)
+                .unwrap();
+            // the issue listing includes the pull requests
+            assert_eq!(issues.total_pages, Some(4));
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(20),
+            "indexing didn't finish: {progress:?}"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! User profiles, which are fetched after the user shows up in a listing.
+
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use common::{Harness, REPO};
+use github_db::{
+    GHOST_ID, GHOST_LOGIN, Repo,
+    enums::UserKind,
+    forge::{
+        Forge, ForgeError, ForgePage, ListType, async_trait,
+        fake::{FakeGithub, FakeProfile},
+    },
+};
+use octocrab::models::{
+    UserProfile,
+    issues::{Comment, Issue},
+    pulls::PullRequest,
+};
+
+/// Sync until the `shared` issues and pull requests are in, and every user has a profile.
+async fn sync_profiles(h: &Harness, shared: i64) {
+    h.sync_until(|c| c.shared == shared).await;
+    let start = Instant::now();
+    while h.gh.progress().await.missing_profiles > 0 {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.gh.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn profiles_are_fetched() {
+    let fake = FakeGithub::new();
+    fake.set_profile(
+        "alice",
+        FakeProfile {
+            name: Some("Alice Liddell".to_string()),
+            company: Some("Wonderland".to_string()),
+            location: Some("Oxford".to_string()),
+            bot: false,
+        },
+    );
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.add_comment(REPO, ice, "bob", "can reproduce");
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    sync_profiles(&h, 1).await;
+
+    let alice = h.gh.user("alice").await.unwrap();
+    assert_eq!(alice.name, "Alice Liddell");
+    assert_eq!(alice.company.as_deref(), Some("Wonderland"));
+    assert_eq!(alice.location.as_deref(), Some("Oxford"));
+    assert_eq!(alice.kind, Some(UserKind::User));
+    // the fake makes accounts a day apart, a year before its first change
+    let created = Utc.with_ymd_and_hms(2019, 1, 2, 0, 0, 0).unwrap();
+    assert_eq!(alice.created_at, Some(created));
+
+    let bob = h.gh.user("bob").await.unwrap();
+    assert_eq!(bob.name, "bob");
+    assert_eq!((bob.company, bob.location), (None, None));
+    assert!(h.gh.user("carol").await.is_none());
+
+    let profile_calls = fake
+        .calls()
+        .into_iter()
+        .filter(|call| call.starts_with("user "))
+        .count();
+    assert_eq!(profile_calls, 2, "each profile is fetched once");
+}
+
+#[tokio::test]
+async fn bots_are_marked() {
+    let fake = FakeGithub::new();
+    fake.set_profile(
+        "dependabot[bot]",
+        FakeProfile {
+            bot: true,
+            ..FakeProfile::default()
+        },
+    );
+    fake.add_pr(REPO, "bump serde", "dependabot[bot]");
+    fake.add_pr(REPO, "rollup of 5 pull requests", "bors");
+    fake.add_pr(REPO, "fix the ICE", "alice");
+    let h = Harness::with_config(Arc::new(fake), |gh| gh.with_bots(&["bors"])).await;
+    sync_profiles(&h, 3).await;
+
+    assert!(h.gh.user("dependabot[bot]").await.unwrap().is_bot());
+    assert!(h.gh.user("bors").await.unwrap().is_bot());
+    assert!(!h.gh.user("alice").await.unwrap().is_bot());
+}
+
+#[tokio::test]
+async fn names_survive_later_listings() {
+    let fake = FakeGithub::new();
+    fake.set_profile(
+        "alice",
+        FakeProfile {
+            name: Some("Alice Liddell".to_string()),
+            ..FakeProfile::default()
+        },
+    );
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "alice");
+    let fake = Arc::new(fake);
+    let h = Harness::new(fake.clone()).await;
+    sync_profiles(&h, 1).await;
+
+    // listings don't include the name
+    fake.edit(REPO, ice, |issue| {
+        issue.title = "ICE in borrowck (again)".to_string()
+    });
+    let start = Instant::now();
+    while h.title(ice).await.as_deref() != Some("ICE in borrowck (again)") {
+        assert!(start.elapsed() < Duration::from_secs(20));
+        h.gh.clone().update().await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(h.gh.user("alice").await.unwrap().name, "Alice Liddell");
+}
+
+/// A [`FakeGithub`] whose first user was deleted, so their profile can't be fetched.
+struct Deleted {
+    fake: FakeGithub,
+}
+
+#[async_trait]
+impl Forge for Deleted {
+    async fn list_prs(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<PullRequest>, ForgeError> {
+        self.fake.list_prs(repo, list_type, page, url).await
+    }
+
+    async fn list_issues(
+        &self,
+        repo: &Repo,
+        list_type: ListType,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Issue>, ForgeError> {
+        self.fake.list_issues(repo, list_type, page, url).await
+    }
+
+    async fn list_comments(
+        &self,
+        repo: &Repo,
+        issue_number: u64,
+        since: Option<DateTime<Utc>>,
+        page: usize,
+        url: Option<&str>,
+    ) -> Result<ForgePage<Comment>, ForgeError> {
+        self.fake
+            .list_comments(repo, issue_number, since, page, url)
+            .await
+    }
+
+    async fn user_profile(&self, user_id: u64) -> Result<UserProfile, ForgeError> {
+        if user_id == 1 {
+            return Err(ForgeError::Other("not found".to_string()));
+        }
+        self.fake.user_profile(user_id).await
+    }
+}
+
+#[tokio::test]
+async fn failed_profiles_are_skipped() {
+    let fake = FakeGithub::new();
+    // users get their ids in order of first use, this makes alice user 1
+    fake.set_profile("alice", FakeProfile::default());
+    fake.add_issue(REPO, "ICE in borrowck", "alice");
+    fake.set_profile(
+        "bob",
+        FakeProfile {
+            name: Some("Bob".to_string()),
+            ..FakeProfile::default()
+        },
+    );
+    fake.add_issue(REPO, "slow compile", "bob");
+    let h = Harness::new(Arc::new(Deleted { fake })).await;
+    sync_profiles(&h, 2).await;
+
+    let alice = h.gh.user("alice").await.unwrap();
+    assert_eq!((alice.name.as_str(), alice.created_at), ("alice", None));
+    assert_eq!(h.gh.user("bob").await.unwrap().name, "Bob");
+}
+
+#[tokio::test]
+async fn deleted_accounts_become_the_ghost() {
+    let fake = FakeGithub::new();
+    let ice = fake.add_issue(REPO, "ICE in borrowck", "mallory");
+    fake.add_comment(REPO, ice, "mallory", "can reproduce");
+    let fix = fake.add_pr(REPO, "fix the ICE", "mallory");
+    fake.delete_user("mallory");
+    let h = Harness::new(Arc::new(fake)).await;
+    h.sync_until(|c| c.shared == 2 && c.prs == 1 && c.comments == 1)
+        .await;
+
+    let repo: Repo = REPO.parse().unwrap();
+    let pr = h.gh.pull_request(&repo, fix).await.unwrap();
+    assert_eq!(pr.item.author, GHOST_LOGIN);
+    let thread = h.gh.comment_thread(&repo, ice).await.unwrap();
+    assert_eq!(thread[0].author, GHOST_LOGIN);
+    assert_eq!(h.gh.user(GHOST_LOGIN).await.unwrap().id, GHOST_ID);
+    assert!(h.gh.user("mallory").await.is_none());
+}